@@ -0,0 +1,81 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Stamps the binary with what `/api/version` reports. Docker builds without a
+// git checkout pass the commit as GIT_COMMIT.
+fn build_info() {
+    let git_commit = std::env::var("GIT_COMMIT").ok().or_else(|| {
+        Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .map(|commit| commit.trim().to_string())
+    });
+    println!(
+        "cargo:rustc-env=BUILD_GIT_COMMIT={}",
+        git_commit.unwrap_or_else(|| String::from("unknown"))
+    );
+
+    // SOURCE_DATE_EPOCH keeps reproducible builds reproducible.
+    let built_at = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default()
+        });
+    println!("cargo:rustc-env=BUILD_TIMESTAMP={}", built_at);
+
+    let mut features: Vec<String> = std::env::vars()
+        .filter_map(|(name, _)| {
+            name.strip_prefix("CARGO_FEATURE_")
+                .map(|feature| feature.to_lowercase().replace('_', "-"))
+        })
+        .filter(|feature| feature != "default")
+        .collect();
+    features.sort();
+    println!("cargo:rustc-env=BUILD_FEATURES={}", features.join(","));
+
+    // Once anything is listed, e.g. the proto with the grpc feature, cargo
+    // reruns this for nothing else, and the commit would go stale.
+    println!("cargo:rerun-if-env-changed=GIT_COMMIT");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    let head = Path::new(".git/HEAD");
+    if head.exists() {
+        println!("cargo:rerun-if-changed=.git/HEAD");
+        // A branch, new commits move it and not HEAD.
+        let reference = fs::read_to_string(head)
+            .ok()
+            .and_then(|head| head.strip_prefix("ref: ").map(|r| r.trim().to_string()));
+        if let Some(reference) = reference {
+            for path in [
+                format!(".git/{}", reference),
+                String::from(".git/packed-refs"),
+            ] {
+                if Path::new(&path).exists() {
+                    println!("cargo:rerun-if-changed={}", path);
+                }
+            }
+        }
+    }
+}
+
+fn main() {
+    build_info();
+
+    #[cfg(feature = "grpc")]
+    {
+        std::env::set_var(
+            "PROTOC",
+            protoc_bin_vendored::protoc_bin_path().expect("No vendored protoc for this platform"),
+        );
+        tonic_build::compile_protos("proto/keyword_notifier.proto")
+            .expect("Failed to compile protos");
+    }
+}
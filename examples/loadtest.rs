@@ -0,0 +1,170 @@
+// Seeds items and fires concurrent requests at a running keyword-notifier,
+// reporting latency percentiles per path.
+//
+//   DATABASE_URL=mysql://... cargo run --release --example loadtest -- \
+//     --base-url http://localhost:3000 --items 5000 --requests 1000 --concurrency 50
+//
+// Seeded items use the `loadtest` source and are removed again unless
+// `--keep` is passed.
+use mysql::prelude::*;
+use mysql::{params, Pool};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+const USAGE: &str = "Usage: loadtest [--base-url URL] [--items N] [--requests N] [--concurrency N] [--paths /,/api/shareables] [--keep]";
+
+struct Options {
+    base_url: String,
+    items: usize,
+    requests: usize,
+    concurrency: usize,
+    paths: Vec<String>,
+    keep: bool,
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        base_url: String::from("http://localhost:3000"),
+        items: 1000,
+        requests: 500,
+        concurrency: 20,
+        paths: vec![String::from("/"), String::from("/api/shareables")],
+        keep: false,
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--keep" {
+            options.keep = true;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", arg))?;
+        let number = || {
+            value
+                .parse::<usize>()
+                .map_err(|e| format!("{}: {}", arg, e))
+        };
+        match arg.as_str() {
+            "--base-url" => options.base_url = value.trim_end_matches('/').to_string(),
+            "--items" => options.items = number()?,
+            "--requests" => options.requests = number()?,
+            "--concurrency" => options.concurrency = number()?.max(1),
+            "--paths" => options.paths = value.split(',').map(String::from).collect(),
+            _ => return Err(format!("Unknown option {}", arg)),
+        }
+    }
+    Ok(options)
+}
+
+fn seed(pool: &Pool, items: usize) -> mysql::Result<()> {
+    let mut conn = pool.get_conn()?;
+    conn.exec_batch(
+        r"INSERT IGNORE INTO shareables (id, title, url, date, source)
+          VALUES (:id, :title, :url, :date, :source)",
+        (0..items).map(|i| {
+            params! {
+                "id" => format!("loadtest-{}", i),
+                "title" => format!("Load test item {} about the keyword", i),
+                "url" => format!("https://example.com/loadtest/{}", i),
+                "date" => format!("2022-06-01T{:02}:{:02}:00Z", (i / 60) % 24, i % 60),
+                "source" => "loadtest",
+            }
+        }),
+    )
+}
+
+fn clean_up(pool: &Pool) -> mysql::Result<()> {
+    pool.get_conn()?
+        .query_drop("DELETE FROM shareables WHERE source = 'loadtest'")
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let index = ((sorted.len() as f64 * p).ceil() as usize).clamp(1, sorted.len()) - 1;
+    sorted[index]
+}
+
+async fn run(options: &Options, path: &str) {
+    let client = reqwest::Client::new();
+    let permits = Arc::new(Semaphore::new(options.concurrency));
+    let url = format!("{}{}", options.base_url, path);
+    let started = Instant::now();
+
+    let mut tasks = vec![];
+    for _ in 0..options.requests {
+        let permit = permits.clone().acquire_owned().await.unwrap();
+        let client = client.clone();
+        let url = url.clone();
+        tasks.push(tokio::spawn(async move {
+            let sent = Instant::now();
+            let ok = match client.get(url).send().await {
+                Ok(resp) => resp.status().is_success() && resp.bytes().await.is_ok(),
+                Err(_) => false,
+            };
+            drop(permit);
+            (sent.elapsed(), ok)
+        }));
+    }
+
+    let mut latencies = vec![];
+    let mut errors = 0;
+    for task in tasks {
+        match task.await {
+            Ok((latency, true)) => latencies.push(latency),
+            _ => errors += 1,
+        }
+    }
+    let total = started.elapsed();
+    latencies.sort();
+
+    println!(
+        "{:<20} {:>6} ok {:>4} err {:>8.1} req/s  p50 {:>6}ms  p90 {:>6}ms  p99 {:>6}ms  max {:>6}ms",
+        path,
+        latencies.len(),
+        errors,
+        options.requests as f64 / total.as_secs_f64(),
+        percentile(&latencies, 0.5).as_millis(),
+        percentile(&latencies, 0.9).as_millis(),
+        percentile(&latencies, 0.99).as_millis(),
+        percentile(&latencies, 1.0).as_millis(),
+    );
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match parse_options(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let pool = std::env::var("DATABASE_URL")
+        .ok()
+        .map(|url| Pool::new(url.as_str()).expect("Could not connect to DATABASE_URL"));
+    match &pool {
+        Some(pool) => {
+            seed(pool, options.items).expect("Could not seed items");
+            println!("Seeded {} items", options.items);
+        }
+        None => println!("DATABASE_URL is not set, running against the existing items"),
+    }
+
+    println!(
+        "{} requests per path, {} at a time, against {}",
+        options.requests, options.concurrency, options.base_url
+    );
+    for path in options.paths.iter() {
+        run(&options, path).await;
+    }
+
+    if let (Some(pool), false) = (&pool, options.keep) {
+        clean_up(pool).expect("Could not remove seeded items");
+    }
+}
@@ -0,0 +1,31 @@
+use chrono::{SecondsFormat, TimeZone, Utc};
+use serde::Serialize;
+
+// Set by build.rs.
+const GIT_COMMIT: &str = env!("BUILD_GIT_COMMIT");
+const BUILD_TIMESTAMP: &str = env!("BUILD_TIMESTAMP");
+const FEATURES: &str = env!("BUILD_FEATURES");
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub git_commit: &'static str,
+    pub built_at: Option<String>,
+    pub features: Vec<&'static str>,
+}
+
+pub fn get() -> BuildInfo {
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_commit: GIT_COMMIT,
+        built_at: BUILD_TIMESTAMP
+            .parse()
+            .ok()
+            .and_then(|secs| Utc.timestamp_opt(secs, 0).single())
+            .map(|at| at.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        features: FEATURES
+            .split(',')
+            .filter(|feature| !feature.is_empty())
+            .collect(),
+    }
+}
@@ -0,0 +1,98 @@
+use std::sync::OnceLock;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::warn;
+
+use crate::fetcher::base::Shareable;
+use crate::fetcher::error::ErrorKind;
+
+// Events a subscriber can fall behind by before it misses some.
+const CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    // Items stored for the first time, by a fetch run, a listener or the
+    // spill drainer.
+    ItemsInserted {
+        fetcher: String,
+        shareables: Vec<Shareable>,
+    },
+    // A fetch run failed or its items could not be stored.
+    SourceFailed {
+        fetcher: &'static str,
+        kind: ErrorKind,
+    },
+}
+
+// Subsystems that react to what happened elsewhere subscribe here instead of
+// being called directly. Publishing never blocks, a subscriber that falls
+// too far behind loses the oldest events.
+static BUS: OnceLock<broadcast::Sender<Event>> = OnceLock::new();
+
+fn sender() -> &'static broadcast::Sender<Event> {
+    BUS.get_or_init(|| broadcast::channel(CAPACITY).0)
+}
+
+pub fn publish(event: Event) {
+    // An error only means nobody is subscribed.
+    let _ = sender().send(event);
+}
+
+// Subscribe before the first event you care about can be published, events
+// are not replayed. The name is used in the warning about missed events.
+pub fn subscribe(name: &'static str) -> Subscription {
+    Subscription {
+        name,
+        receiver: sender().subscribe(),
+    }
+}
+
+pub struct Subscription {
+    name: &'static str,
+    receiver: broadcast::Receiver<Event>,
+}
+
+impl Subscription {
+    // The next event, None once the bus is gone.
+    pub async fn recv(&mut self) -> Option<Event> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) => return Some(event),
+                Err(RecvError::Lagged(missed)) => {
+                    warn!("{} fell behind and missed {} events", self.name, missed)
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn delivers_to_every_subscriber() {
+        let mut first = subscribe("first");
+        let mut second = subscribe("second");
+        publish(Event::SourceFailed {
+            fetcher: "bus-test",
+            kind: ErrorKind::Network,
+        });
+        // Other tests publish on the same bus.
+        for subscription in [&mut first, &mut second] {
+            loop {
+                match subscription.recv().await {
+                    Some(Event::SourceFailed {
+                        fetcher: "bus-test",
+                        kind,
+                    }) => {
+                        assert_eq!(kind, ErrorKind::Network);
+                        break;
+                    }
+                    Some(_) => {}
+                    None => panic!("bus closed"),
+                }
+            }
+        }
+    }
+}
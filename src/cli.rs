@@ -0,0 +1,109 @@
+use mysql::prelude::*;
+use mysql::*;
+
+const USAGE: &str = "Usage:
+  app query list [SOURCE] [LIMIT]   newest items, optionally of one source
+  app query search TEXT [LIMIT]     items whose title contains TEXT
+  app query tag ID TAG              add TAG to the item with ID
+  app query untag ID TAG            remove TAG from the item with ID";
+
+const SELECT_ITEMS: &str = r"SELECT s.id, s.title, s.date, s.source, GROUP_CONCAT(t.tag ORDER BY t.tag)
+  FROM shareables s LEFT JOIN shareable_tags t ON t.shareable_id = s.id";
+
+type ItemRow = (String, String, String, String, Option<String>);
+
+fn print_items(rows: Vec<ItemRow>) {
+    for (id, title, date, source, tags) in rows {
+        println!(
+            "{}\t{}\t{}\t{}\t{}",
+            date,
+            source,
+            id,
+            title.replace('\n', " "),
+            tags.unwrap_or_default()
+        );
+    }
+}
+
+fn parse_limit(limit: Option<&String>) -> Result<u32, String> {
+    limit
+        .map(|limit| {
+            limit
+                .parse()
+                .map_err(|_| format!("Invalid limit {}", limit))
+        })
+        .unwrap_or(Ok(50))
+}
+
+fn run(conn: &mut PooledConn, args: &[String]) -> Result<(), String> {
+    let arg = |i: usize| args.get(i).ok_or_else(|| String::from(USAGE));
+
+    match args.first().map(String::as_str) {
+        Some("list") => {
+            // A lone number is a limit, not a source.
+            let (source, limit) = match args.get(1) {
+                Some(first) if first.parse::<u32>().is_ok() => (None, parse_limit(Some(first))?),
+                source => (source.cloned(), parse_limit(args.get(2))?),
+            };
+            let rows = conn
+                .exec(
+                    format!(
+                        "{} WHERE :source IS NULL OR s.source = :source GROUP BY s.id ORDER BY s.date DESC LIMIT {}",
+                        SELECT_ITEMS, limit
+                    ),
+                    params! { "source" => source },
+                )
+                .map_err(|e| format!("{}", e))?;
+            print_items(rows);
+        }
+        Some("search") => {
+            let text = arg(1)?;
+            let limit = parse_limit(args.get(2))?;
+            let rows = conn
+                .exec(
+                    format!(
+                        "{} WHERE s.title LIKE :pattern GROUP BY s.id ORDER BY s.date DESC LIMIT {}",
+                        SELECT_ITEMS, limit
+                    ),
+                    params! { "pattern" => format!("%{}%", text) },
+                )
+                .map_err(|e| format!("{}", e))?;
+            print_items(rows);
+        }
+        Some("tag") => {
+            conn.exec_drop(
+                "INSERT IGNORE INTO shareable_tags (shareable_id, tag) VALUES (:id, :tag)",
+                params! { "id" => arg(1)?, "tag" => arg(2)? },
+            )
+            .map_err(|e| format!("{}", e))?;
+            println!("Tagged {} with {}", arg(1)?, arg(2)?);
+        }
+        Some("untag") => {
+            conn.exec_drop(
+                "DELETE FROM shareable_tags WHERE shareable_id = :id AND tag = :tag",
+                params! { "id" => arg(1)?, "tag" => arg(2)? },
+            )
+            .map_err(|e| format!("{}", e))?;
+            println!("Removed {} from {}", arg(2)?, arg(1)?);
+        }
+        _ => return Err(String::from(USAGE)),
+    }
+
+    Ok(())
+}
+
+// Entry point for `app query ...`, returns the process exit code.
+pub fn query(pool: &Pool, args: &[String]) -> i32 {
+    let result = pool
+        .get_conn()
+        .map_err(|e| format!("{}", e))
+        .and_then(|mut conn| run(&mut conn, args));
+
+    match result {
+        Ok(_) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
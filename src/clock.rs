@@ -0,0 +1,66 @@
+use chrono::{DateTime, Utc};
+use std::fmt::Debug;
+
+// Wall-clock time for the fetch loops, quiet hours, digests and export
+// expiry. Waiting goes through tokio::time, which
+// tests can pause and advance; `TestClock` moves along with it.
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+#[derive(Debug)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+pub static SYSTEM: SystemClock = SystemClock;
+
+// Starts at a fixed time and advances with tokio's (paused) clock.
+#[cfg(test)]
+#[derive(Debug)]
+pub struct TestClock {
+    start: DateTime<Utc>,
+    started: tokio::time::Instant,
+}
+
+#[cfg(test)]
+impl TestClock {
+    pub fn new(start: DateTime<Utc>) -> TestClock {
+        TestClock {
+            start,
+            started: tokio::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Clock for TestClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.start + chrono::Duration::from_std(self.started.elapsed()).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_clock_follows_paused_time() {
+        let start = DateTime::parse_from_rfc3339("2022-06-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let clock = TestClock::new(start);
+        assert_eq!(clock.now(), start);
+
+        tokio::time::advance(Duration::from_secs(90)).await;
+        assert_eq!(clock.now(), start + chrono::Duration::seconds(90));
+
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        assert_eq!(clock.now(), start + chrono::Duration::seconds(120));
+    }
+}
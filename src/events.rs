@@ -0,0 +1,46 @@
+use mysql::params;
+use mysql::prelude::*;
+use serde::Serialize;
+
+use crate::fetcher::base::Shareable;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum EventType {
+    // First time we have seen the item.
+    Created,
+    // The upstream item changed, e.g. an edited title.
+    Updated,
+    // The upstream question or issue got an accepted answer or was closed.
+    Answered,
+    // We acted on the item: filed a ticket or queued a reshare.
+    Responded,
+}
+
+impl EventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventType::Created => "item.created",
+            EventType::Updated => "item.updated",
+            EventType::Answered => "item.answered",
+            EventType::Responded => "item.responded",
+        }
+    }
+}
+
+// Appends to the item event log. Consumers (webhooks) keep their own cursor
+// into it, which is what makes replaying missed deliveries possible.
+pub fn record(
+    conn: &mut mysql::PooledConn,
+    event_type: EventType,
+    shareable: &Shareable,
+) -> mysql::Result<()> {
+    conn.exec_drop(
+        r"INSERT INTO item_events (event_type, shareable_id, payload)
+      VALUES (:event_type, :shareable_id, :payload)",
+        params! {
+            "event_type" => event_type.as_str(),
+            "shareable_id" => shareable.id.clone(),
+            "payload" => serde_json::to_string(shareable).unwrap_or_default(),
+        },
+    )
+}
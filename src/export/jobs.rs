@@ -0,0 +1,480 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use mysql::prelude::*;
+use mysql::*;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::{self, JoinHandle};
+use tokio::time;
+use tracing::{error, info, warn};
+
+use crate::clock::Clock;
+use crate::fetcher::date::{parse_source_date, DateFormat};
+use crate::maintenance;
+use crate::shutdown;
+
+// Rows read per query, progress is saved after each batch.
+const BATCH_SIZE: usize = 1000;
+// Jobs that failed this often, including runs cut short by a restart, stay
+// failed.
+const MAX_ATTEMPTS: u32 = 3;
+// How often finished exports are checked for expired files.
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+const COLUMNS: &[&str] = &["id", "title", "url", "canonical_url", "source", "date"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    // Done or failed, and the file is deleted.
+    Expired,
+}
+
+impl Status {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Status::Queued => "queued",
+            Status::Running => "running",
+            Status::Done => "done",
+            Status::Failed => "failed",
+            Status::Expired => "expired",
+        }
+    }
+
+    fn from_str(status: &str) -> Option<Status> {
+        match status {
+            "queued" => Some(Status::Queued),
+            "running" => Some(Status::Running),
+            "done" => Some(Status::Done),
+            "failed" => Some(Status::Failed),
+            "expired" => Some(Status::Expired),
+            _ => None,
+        }
+    }
+}
+
+// What to export, every item when empty.
+#[derive(Debug, Default, Deserialize)]
+pub struct Request {
+    pub source: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub id: u64,
+    pub status: Status,
+    pub source: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    // Items looked at so far out of all items of the source, the date range
+    // is checked while reading.
+    pub scanned: u64,
+    pub total: Option<u64>,
+    pub rows: u64,
+    pub attempts: u32,
+    pub error: Option<String>,
+}
+
+impl Job {
+    pub fn progress(&self) -> f64 {
+        match (self.status, self.total) {
+            (Status::Done, _) => 1.0,
+            (_, Some(total)) if total > 0 => (self.scanned as f64 / total as f64).min(1.0),
+            _ => 0.0,
+        }
+    }
+
+    fn includes(&self, date: &str) -> bool {
+        if self.since.is_none() && self.until.is_none() {
+            return true;
+        }
+        match parse_source_date(date, DateFormat::Any) {
+            Some(date) => {
+                self.since.iter().all(|since| date >= *since)
+                    && self.until.iter().all(|until| date < *until)
+            }
+            None => false,
+        }
+    }
+}
+
+pub fn path(dir: &Path, id: u64) -> PathBuf {
+    dir.join(format!("export-{}.csv", id))
+}
+
+fn format_date(date: &Option<DateTime<Utc>>) -> Option<String> {
+    date.map(|date| date.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+fn parse_date(date: Option<String>) -> Option<DateTime<Utc>> {
+    date.and_then(|date| parse_source_date(&date, DateFormat::Rfc3339))
+}
+
+// Spreadsheets run cells starting with these as formulas, and titles come
+// from anyone on the internet.
+const FORMULA_PREFIXES: &[char] = &['=', '+', '-', '@', '\t', '\r'];
+
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(FORMULA_PREFIXES) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains(['"', ',', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+fn csv_row(fields: &[&str]) -> String {
+    let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+    format!("{}\r\n", fields.join(","))
+}
+
+pub fn create(pool: &Pool, request: &Request) -> mysql::Result<u64> {
+    let mut conn = pool.get_conn()?;
+    conn.exec_drop(
+        r"INSERT INTO export_jobs (status, source, date_from, date_to)
+          VALUES (:status, :source, :date_from, :date_to)",
+        params! {
+            "status" => Status::Queued.as_str(),
+            "source" => request.source.clone(),
+            "date_from" => format_date(&request.since),
+            "date_to" => format_date(&request.until),
+        },
+    )?;
+    Ok(conn.last_insert_id())
+}
+
+pub fn load(pool: &Pool, id: u64) -> mysql::Result<Option<Job>> {
+    let row: Option<Row> = pool.get_conn()?.exec_first(
+        r"SELECT id, status, source, date_from, date_to, scanned, total, written, attempts, error
+          FROM export_jobs WHERE id = :id",
+        params! { "id" => id },
+    )?;
+    Ok(row.map(|mut row| Job {
+        id: row.take(0).unwrap_or_default(),
+        status: row
+            .take::<String, _>(1)
+            .and_then(|status| Status::from_str(&status))
+            .unwrap_or(Status::Failed),
+        source: row.take(2).flatten(),
+        since: parse_date(row.take(3).flatten()),
+        until: parse_date(row.take(4).flatten()),
+        scanned: row.take(5).unwrap_or_default(),
+        total: row.take(6).flatten(),
+        rows: row.take(7).unwrap_or_default(),
+        attempts: row.take(8).unwrap_or_default(),
+        error: row.take(9).flatten(),
+    }))
+}
+
+// Jobs still running were cut short by a restart.
+fn requeue_interrupted(pool: &Pool) -> mysql::Result<()> {
+    let mut conn = pool.get_conn()?;
+    conn.exec_drop(
+        r"UPDATE export_jobs SET status = :failed, error = 'interrupted', finished_at = UTC_TIMESTAMP()
+          WHERE status = :running AND attempts >= :max_attempts",
+        params! {
+            "failed" => Status::Failed.as_str(),
+            "running" => Status::Running.as_str(),
+            "max_attempts" => MAX_ATTEMPTS,
+        },
+    )?;
+    conn.exec_drop(
+        "UPDATE export_jobs SET status = :queued WHERE status = :running",
+        params! {
+            "queued" => Status::Queued.as_str(),
+            "running" => Status::Running.as_str(),
+        },
+    )
+}
+
+fn claim_next(pool: &Pool) -> mysql::Result<Option<Job>> {
+    let mut conn = pool.get_conn()?;
+    let id: Option<u64> = conn.exec_first(
+        "SELECT id FROM export_jobs WHERE status = :queued ORDER BY id LIMIT 1",
+        params! { "queued" => Status::Queued.as_str() },
+    )?;
+    let id = match id {
+        Some(id) => id,
+        None => return Ok(None),
+    };
+    conn.exec_drop(
+        r"UPDATE export_jobs
+          SET status = :running, attempts = attempts + 1, scanned = 0, total = NULL, written = 0
+          WHERE id = :id",
+        params! { "id" => id, "running" => Status::Running.as_str() },
+    )?;
+    load(pool, id)
+}
+
+fn save_progress(pool: &Pool, job: &Job) -> mysql::Result<()> {
+    pool.get_conn()?.exec_drop(
+        "UPDATE export_jobs SET scanned = :scanned, total = :total, written = :rows WHERE id = :id",
+        params! {
+            "id" => job.id,
+            "scanned" => job.scanned,
+            "total" => job.total,
+            "rows" => job.rows,
+        },
+    )
+}
+
+fn finish(pool: &Pool, job: &Job, result: &Result<(), String>) -> mysql::Result<()> {
+    let status = match result {
+        Ok(()) => Status::Done,
+        Err(_) if job.attempts < MAX_ATTEMPTS => Status::Queued,
+        Err(_) => Status::Failed,
+    };
+    pool.get_conn()?.exec_drop(
+        r"UPDATE export_jobs SET status = :status, error = :error,
+            finished_at = IF(:status IN ('done', 'failed'), UTC_TIMESTAMP(), NULL)
+          WHERE id = :id",
+        params! {
+            "id" => job.id,
+            "status" => status.as_str(),
+            "error" => result.as_ref().err().cloned(),
+        },
+    )
+}
+
+// Exports that finished before this are expired, as a MySQL DATETIME.
+fn expired_before(clock: &dyn Clock, ttl: Duration) -> String {
+    match chrono::Duration::from_std(ttl)
+        .ok()
+        .and_then(|ttl| clock.now().checked_sub_signed(ttl))
+    {
+        Some(cutoff) => cutoff.format("%Y-%m-%d %H:%M:%S").to_string(),
+        // The earliest DATETIME, nothing expires.
+        None => String::from("1000-01-01 00:00:00"),
+    }
+}
+
+// Deletes the files of exports finished longer than `ttl` ago, and what a
+// failed run left behind.
+fn expire(pool: &Pool, dir: &Path, ttl: Duration, clock: &dyn Clock) -> mysql::Result<()> {
+    let mut conn = pool.get_conn()?;
+    let ids: Vec<u64> = conn.exec(
+        r"SELECT id FROM export_jobs
+          WHERE status IN (:done, :failed) AND finished_at < :cutoff",
+        params! {
+            "done" => Status::Done.as_str(),
+            "failed" => Status::Failed.as_str(),
+            "cutoff" => expired_before(clock, ttl),
+        },
+    )?;
+    for id in ids {
+        let target = path(dir, id);
+        for file in [target.with_extension("csv.part"), target] {
+            match fs::remove_file(&file) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    // Tried again on the next check.
+                    warn!("Could not delete expired export {}: {}", file.display(), e);
+                    return Ok(());
+                }
+            }
+        }
+        conn.exec_drop(
+            "UPDATE export_jobs SET status = :expired WHERE id = :id",
+            params! { "id" => id, "expired" => Status::Expired.as_str() },
+        )?;
+        info!("Deleted expired export {}", id);
+    }
+    Ok(())
+}
+
+// Writes the CSV next to its final path and only moves it there once it is
+// complete, so a download never sees half a file.
+fn run(pool: &Pool, dir: &Path, job: &mut Job) -> Result<(), String> {
+    let mut conn = pool.get_conn().map_err(|e| format!("{}", e))?;
+    job.total = conn
+        .exec_first(
+            "SELECT COUNT(*) FROM shareables WHERE (:source IS NULL OR source = :source)",
+            params! { "source" => job.source.clone() },
+        )
+        .map_err(|e| format!("{}", e))?;
+
+    let target = path(dir, job.id);
+    let partial = target.with_extension("csv.part");
+    let file = File::create(&partial).map_err(|e| format!("{}: {}", partial.display(), e))?;
+    let mut writer = BufWriter::new(file);
+    let write_error = |e: std::io::Error| format!("{}: {}", partial.display(), e);
+    writer
+        .write_all(csv_row(COLUMNS).as_bytes())
+        .map_err(write_error)?;
+
+    let mut after = String::new();
+    loop {
+        if shutdown::is_requested() {
+            return Err(String::from("interrupted by shutdown"));
+        }
+        let batch: Vec<(String, String, String, Option<String>, String, String)> = conn
+            .exec(
+                r"SELECT id, title, url, canonical_url, source, date FROM shareables
+                  WHERE id > :after AND (:source IS NULL OR source = :source)
+                  ORDER BY id LIMIT :limit",
+                params! {
+                    "after" => after.clone(),
+                    "source" => job.source.clone(),
+                    "limit" => BATCH_SIZE,
+                },
+            )
+            .map_err(|e| format!("{}", e))?;
+        let last_batch = batch.len() < BATCH_SIZE;
+
+        for (id, title, url, canonical_url, source, date) in batch {
+            job.scanned += 1;
+            if job.includes(&date) {
+                let canonical_url = canonical_url.unwrap_or_default();
+                writer
+                    .write_all(
+                        csv_row(&[&id, &title, &url, &canonical_url, &source, &date]).as_bytes(),
+                    )
+                    .map_err(write_error)?;
+                job.rows += 1;
+            }
+            after = id;
+        }
+        save_progress(pool, job).map_err(|e| format!("{}", e))?;
+        if last_batch {
+            break;
+        }
+    }
+
+    writer.flush().map_err(write_error)?;
+    drop(writer);
+    fs::rename(&partial, &target).map_err(|e| format!("{}: {}", target.display(), e))
+}
+
+// Works through queued export jobs one at a time, writing them to
+// EXPORT_DIR, and deletes their files `ttl` after they finished.
+pub fn spawn_worker(
+    pool: Arc<Pool>,
+    dir: PathBuf,
+    ttl: Duration,
+    clock: &'static dyn Clock,
+) -> JoinHandle<()> {
+    task::spawn(async move {
+        if let Err(e) = requeue_interrupted(&pool) {
+            error!("Could not requeue interrupted exports: {}", e);
+        }
+        let mut interval = time::interval(Duration::from_secs(5));
+        let mut last_expiry: Option<time::Instant> = None;
+        while shutdown::tick(&mut interval).await {
+            if maintenance::is_active() {
+                continue;
+            }
+            if last_expiry.is_none_or(|at| at.elapsed() >= EXPIRY_CHECK_INTERVAL) {
+                last_expiry = Some(time::Instant::now());
+                if let Err(e) = expire(&pool, &dir, ttl, clock) {
+                    error!("Could not delete expired exports: {}", e);
+                }
+            }
+            let mut job = match claim_next(&pool) {
+                Ok(Some(job)) => job,
+                Ok(None) => continue,
+                Err(e) => {
+                    error!("Error loading export jobs: {}", e);
+                    continue;
+                }
+            };
+            info!("Starting export {} (attempt {})", job.id, job.attempts);
+            let (pool, dir) = (pool.clone(), dir.clone());
+            let finished = task::spawn_blocking(move || {
+                let result = run(&pool, &dir, &mut job);
+                match &result {
+                    Ok(()) => info!("Export {} wrote {} rows", job.id, job.rows),
+                    Err(e) => warn!("Export {} failed: {}", job.id, e),
+                }
+                // Left running on shutdown, it is picked up again on start.
+                if !shutdown::is_requested() {
+                    if let Err(e) = finish(&pool, &job, &result) {
+                        error!("Error saving export {}: {}", job.id, e);
+                    }
+                }
+            })
+            .await;
+            if let Err(e) = finished {
+                error!("Export worker panicked: {}", e);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::TestClock;
+
+    #[test]
+    fn quotes_csv_fields() {
+        assert_eq!(
+            csv_row(&["a", "b c", "say \"hi\"", "1,2", "line\nbreak"]),
+            "a,b c,\"say \"\"hi\"\"\",\"1,2\",\"line\nbreak\"\r\n"
+        );
+    }
+
+    #[test]
+    fn neutralizes_formulas() {
+        assert_eq!(
+            csv_row(&[
+                "=HYPERLINK(\"https://evil.example.com\")",
+                "+1",
+                "-2",
+                "@SUM(A1)"
+            ]),
+            "\"'=HYPERLINK(\"\"https://evil.example.com\"\")\",'+1,'-2,'@SUM(A1)\r\n"
+        );
+        assert_eq!(csv_field("\tcmd"), "'\tcmd");
+        assert_eq!(csv_field("\r=1"), "\"'\r=1\"");
+        assert_eq!(csv_field("cdktf = great"), "cdktf = great");
+    }
+
+    #[test]
+    fn filters_by_date() {
+        let job = Job {
+            id: 1,
+            status: Status::Running,
+            source: None,
+            since: parse_source_date("2022-01-01T00:00:00Z", DateFormat::Rfc3339),
+            until: parse_source_date("2023-01-01T00:00:00Z", DateFormat::Rfc3339),
+            scanned: 0,
+            total: None,
+            rows: 0,
+            attempts: 1,
+            error: None,
+        };
+        assert!(job.includes("2022-06-01T12:00:00Z"));
+        assert!(job.includes("2022-06-01UTC"));
+        assert!(!job.includes("2023-01-01T00:00:00Z"));
+        assert!(!job.includes("2021-12-31T23:59:59Z"));
+        assert!(!job.includes("not a date"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn expires_by_the_clock() {
+        let clock = TestClock::new("2022-06-01T12:00:00Z".parse().unwrap());
+        let day = Duration::from_secs(24 * 60 * 60);
+        assert_eq!(expired_before(&clock, day), "2022-05-31 12:00:00");
+
+        time::advance(Duration::from_secs(60 * 60)).await;
+        assert_eq!(expired_before(&clock, day), "2022-05-31 13:00:00");
+        assert_eq!(
+            expired_before(&clock, Duration::from_secs(u64::MAX)),
+            "1000-01-01 00:00:00"
+        );
+    }
+}
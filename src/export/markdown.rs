@@ -0,0 +1,87 @@
+use std::collections::HashSet;
+use std::io::{Cursor, Write};
+use std::path::Path;
+use zip::write::FileOptions;
+
+use crate::fetcher::base::Shareable;
+
+fn yaml_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// One note per item, with front-matter Obsidian and friends can query.
+pub fn note(shareable: &Shareable) -> String {
+    format!(
+        "---\ntitle: {}\nsource: {}\ndate: {}\nurl: {}\ntags:\n  - keyword-notifier\n  - {}\n---\n\n{}\n\n[Open on {}]({})\n",
+        yaml_string(&shareable.display_title()),
+        shareable.source,
+        yaml_string(&shareable.date),
+        yaml_string(&shareable.url),
+        shareable.source,
+        shareable.display_title(),
+        shareable.source,
+        shareable.url
+    )
+}
+
+// All items in a single note, newest first.
+pub fn digest(shareables: &[Shareable]) -> String {
+    let mut out = String::from("---\ntags:\n  - keyword-notifier\n---\n\n# Mentions\n\n");
+    for shareable in shareables {
+        out.push_str(&format!(
+            "- [{}]({}) ({}, {})\n",
+            shareable.display_title().replace(['[', ']'], ""),
+            shareable.url,
+            shareable.source,
+            shareable.date
+        ));
+    }
+    out
+}
+
+// Stable, filesystem-safe file names; duplicates get a numeric suffix.
+fn file_names(shareables: &[Shareable]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    shareables
+        .iter()
+        .map(|shareable| {
+            let slug = shareable
+                .display_title()
+                .chars()
+                .map(|c| if c.is_alphanumeric() { c } else { '-' })
+                .collect::<String>()
+                .split('-')
+                .filter(|part| !part.is_empty())
+                .take(10)
+                .collect::<Vec<&str>>()
+                .join("-");
+            let base = format!("{}-{}", shareable.source, slug);
+
+            let mut name = format!("{}.md", base);
+            let mut n = 2;
+            while !seen.insert(name.clone()) {
+                name = format!("{}-{}.md", base, n);
+                n += 1;
+            }
+            name
+        })
+        .collect()
+}
+
+pub fn zip(shareables: &[Shareable]) -> zip::result::ZipResult<Vec<u8>> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for (shareable, name) in shareables.iter().zip(file_names(shareables)) {
+        writer.start_file(name, FileOptions::default())?;
+        writer.write_all(note(shareable).as_bytes())?;
+    }
+    Ok(writer.finish()?.into_inner())
+}
+
+// Writes one note per item into `dir`, overwriting notes from earlier syncs.
+pub fn sync_to_dir(dir: &Path, shareables: &[Shareable]) -> std::io::Result<usize> {
+    std::fs::create_dir_all(dir)?;
+    for (shareable, name) in shareables.iter().zip(file_names(shareables)) {
+        std::fs::write(dir.join(name), note(shareable))?;
+    }
+    Ok(shareables.len())
+}
@@ -0,0 +1,40 @@
+pub mod jobs;
+pub mod markdown;
+
+use mysql::prelude::*;
+use mysql::*;
+
+use crate::fetcher::base::Shareable;
+
+// Loads the given shareables, or all starred ones when no ids are given.
+pub fn load_selection(pool: &Pool, ids: &[String]) -> mysql::Result<Vec<Shareable>> {
+    let mut conn = pool.get_conn()?;
+    let to_shareable = |(id, title, url, date, source)| Shareable {
+        id,
+        title,
+        date,
+        url,
+        source,
+        canonical_url: None,
+    };
+
+    let mut shareables = if ids.is_empty() {
+        conn.query_map(
+            "SELECT id, title, url, date, source FROM shareables WHERE starred_at IS NOT NULL",
+            to_shareable,
+        )?
+    } else {
+        let placeholders = vec!["?"; ids.len()].join(", ");
+        conn.exec_map(
+            format!(
+                "SELECT id, title, url, date, source FROM shareables WHERE id IN ({})",
+                placeholders
+            ),
+            ids.to_vec(),
+            to_shareable,
+        )?
+    };
+
+    shareables.sort_by(|a, b| b.cmp(a));
+    Ok(shareables)
+}
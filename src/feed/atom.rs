@@ -0,0 +1,58 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use super::{date, Feed};
+use crate::fetcher::base::Shareable;
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn timestamp(date: DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+// Item ids are stable and unique, which is all Atom asks of an entry id.
+fn entry(shareable: &Shareable, updated: DateTime<Utc>) -> String {
+    format!(
+        r#"<entry><id>urn:keyword-notifier:{}</id><title>{}</title><link href="{}"/><updated>{}</updated><category term="{}"/></entry>"#,
+        escape(&shareable.id),
+        escape(&shareable.display_title()),
+        escape(shareable.link()),
+        timestamp(date(shareable).unwrap_or(updated)),
+        escape(&shareable.source),
+    )
+}
+
+// Atom 1.0, items without a readable date count as updated with the feed.
+pub fn render(feed: &Feed) -> String {
+    let updated = feed.updated();
+    let entries: String = feed
+        .shareables
+        .iter()
+        .map(|shareable| entry(shareable, updated))
+        .collect();
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?><feed xmlns="http://www.w3.org/2005/Atom"><id>{}</id><title>{}</title><subtitle>{}</subtitle><link rel="self" href="{}"/><link rel="alternate" href="{}"/><updated>{}</updated><author><name>keyword-notifier</name></author>{}</feed>"#,
+        escape(&feed.feed_url),
+        escape(&feed.title()),
+        escape(&feed.description()),
+        escape(&feed.feed_url),
+        escape(&feed.home_url),
+        timestamp(updated),
+        entries,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{sample, sample_shareables};
+    use super::*;
+
+    #[test]
+    fn renders_atom() {
+        insta::assert_snapshot!(render(&sample(&sample_shareables())));
+    }
+}
@@ -0,0 +1,44 @@
+use chrono::SecondsFormat;
+use serde_json::json;
+
+use super::{date, Feed};
+use crate::fetcher::base::Shareable;
+
+fn item(shareable: &Shareable) -> serde_json::Value {
+    json!({
+        "id": shareable.id,
+        "url": shareable.link(),
+        "title": shareable.display_title(),
+        "content_text": shareable.display_title(),
+        "date_published": date(shareable)
+            .map(|date| date.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        "tags": [shareable.source],
+    })
+}
+
+// JSON Feed 1.1
+pub fn render(feed: &Feed) -> String {
+    let items: Vec<serde_json::Value> = feed.shareables.iter().map(item).collect();
+    json!({
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": feed.title(),
+        "description": feed.description(),
+        "home_page_url": feed.home_url,
+        "feed_url": feed.feed_url,
+        "items": items,
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{sample, sample_shareables};
+    use super::*;
+
+    #[test]
+    fn renders_json_feed() {
+        let rendered: serde_json::Value =
+            serde_json::from_str(&render(&sample(&sample_shareables()))).unwrap();
+        insta::assert_snapshot!(serde_json::to_string_pretty(&rendered).unwrap());
+    }
+}
@@ -0,0 +1,116 @@
+mod atom;
+mod json;
+mod rss;
+
+use chrono::{DateTime, Utc};
+
+use crate::fetcher::base::Shareable;
+use crate::fetcher::date::{parse_source_date, DateFormat};
+
+// Readers only look at the newest items.
+pub const LENGTH: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Rss,
+    Atom,
+    Json,
+}
+
+impl Format {
+    // `xml` for RSS, as in `/feed.xml`.
+    pub fn from_extension(extension: &str) -> Option<Format> {
+        match extension {
+            "xml" => Some(Format::Rss),
+            "atom" => Some(Format::Atom),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Rss => "application/rss+xml; charset=utf-8",
+            Format::Atom => "application/atom+xml; charset=utf-8",
+            Format::Json => "application/feed+json; charset=utf-8",
+        }
+    }
+}
+
+// The newest items of all sources or one, in whichever format a reader
+// wants.
+pub struct Feed<'a> {
+    pub keyword: &'a str,
+    pub source: Option<&'a str>,
+    // Absolute URLs of the dashboard and of the feed itself.
+    pub home_url: String,
+    pub feed_url: String,
+    pub shareables: &'a [Shareable],
+}
+
+impl Feed<'_> {
+    fn title(&self) -> String {
+        match self.source {
+            Some(source) => format!("{} mentions on {}", self.keyword, source),
+            None => format!("{} mentions", self.keyword),
+        }
+    }
+
+    fn description(&self) -> String {
+        format!("New {} collected by keyword-notifier", self.title())
+    }
+
+    // Of the newest item, now for an empty feed.
+    fn updated(&self) -> DateTime<Utc> {
+        self.shareables
+            .iter()
+            .filter_map(date)
+            .max()
+            .unwrap_or_else(Utc::now)
+    }
+
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Rss => rss::render(self),
+            Format::Atom => atom::render(self),
+            Format::Json => json::render(self),
+        }
+    }
+}
+
+fn date(shareable: &Shareable) -> Option<DateTime<Utc>> {
+    parse_source_date(&shareable.date, DateFormat::Any)
+}
+
+#[cfg(test)]
+fn sample(shareables: &[Shareable]) -> Feed<'_> {
+    Feed {
+        keyword: "cdktf",
+        source: None,
+        home_url: String::from("https://news.example.com/"),
+        feed_url: String::from("https://news.example.com/feed.xml"),
+        shareables,
+    }
+}
+
+#[cfg(test)]
+fn sample_shareables() -> Vec<Shareable> {
+    vec![
+        Shareable {
+            id: String::from("stackoverflow-1"),
+            title: String::from(":question: How do I use <cdktf> & friends?"),
+            date: String::from("2022-06-01T12:00:00Z"),
+            url: String::from("https://stackoverflow.com/questions/1/cdktf?utm_source=feed"),
+            source: String::from("stackoverflow"),
+            canonical_url: Some(String::from("https://stackoverflow.com/questions/1/cdktf")),
+        },
+        Shareable {
+            id: String::from("twitter-2"),
+            title: String::from("cdktf is great"),
+            date: String::from("2022-05-31UTC"),
+            url: String::from("https://twitter.com/twitter/status/2"),
+            source: String::from("twitter"),
+            canonical_url: None,
+        },
+    ]
+}
@@ -0,0 +1,44 @@
+use ::rss::{Category, Channel, Guid, Item};
+
+use super::{date, Feed};
+use crate::fetcher::base::Shareable;
+
+fn item(shareable: &Shareable) -> Item {
+    Item {
+        title: Some(shareable.display_title()),
+        link: Some(shareable.link().to_string()),
+        guid: Some(Guid {
+            value: shareable.id.clone(),
+            permalink: false,
+        }),
+        pub_date: date(shareable).map(|date| date.to_rfc2822()),
+        categories: vec![Category {
+            name: shareable.source.clone(),
+            domain: None,
+        }],
+        ..Item::default()
+    }
+}
+
+// RSS 2.0
+pub fn render(feed: &Feed) -> String {
+    Channel {
+        title: feed.title(),
+        link: feed.home_url.clone(),
+        description: feed.description(),
+        items: feed.shareables.iter().map(item).collect(),
+        ..Channel::default()
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{sample, sample_shareables};
+    use super::*;
+
+    #[test]
+    fn renders_rss() {
+        insta::assert_snapshot!(render(&sample(&sample_shareables())));
+    }
+}
@@ -1,12 +1,73 @@
+use chrono::{DateTime, Utc};
+use mysql::params;
+use mysql::prelude::*;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio::{task, time};
+use tracing::{debug, debug_span, error, field, info, info_span, trace, warn, Instrument};
 
-#[derive(Deserialize, Debug, Clone, Serialize, Eq, PartialEq,)]
+use crate::build_info;
+use crate::bus::{self, Event};
+use crate::clock::Clock;
+use crate::events::{self, EventType};
+use crate::fetcher::error::{ErrorKind, FetchError};
+use crate::fetcher::fault::Faults;
+use crate::fetcher::spill;
+use crate::links;
+use crate::maintenance;
+use crate::shutdown;
+use crate::source::Source;
+use crate::text;
+
+#[derive(Deserialize, Debug, Clone, Serialize, Eq, PartialEq)]
 pub struct Shareable {
     pub id: String,
     pub title: String,
     pub date: String,
     pub url: String,
     pub source: String,
+    // `url` is what the source links to, e.g. a tweet permalink, this is the
+    // normalized and unshortened form used to find duplicates. Set before an
+    // item is stored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical_url: Option<String>,
+}
+
+impl Shareable {
+    // Fetchers encode state as slack-style markers, render them as emoji.
+    pub fn display_title(&self) -> String {
+        self.title
+            .replace(":question:", "❓")
+            .replace(":white_check_mark:", "✅")
+            .replace(":waiting-spin:", "🔄")
+    }
+
+    // Full titles are stored, lists show this when the title is too long.
+    pub fn short_title(&self, max_length: usize) -> Option<String> {
+        text::truncate(&self.title, max_length)
+    }
+
+    pub fn icon_path(&self) -> String {
+        Source(&self.source).icon_path()
+    }
+
+    // Where clicks go. Items stored before canonical URLs have none.
+    pub fn link(&self) -> &str {
+        self.canonical_url.as_deref().unwrap_or(&self.url)
+    }
+}
+
+impl Shareable {
+    fn size_in_bytes(&self) -> usize {
+        self.id.len() + self.title.len() + self.date.len() + self.url.len() + self.source.len()
+    }
 }
 
 impl PartialOrd for Shareable {
@@ -19,4 +80,1086 @@ impl Ord for Shareable {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         self.date.cmp(&other.date)
     }
-}
\ No newline at end of file
+}
+
+// Item ids are the kind of item (`github-release`, a Stack Exchange site, ...)
+// and a hash of its id on the platform, so they have a fixed size no matter
+// how long the platform id is. Use the most stable id the platform has, e.g.
+// a question id rather than the question URL.
+pub fn item_id(kind: &str, platform_id: impl Display) -> String {
+    let digest = Sha256::digest(format!("{}:{}", kind, platform_id).as_bytes());
+    let hash: String = digest[..16]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("{}-{}", kind, hash)
+}
+
+const LOG_SAMPLE_SIZE: usize = 3;
+
+// Responses and ID lists can be huge, so debug logs get the count and only
+// trace level gets a sample of the entries.
+pub fn log_sample<T: std::fmt::Debug>(what: &str, entries: &[T]) {
+    debug!("{}: {} entries", what, entries.len());
+    trace!(
+        "{}: first {} of {}: {:?}",
+        what,
+        entries.len().min(LOG_SAMPLE_SIZE),
+        entries.len(),
+        &entries[..entries.len().min(LOG_SAMPLE_SIZE)]
+    );
+}
+
+// Fetchers that merge several result lists (sites, pages, instances) see some
+// items twice. Keeps the first shareable of every id, sorted by id.
+pub fn dedup_shareables(shareables: &mut Vec<Shareable>) {
+    shareables.sort_by(|a, b| a.id.cmp(&b.id));
+    shareables.dedup_by(|a, b| a.id == b.id);
+}
+
+// The fetcher that stores a batch and the keyword it searched for, kept with
+// every item it creates.
+#[derive(Debug, Clone, Copy)]
+pub struct Origin<'a> {
+    pub fetcher: &'a str,
+    pub keyword: &'a str,
+}
+
+// Another source already has an item linking to the same page, e.g. an
+// article found through both a news feed and dev.to.
+fn linked_elsewhere(
+    conn: &mut mysql::PooledConn,
+    shareable: &Shareable,
+    canonical_url: &str,
+) -> mysql::Result<Option<String>> {
+    conn.exec_first(
+        r"SELECT id FROM shareables
+          WHERE canonical_url = :canonical_url AND source <> :source AND id <> :id
+          LIMIT 1",
+        params! {
+            "canonical_url" => canonical_url,
+            "source" => &shareable.source,
+            "id" => &shareable.id,
+        },
+    )
+}
+
+fn stored_title(conn: &mut mysql::PooledConn, id: &str) -> mysql::Result<Option<String>> {
+    conn.exec_first(
+        "SELECT title FROM shareables WHERE id = :id",
+        params! { "id" => id },
+    )
+}
+
+fn is_answered(title: &str) -> bool {
+    title.contains(":white_check_mark:")
+}
+
+// The event for storing `title` over the `previous` one, None if nothing
+// changed. Only an item turning answered is `Answered`, later edits of its
+// title are updates.
+fn event_type(previous: Option<&str>, title: &str) -> Option<EventType> {
+    match previous {
+        None => Some(EventType::Created),
+        Some(previous) if previous == title => None,
+        Some(previous) if !is_answered(previous) && is_answered(title) => Some(EventType::Answered),
+        Some(_) => Some(EventType::Updated),
+    }
+}
+
+// What items linking to the same page agree on. Spilled batches were never
+// canonicalized, their links are at least normalized.
+fn canonical_key(shareable: &Shareable) -> String {
+    match &shareable.canonical_url {
+        Some(canonical_url) => canonical_url.clone(),
+        None => links::normalize(&shareable.url),
+    }
+}
+
+// Stores the shareables and records an item event for every one that is new
+// or changed upstream. Returns the newly created ones. Items linking to the
+// same page as one from another source are skipped. New items get their
+// origin and the app version as provenance.
+pub fn insert_shareables(
+    conn: &mut mysql::PooledConn,
+    origin: Origin,
+    shareables: &[Shareable],
+) -> mysql::Result<Vec<Shareable>> {
+    let mut created = vec![];
+    let build = build_info::get();
+    let version = format!("{} ({})", build.version, build.git_commit);
+
+    for p in shareables {
+        let span = debug_span!(
+            "item",
+            id = %p.id,
+            source = %p.source,
+            event = field::Empty
+        );
+        let _entered = span.enter();
+        let canonical_url = canonical_key(p);
+        if let Some(duplicate) = linked_elsewhere(conn, p, &canonical_url)? {
+            debug!("Skipping, {} links to the same page", duplicate);
+            continue;
+        }
+        let previous = stored_title(conn, &p.id)?;
+        conn.exec_drop(
+            r"INSERT INTO shareables (id, title, url, date, source, canonical_url,
+            ingested_by, ingested_keyword, ingested_version, ingested_at)
+          VALUES (:id, :title, :url, :date, :source, :canonical_url,
+            :ingested_by, :ingested_keyword, :ingested_version, UTC_TIMESTAMP())
+          ON DUPLICATE KEY UPDATE title = VALUES(title)",
+            params! {
+                "id" => p.id.clone(),
+                "title" => p.title.clone(),
+                "url" => p.url.clone(),
+                "date" => p.date.clone(),
+                "source" => p.source.clone(),
+                "canonical_url" => canonical_url,
+                "ingested_by" => origin.fetcher,
+                "ingested_keyword" => origin.keyword,
+                "ingested_version" => &version
+            },
+        )?;
+
+        let event_type = match event_type(previous.as_deref(), &p.title) {
+            Some(event_type) => event_type,
+            None => continue,
+        };
+        span.record("event", event_type.as_str());
+        events::record(conn, event_type, p)?;
+        if event_type == EventType::Created {
+            created.push(p.clone());
+        }
+    }
+
+    Ok(created)
+}
+
+static SKIPPED_RUNS: AtomicU64 = AtomicU64::new(0);
+
+// Runs that did not happen because the previous one was still in progress.
+// Every fetcher runs in one loop, so a slow run delays the next ticks instead
+// of overlapping with them.
+pub fn skipped_runs() -> u64 {
+    SKIPPED_RUNS.load(Ordering::Relaxed)
+}
+
+fn skip_runs(name: &str, count: u64) {
+    SKIPPED_RUNS.fetch_add(count, Ordering::Relaxed);
+    warn!(
+        "Skipped {} run(s) of {} because the previous run was still in progress",
+        count, name
+    );
+}
+
+// Empty runs in a row before an adaptive schedule starts backing off.
+const QUIET_RUNS_BEFORE_BACKOFF: u32 = 3;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Schedule {
+    pub interval_in_sec: u64,
+    // Enables adaptive polling: quiet fetchers back off up to this interval.
+    pub max_interval_in_sec: Option<u64>,
+    pub limits: RunLimits,
+    pub clock: &'static dyn Clock,
+    // Dev-only failure injection, see `FAULT_<PREFIX>`.
+    pub faults: Option<Faults>,
+}
+
+pub const DEFAULT_MAX_ITEMS_PER_RUN: usize = 500;
+pub const DEFAULT_MAX_BYTES_PER_RUN: usize = 1024 * 1024;
+pub const DEFAULT_MAX_PAGES_PER_RUN: u32 = 10;
+
+// Caps on what a single run may collect, so hot keywords can't grow a run
+// without bounds. Fetchers that make more than one request track them with a
+// `Budget` and stop asking once it is used up.
+#[derive(Debug, Clone, Copy)]
+pub struct RunLimits {
+    pub max_items: usize,
+    pub max_bytes: usize,
+    // Requests, i.e. result pages, sites, instances, ...
+    pub max_pages: u32,
+}
+
+impl Default for RunLimits {
+    fn default() -> Self {
+        RunLimits {
+            max_items: DEFAULT_MAX_ITEMS_PER_RUN,
+            max_bytes: DEFAULT_MAX_BYTES_PER_RUN,
+            max_pages: DEFAULT_MAX_PAGES_PER_RUN,
+        }
+    }
+}
+
+impl RunLimits {
+    // An equal share for each of `parts`, e.g. the sites a fetcher searches,
+    // so one busy part can't use up the whole run. Every part gets a request.
+    pub fn split(&self, parts: usize) -> RunLimits {
+        let parts = parts.max(1);
+        RunLimits {
+            max_items: (self.max_items / parts).max(1),
+            max_bytes: self.max_bytes / parts,
+            max_pages: (self.max_pages / parts as u32).max(1),
+        }
+    }
+
+    // What the runner still enforces on the whole result, for fetchers that
+    // make a single request. Keeps the newest shareables that fit, returns
+    // how many were dropped.
+    fn apply(&self, shareables: &mut Vec<Shareable>) -> usize {
+        let before = shareables.len();
+        shareables.sort_by(|a, b| b.date.cmp(&a.date));
+        shareables.truncate(self.max_items);
+        let mut bytes = 0;
+        let fitting = shareables
+            .iter()
+            .take_while(|shareable| {
+                bytes += shareable.size_in_bytes();
+                bytes <= self.max_bytes
+            })
+            .count();
+        shareables.truncate(fitting);
+        before - shareables.len()
+    }
+}
+
+// What is left of the run limits while a fetch collects items.
+#[derive(Debug, Clone)]
+pub struct Budget {
+    limits: RunLimits,
+    pages: u32,
+    items: usize,
+    bytes: usize,
+    exhausted: bool,
+}
+
+impl Budget {
+    pub fn new(limits: RunLimits) -> Self {
+        Budget {
+            limits,
+            pages: 0,
+            items: 0,
+            bytes: 0,
+            exhausted: false,
+        }
+    }
+
+    // Counts a request, false once the run made as many as it may. The
+    // fetcher stops there.
+    pub fn next_page(&mut self) -> bool {
+        if self.pages >= self.limits.max_pages {
+            self.run_out("page");
+            return false;
+        }
+        self.pages += 1;
+        true
+    }
+
+    // Moves the shareables over in the given order until the item or byte
+    // limit is reached. False if not all of them fit.
+    pub fn keep(
+        &mut self,
+        kept: &mut Vec<Shareable>,
+        shareables: impl IntoIterator<Item = Shareable>,
+    ) -> bool {
+        for shareable in shareables {
+            let size = shareable.size_in_bytes();
+            if self.items >= self.limits.max_items || self.bytes + size > self.limits.max_bytes {
+                self.run_out("item or size");
+                return false;
+            }
+            self.items += 1;
+            self.bytes += size;
+            kept.push(shareable);
+        }
+        true
+    }
+
+    // Whether anything was left out, the cursor then may only move as far as
+    // the items that were kept.
+    pub fn exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    fn run_out(&mut self, limit: &str) {
+        if !self.exhausted {
+            // Runs are instrumented with the fetcher.
+            warn!(
+                "Run hit its {} limit, the rest is left for later runs",
+                limit
+            );
+        }
+        self.exhausted = true;
+    }
+}
+
+// Cursor of sources that list the newest items first and can be asked for
+// items between two positions (tweet ids, timestamps, ...). A run that hit
+// its limits before reaching the previous cursor leaves a gap, which the next
+// runs fill before they ask for newer items again. Stored as `<after>` or
+// `<after>..<before>..<newest>` while there is a gap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewestFirst<T> {
+    pub after: Option<T>,
+    // Set while filling a gap: the oldest item kept so far, and the newest
+    // position covered before the gap opened.
+    pub before: Option<T>,
+    newest: Option<T>,
+}
+
+impl<T: Clone + Ord + Display + std::str::FromStr> NewestFirst<T> {
+    pub fn parse(cursor: Option<&str>) -> Self {
+        let mut parts = cursor.unwrap_or_default().split("..");
+        let mut next = || {
+            parts
+                .next()
+                .filter(|part| !part.is_empty())
+                .and_then(|part| part.parse::<T>().ok())
+        };
+        let (after, before, newest) = (next(), next(), next());
+        match (after, before, newest) {
+            (after, Some(before), Some(newest)) => NewestFirst {
+                after,
+                before: Some(before),
+                newest: Some(newest),
+            },
+            (after, _, _) => NewestFirst {
+                after,
+                before: None,
+                newest: None,
+            },
+        }
+    }
+
+    // The cursor after a run that covered everything up to `covered` (e.g.
+    // its start time, or the newest id it saw) and kept items back to
+    // `oldest_kept`. `complete` is false if it stopped before reaching `after`.
+    pub fn advance(
+        self,
+        covered: Option<T>,
+        oldest_kept: Option<T>,
+        complete: bool,
+    ) -> Option<String> {
+        let newest = match &self.newest {
+            // Filling a gap, what came after it is covered already.
+            Some(newest) => Some(newest.clone()),
+            None => covered.max(self.after.clone()),
+        };
+        match (complete, &self.after, oldest_kept) {
+            (false, Some(after), Some(oldest_kept)) => {
+                let newest = newest.unwrap_or_else(|| oldest_kept.clone());
+                Some(format!("{}..{}..{}", after, oldest_kept, newest))
+            }
+            // Nothing kept, the next run tries the same again.
+            (false, Some(_), None) => Some(self.to_string()),
+            // Without a previous cursor there is no gap to fill, older items
+            // were never asked for.
+            _ => newest.map(|newest| newest.to_string()),
+        }
+    }
+}
+
+impl<T: Display> Display for NewestFirst<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.after, &self.before, &self.newest) {
+            (Some(after), Some(before), Some(newest)) => {
+                write!(f, "{}..{}..{}", after, before, newest)
+            }
+            (Some(after), _, _) => write!(f, "{}", after),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Schedule {
+    // Doubles the interval after every few runs without new items, and goes
+    // straight back to the base interval once something new shows up.
+    fn next_interval(&self, current: u64, quiet_runs: u32) -> u64 {
+        let base = self.interval_in_sec.max(1);
+        match self.max_interval_in_sec {
+            Some(max) if quiet_runs >= QUIET_RUNS_BEFORE_BACKOFF => {
+                current.saturating_mul(2).min(max.max(base))
+            }
+            Some(_) if quiet_runs > 0 => current,
+            _ => base,
+        }
+    }
+}
+
+// Set when the last successful run is so far back that the usual window
+// would miss the items in between.
+fn catch_up_since(
+    last_success: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    interval_in_sec: u64,
+) -> Option<DateTime<Utc>> {
+    last_success.filter(|at| (now - *at).num_seconds() > 2 * interval_in_sec as i64)
+}
+
+// Ticks that passed while the run was still going are skipped, the next run
+// starts on the following one.
+fn next_tick(started: Instant, now: Instant, interval_in_sec: u64) -> (u64, Instant) {
+    let missed = now.saturating_duration_since(started).as_secs() / interval_in_sec;
+    (
+        missed,
+        started + Duration::from_secs(interval_in_sec * (missed + 1)),
+    )
+}
+
+// What a fetch may use to only ask for new items. `since` is set when the
+// previous successful run is too far back, `cursor` is whatever the fetcher
+// returned last time (a since_id, a timestamp, ...).
+#[derive(Debug, Clone, Default)]
+pub struct Window {
+    pub since: Option<DateTime<Utc>>,
+    pub cursor: Option<String>,
+    pub limits: RunLimits,
+}
+
+impl Window {
+    pub fn budget(&self) -> Budget {
+        Budget::new(self.limits)
+    }
+}
+
+// The result of an incremental fetch. Returning a plain list of shareables
+// keeps the previous cursor.
+#[derive(Debug, Clone, Default)]
+pub struct Fetched {
+    pub shareables: Vec<Shareable>,
+    pub cursor: Option<String>,
+}
+
+impl From<Vec<Shareable>> for Fetched {
+    fn from(shareables: Vec<Shareable>) -> Self {
+        Fetched {
+            shareables,
+            cursor: None,
+        }
+    }
+}
+
+const STORE_ATTEMPTS: u32 = 3;
+
+// Errors after which a fresh connection is worth a try, e.g. "server has gone
+// away" (2006) or "lost connection" (2013) on a connection that sat idle.
+fn is_connection_error(error: &mysql::Error) -> bool {
+    match error {
+        mysql::Error::IoError(_) | mysql::Error::DriverError(_) => true,
+        mysql::Error::MySqlError(e) => e.code == 2006 || e.code == 2013,
+        _ => false,
+    }
+}
+
+// Tells the notifiers and whoever else listens about new items.
+pub fn inserted(origin: Origin<'_>, created: &[Shareable]) {
+    if !created.is_empty() {
+        bus::publish(Event::ItemsInserted {
+            fetcher: origin.fetcher.to_string(),
+            shareables: created.to_vec(),
+        });
+    }
+}
+
+fn failed(name: &'static str, kind: ErrorKind) {
+    bus::publish(Event::SourceFailed {
+        fetcher: name,
+        kind,
+    });
+}
+
+// Inserts the batch, retrying it on a new connection if the current one
+// dropped. The pool pings connections on checkout and reconnects dead ones,
+// and rows that made it in before the drop are no-ops on the retry. New items
+// are published on the bus.
+async fn store(
+    pool: &mysql::Pool,
+    origin: Origin<'_>,
+    shareables: &[Shareable],
+) -> mysql::Result<Vec<Shareable>> {
+    let shareables = &links::canonicalize(shareables).await;
+    let mut attempt = 1;
+    loop {
+        match pool
+            .get_conn()
+            .and_then(|mut conn| insert_shareables(&mut conn, origin, shareables))
+        {
+            Ok(created) => {
+                inserted(origin, &created);
+                return Ok(created);
+            }
+            Err(e) if attempt < STORE_ATTEMPTS && is_connection_error(&e) => {
+                warn!(
+                    "Lost the database connection while storing {} (attempt {}), retrying: {}",
+                    origin.fetcher, attempt, e
+                );
+                time::sleep(Duration::from_secs(2u64.pow(attempt))).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+// For listeners that get items pushed instead of fetching them, spills the
+// batch the same way a fetch run does.
+pub async fn store_received(pool: &mysql::Pool, origin: Origin<'_>, shareables: &[Shareable]) {
+    // Keeps the database alone during maintenance if it can.
+    if (spill::is_pending() || maintenance::is_active()) && spill::push(origin, shareables) {
+        return;
+    }
+    match store(pool, origin, shareables).await {
+        Ok(created) => info!("Stored {} new {}", created.len(), origin.fetcher),
+        Err(e) if is_connection_error(&e) && spill::push(origin, shareables) => {
+            error!("Could not store {}, spilled to disk: {}", origin.fetcher, e)
+        }
+        Err(e) => error!("Error: {}", e),
+    }
+}
+
+fn load_cursor(pool: &mysql::Pool, key: &str) -> mysql::Result<Option<String>> {
+    pool.get_conn()?.exec_first(
+        "SELECT cursor_value FROM source_cursors WHERE fetcher = :fetcher",
+        params! { "fetcher" => key },
+    )
+}
+
+fn save_cursor(pool: &mysql::Pool, key: &str, cursor: &str) -> mysql::Result<()> {
+    pool.get_conn()?.exec_drop(
+        r"INSERT INTO source_cursors (fetcher, cursor_value)
+          VALUES (:fetcher, :cursor)
+          ON DUPLICATE KEY UPDATE cursor_value = VALUES(cursor_value)",
+        params! { "fetcher" => key, "cursor" => cursor },
+    )
+}
+
+fn last_success(pool: &mysql::Pool, key: &str) -> mysql::Result<Option<DateTime<Utc>>> {
+    let last_success_at: Option<String> = pool.get_conn()?.exec_first(
+        r"SELECT DATE_FORMAT(last_success_at, '%Y-%m-%dT%H:%i:%sZ')
+          FROM fetch_checkpoints WHERE fetcher = :fetcher",
+        params! { "fetcher" => key },
+    )?;
+    Ok(last_success_at
+        .and_then(|at| DateTime::parse_from_rfc3339(&at).ok())
+        .map(|at| at.with_timezone(&Utc)))
+}
+
+fn record_success(pool: &mysql::Pool, key: &str, at: DateTime<Utc>) -> mysql::Result<()> {
+    pool.get_conn()?.exec_drop(
+        r"INSERT INTO fetch_checkpoints (fetcher, last_success_at)
+          VALUES (:fetcher, :at)
+          ON DUPLICATE KEY UPDATE last_success_at = VALUES(last_success_at)",
+        params! { "fetcher" => key, "at" => at.format("%Y-%m-%d %H:%M:%S").to_string() },
+    )
+}
+
+// Runs `fetch` on the given schedule and stores whatever it returns. A failed
+// fetch is logged and retried on the next tick. Ticks that pass while a fetch
+// is still running are skipped instead of being caught up.
+//
+// If the last successful run is longer ago than two intervals (the service
+// was down or the source kept failing) `fetch` gets its time as `since`, so
+// sources that support time windows can backfill the gap. A cursor returned
+// by `fetch` is persisted once its items are stored and handed to the next run.
+// Failed runs are counted by the kind of error.
+pub fn spawn_fetcher<F, Fut, T, E>(
+    name: &'static str,
+    keyword: String,
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    fetch: F,
+) -> JoinHandle<()>
+where
+    F: Fn(Window) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<T, E>> + Send,
+    T: Into<Fetched>,
+    E: Into<FetchError>,
+{
+    task::spawn(async move {
+        let key = format!("{}:{}", name, keyword);
+        let origin = Origin {
+            fetcher: name,
+            keyword: &keyword,
+        };
+        let mut interval_in_sec = schedule.interval_in_sec.max(1);
+        let mut quiet_runs = 0;
+
+        loop {
+            if maintenance::is_active() {
+                info!("Pausing {} for maintenance", name);
+                if !maintenance::wait_until_over().await {
+                    break;
+                }
+            }
+            let started = Instant::now();
+
+            let fetch_started_at = schedule.clock.now();
+            let since = match last_success(&pool, &key) {
+                Ok(at) => catch_up_since(at, fetch_started_at, interval_in_sec),
+                Err(e) => {
+                    error!("Could not load last successful run of {}: {}", name, e);
+                    None
+                }
+            };
+            if let Some(at) = since {
+                info!("Catching up on {} since {}", name, at);
+            }
+
+            let cursor = match load_cursor(&pool, &key) {
+                Ok(cursor) => cursor,
+                Err(e) => {
+                    error!("Could not load cursor of {}: {}", name, e);
+                    None
+                }
+            };
+
+            // One span per run, the item spans of the stored items (and their
+            // ids in later webhook and reshare spans) hang off it.
+            let run_span = info_span!(
+                "fetch_run",
+                fetcher = name,
+                keyword = %keyword,
+                found = field::Empty,
+                created = field::Empty
+            );
+
+            info!("Fetching {}", name);
+            let window = Window {
+                since,
+                cursor,
+                limits: schedule.limits,
+            };
+            let result: Result<Fetched, FetchError> =
+                match schedule.faults.and_then(|faults| faults.roll()) {
+                    Some(fault) => {
+                        warn!("Injecting {:?} into {}", fault, name);
+                        Err(fault.inject().await)
+                    }
+                    None => fetch(window)
+                        .instrument(run_span.clone())
+                        .await
+                        .map(Into::into)
+                        .map_err(Into::into),
+                };
+            match result.map(|mut fetched: Fetched| {
+                let dropped = schedule.limits.apply(&mut fetched.shareables);
+                if dropped > 0 {
+                    // The cursor may be past the dropped items, the next run
+                    // starts from the old one instead.
+                    warn!(
+                        "Run of {} hit its item or size limit, dropped the {} oldest and kept the cursor",
+                        name, dropped
+                    );
+                    fetched.cursor = None;
+                }
+                fetched
+            }) {
+                // Batches that are still spilled have to be stored first.
+                Ok(Fetched { shareables, .. })
+                    if spill::is_pending() && spill::push(origin, &shareables) => {}
+                Ok(Fetched { shareables, cursor }) => {
+                    info!("Found {} {}", shareables.len(), name);
+                    run_span.record("found", shareables.len());
+                    match store(&pool, origin, &shareables)
+                        .instrument(run_span.clone())
+                        .await
+                    {
+                        Ok(created) => {
+                            run_span.record("created", created.len());
+                            info!("Stored {} new {}, waiting...", created.len(), name);
+                            quiet_runs = if created.is_empty() {
+                                quiet_runs + 1
+                            } else {
+                                0
+                            };
+                            if let Err(e) = record_success(&pool, &key, fetch_started_at) {
+                                error!("Could not record successful run of {}: {}", name, e);
+                            }
+                            if let Some(cursor) = cursor {
+                                if let Err(e) = save_cursor(&pool, &key, &cursor) {
+                                    error!("Could not store cursor of {}: {}", name, e);
+                                }
+                            }
+                        }
+                        Err(e) if is_connection_error(&e) && spill::push(origin, &shareables) => {
+                            failed(name, ErrorKind::Db);
+                            error!("Could not store {}, spilled to disk: {}", name, e)
+                        }
+                        Err(e) => {
+                            failed(name, ErrorKind::Db);
+                            error!(error_kind = ErrorKind::Db.as_str(), "Error: {}", e)
+                        }
+                    }
+                }
+                Err(e) => {
+                    let e: FetchError = e;
+                    failed(name, e.kind);
+                    error!(
+                        error_kind = e.kind.as_str(),
+                        "Could not fetch {}, aborting: {}", name, e
+                    );
+                }
+            }
+
+            let next_interval = schedule.next_interval(interval_in_sec, quiet_runs);
+            if next_interval != interval_in_sec {
+                info!("Polling {} every {}s from now on", name, next_interval);
+                interval_in_sec = next_interval;
+            }
+
+            let (missed, next_run) = next_tick(started, Instant::now(), interval_in_sec);
+            if missed > 0 {
+                skip_runs(name, missed);
+            }
+            // A run that is underway is finished and stored, shutdown only
+            // stops the next one.
+            if !shutdown::sleep_until(next_run).await {
+                info!("Stopped fetching {}", name);
+                break;
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::TestClock;
+    use proptest::prelude::*;
+
+    fn schedule(max_interval_in_sec: Option<u64>) -> Schedule {
+        Schedule {
+            interval_in_sec: 60,
+            max_interval_in_sec,
+            limits: RunLimits::default(),
+            clock: &crate::clock::SYSTEM,
+            faults: None,
+        }
+    }
+
+    #[test]
+    fn item_ids_have_a_fixed_size() {
+        let url = format!("https://stackoverflow.com/questions/1/{}", "a".repeat(500));
+        assert_eq!(item_id("twitter", 1532), item_id("twitter", "1532"));
+        assert_ne!(item_id("twitter", 1532), item_id("mastodon", 1532));
+        assert!(item_id("stackoverflow", 1).starts_with("stackoverflow-"));
+        assert_eq!(
+            item_id("stackoverflow", &url).len(),
+            "stackoverflow-".len() + 32
+        );
+    }
+
+    #[test]
+    fn backs_off_after_quiet_runs_and_resets_on_new_items() {
+        let adaptive = schedule(Some(300));
+        assert_eq!(adaptive.next_interval(60, 1), 60);
+        assert_eq!(adaptive.next_interval(60, 3), 120);
+        assert_eq!(adaptive.next_interval(120, 4), 240);
+        assert_eq!(adaptive.next_interval(240, 5), 300);
+        assert_eq!(adaptive.next_interval(300, 0), 60);
+
+        assert_eq!(schedule(None).next_interval(60, 10), 60);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn catches_up_after_a_long_gap() {
+        let clock = TestClock::new(Utc::now());
+        let last_success = Some(clock.now());
+
+        time::advance(Duration::from_secs(120)).await;
+        assert_eq!(catch_up_since(last_success, clock.now(), 60), None);
+
+        time::advance(Duration::from_secs(1)).await;
+        assert_eq!(catch_up_since(last_success, clock.now(), 60), last_success);
+        assert_eq!(catch_up_since(None, clock.now(), 60), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn skips_ticks_missed_by_a_slow_run() {
+        let started = Instant::now();
+
+        time::advance(Duration::from_secs(30)).await;
+        assert_eq!(
+            next_tick(started, Instant::now(), 60),
+            (0, started + Duration::from_secs(60))
+        );
+
+        time::advance(Duration::from_secs(120)).await;
+        let (missed, next_run) = next_tick(started, Instant::now(), 60);
+        assert_eq!(missed, 2);
+        assert_eq!(next_run, started + Duration::from_secs(180));
+
+        time::sleep_until(next_run).await;
+        assert_eq!(Instant::now(), started + Duration::from_secs(180));
+    }
+
+    fn shareable(id: String, title: String) -> Shareable {
+        Shareable {
+            url: format!("https://example.com/{}", id),
+            id,
+            title,
+            date: String::from("2022-06-01T12:00:00Z"),
+            source: String::from("test"),
+            canonical_url: None,
+        }
+    }
+
+    #[test]
+    fn only_turning_answered_is_answered() {
+        let question = ":question: How do I use cdktf?";
+        let answered = ":white_check_mark: How do I use cdktf?";
+        assert_eq!(event_type(None, question), Some(EventType::Created));
+        assert_eq!(event_type(Some(question), question), None);
+        assert_eq!(
+            event_type(Some(question), answered),
+            Some(EventType::Answered)
+        );
+        assert_eq!(
+            event_type(
+                Some(answered),
+                ":white_check_mark: How do I use cdktf with Go?"
+            ),
+            Some(EventType::Updated)
+        );
+        assert_eq!(
+            event_type(Some(answered), question),
+            Some(EventType::Updated)
+        );
+    }
+
+    #[test]
+    fn run_limits_keep_the_newest_shareables() {
+        let mut shareables: Vec<Shareable> = (1..=5)
+            .map(|day| Shareable {
+                date: format!("2022-06-0{}T12:00:00Z", day),
+                ..shareable(format!("id-{}", day), String::from("title"))
+            })
+            .collect();
+        let size = shareables[0].size_in_bytes();
+
+        let limits = RunLimits {
+            max_items: 4,
+            max_bytes: size * 3 + 1,
+            max_pages: 1,
+        };
+        assert_eq!(limits.apply(&mut shareables), 2);
+        assert_eq!(
+            shareables.iter().map(|s| s.id.as_str()).collect::<Vec<_>>(),
+            vec!["id-5", "id-4", "id-3"]
+        );
+        assert_eq!(RunLimits::default().apply(&mut shareables), 0);
+    }
+
+    #[test]
+    fn budgets_stop_at_the_limits() {
+        let shareables = |ids: &[&str]| -> Vec<Shareable> {
+            ids.iter()
+                .map(|id| shareable(id.to_string(), String::from("title")))
+                .collect()
+        };
+        let mut budget = Budget::new(RunLimits {
+            max_items: 3,
+            max_bytes: usize::MAX,
+            max_pages: 2,
+        });
+        let mut kept = vec![];
+        assert!(budget.next_page());
+        assert!(budget.keep(&mut kept, shareables(&["1", "2"])));
+        assert!(!budget.exhausted());
+        assert!(budget.next_page());
+        assert!(!budget.keep(&mut kept, shareables(&["3", "4"])));
+        assert!(budget.exhausted());
+        assert!(!budget.next_page());
+        assert_eq!(
+            kept.iter().map(|s| s.id.as_str()).collect::<Vec<_>>(),
+            vec!["1", "2", "3"]
+        );
+
+        let split = RunLimits::default().split(3);
+        assert_eq!(split.max_items, DEFAULT_MAX_ITEMS_PER_RUN / 3);
+        assert_eq!(split.max_pages, DEFAULT_MAX_PAGES_PER_RUN / 3);
+        assert_eq!(RunLimits::default().split(100).max_pages, 1);
+    }
+
+    #[test]
+    fn newest_first_cursors_fill_gaps() {
+        let cursor = |value: Option<&str>| NewestFirst::<u64>::parse(value);
+
+        // Everything since 100 fit, up to 180.
+        assert_eq!(
+            cursor(Some("100")).advance(Some(180), Some(101), true),
+            Some(String::from("180"))
+        );
+        // Only back to 150, 100..150 is left for the next runs.
+        let gap = cursor(Some("100")).advance(Some(180), Some(150), false);
+        assert_eq!(gap.as_deref(), Some("100..150..180"));
+        let gap = cursor(gap.as_deref());
+        assert_eq!(gap.after, Some(100));
+        assert_eq!(gap.before, Some(150));
+        // Back to 120 only, the gap gets smaller.
+        assert_eq!(
+            gap.clone().advance(Some(149), Some(120), false),
+            Some(String::from("100..120..180"))
+        );
+        // Nothing kept, the same gap again.
+        assert_eq!(
+            gap.clone().advance(None, None, false),
+            Some(String::from("100..150..180"))
+        );
+        // Filled, newer items come next.
+        assert_eq!(
+            gap.advance(Some(149), Some(101), true),
+            Some(String::from("180"))
+        );
+
+        // The first run has no gap to fill.
+        assert_eq!(
+            cursor(None).advance(Some(180), Some(150), false),
+            Some(String::from("180"))
+        );
+        assert_eq!(
+            cursor(Some("100")).advance(None, None, true),
+            Some(String::from("100"))
+        );
+        assert_eq!(cursor(Some("garbage")).after, None);
+    }
+
+    // Ids as fetchers build them, from platform ids of a few sources.
+    fn any_item_id() -> impl Strategy<Value = String> {
+        (
+            prop::sample::select(vec!["stackoverflow", "devto", "mastodon"]),
+            "[a-z0-9äöü日本]{1,8}",
+        )
+            .prop_map(|(source, platform_id)| item_id(source, platform_id))
+    }
+
+    // Links as sources hand them out, already in their normalized form.
+    fn any_link() -> impl Strategy<Value = String> {
+        (
+            prop::sample::select(vec!["http", "https"]),
+            "[a-z]{1,10}\\.(com|org|io)",
+            prop::collection::vec("[a-zA-Z0-9äöü日本_-]{1,6}", 1..4),
+            prop::option::of("id=[0-9]{1,4}"),
+        )
+            .prop_map(|(scheme, host, segments, query)| {
+                let url = format!("{}://{}/{}", scheme, host, segments.join("/"));
+                match query {
+                    Some(query) => format!("{}?{}", url, query),
+                    None => url,
+                }
+            })
+    }
+
+    // The same link with a different host case, `www.`, trailing slash,
+    // tracking parameter or fragment, any number of them.
+    fn variant(link: &str, changes: &[u8]) -> String {
+        let (scheme, rest) = link.split_once("://").unwrap();
+        let (host, rest) = rest.split_once('/').unwrap();
+        let (path, query) = match rest.split_once('?') {
+            Some((path, query)) => (path.to_string(), Some(query.to_string())),
+            None => (rest.to_string(), None),
+        };
+        let (mut scheme, mut host, mut path, mut query) =
+            (scheme.to_string(), host.to_string(), path, query);
+        let mut fragment = None;
+        for change in changes {
+            match change % 5 {
+                0 => {
+                    scheme = scheme.to_uppercase();
+                    host = host.to_uppercase();
+                }
+                1 if !host.to_lowercase().starts_with("www.") => host = format!("www.{}", host),
+                2 if !path.ends_with('/') => path.push('/'),
+                3 => {
+                    query = Some(match query {
+                        Some(query) => format!("{}&utm_source=twitter&fbclid=abc", query),
+                        None => String::from("utm_medium=social"),
+                    })
+                }
+                4 => fragment = Some("#comments"),
+                _ => {}
+            }
+        }
+        format!(
+            "{}://{}/{}{}{}",
+            scheme,
+            host,
+            path,
+            query.map(|query| format!("?{}", query)).unwrap_or_default(),
+            fragment.unwrap_or_default()
+        )
+    }
+
+    fn linked(url: String) -> Shareable {
+        Shareable {
+            url,
+            ..shareable(String::from("id"), String::from("title"))
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn dedup_keeps_one_shareable_per_id(
+            items in prop::collection::vec((any_item_id(), "\\PC{0,20}"), 0..30)
+        ) {
+            let mut shareables: Vec<Shareable> = items
+                .iter()
+                .map(|(id, title)| shareable(id.clone(), title.clone()))
+                .collect();
+            dedup_shareables(&mut shareables);
+
+            let mut ids: Vec<&String> = items.iter().map(|(id, _)| id).collect();
+            ids.sort();
+            ids.dedup();
+            prop_assert_eq!(shareables.iter().map(|s| &s.id).collect::<Vec<_>>(), ids);
+
+            // The first occurrence wins.
+            for s in shareables.iter() {
+                let first = items.iter().find(|(id, _)| *id == s.id).unwrap();
+                prop_assert_eq!(&s.title, &first.1);
+            }
+        }
+
+        #[test]
+        fn dedup_is_stable(items in prop::collection::vec((any_item_id(), "\\PC{0,20}"), 0..30)) {
+            let mut once: Vec<Shareable> = items
+                .into_iter()
+                .map(|(id, title)| shareable(id, title))
+                .collect();
+            dedup_shareables(&mut once);
+            let mut twice = once.clone();
+            dedup_shareables(&mut twice);
+            prop_assert_eq!(once, twice);
+        }
+
+        #[test]
+        fn canonical_keys_are_idempotent(
+            link in any_link(),
+            changes in prop::collection::vec(any::<u8>(), 0..5)
+        ) {
+            let key = canonical_key(&linked(variant(&link, &changes)));
+            prop_assert_eq!(canonical_key(&linked(key.clone())), key);
+        }
+
+        #[test]
+        fn link_variants_share_a_canonical_key(
+            link in any_link(),
+            changes in prop::collection::vec(any::<u8>(), 1..5)
+        ) {
+            prop_assert_eq!(
+                canonical_key(&linked(variant(&link, &changes))),
+                canonical_key(&linked(link))
+            );
+        }
+
+        #[test]
+        fn display_title_is_stable_for_unicode_titles(
+            title in "(\\PC|:question:|:white_check_mark:|:waiting-spin:){0,20}"
+        ) {
+            let once = shareable(String::from("id"), title).display_title();
+            let twice = shareable(String::from("id"), once.clone()).display_title();
+            prop_assert_eq!(once, twice);
+        }
+    }
+}
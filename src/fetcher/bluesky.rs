@@ -0,0 +1,98 @@
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::error;
+
+use crate::fetcher::base::{self, Schedule, Shareable};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::{self, FetchError};
+use crate::http;
+
+#[derive(Debug, Deserialize)]
+struct BlueskyAuthor {
+    handle: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlueskyRecord {
+    text: String,
+    created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlueskyPost {
+    uri: String,
+    author: BlueskyAuthor,
+    record: BlueskyRecord,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlueskySearchResponse {
+    posts: Vec<BlueskyPost>,
+}
+
+// TODO: walk through pagination if needed
+async fn fetch_bluesky_api(query: String) -> Result<BlueskySearchResponse, FetchError> {
+    let resp = match http::client()
+        .get("https://public.api.bsky.app/xrpc/app.bsky.feed.searchPosts")
+        .query(&[("q", query.as_str()), ("sort", "latest"), ("limit", "100")])
+        .send()
+        .await
+    {
+        Ok(resp) => match error::json::<BlueskySearchResponse>(resp).await {
+            Ok(json) => json,
+            Err(err) => {
+                error!("Could not parse Bluesky API: {}", err);
+                return Err(err);
+            }
+        },
+        Err(e) => {
+            error!("Bluesky responded with an Error exit code: {}", e);
+            return Err(FetchError::from(e));
+        }
+    };
+
+    base::log_sample("Bluesky response", &resp.posts);
+    Ok(resp)
+}
+
+// at://did:plc:xyz/app.bsky.feed.post/<rkey> -> https://bsky.app/profile/<handle>/post/<rkey>
+fn post_url(post: &BlueskyPost) -> String {
+    let rkey = post.uri.rsplit('/').next().unwrap_or_default();
+    format!(
+        "https://bsky.app/profile/{}/post/{}",
+        post.author.handle, rkey
+    )
+}
+
+async fn fetch(keyword: String) -> Result<Vec<Shareable>, FetchError> {
+    let data = fetch_bluesky_api(keyword).await?;
+
+    Ok(data
+        .posts
+        .iter()
+        .map(|item| Shareable {
+            id: base::item_id("bluesky", &item.uri),
+            title: format!("@{}: {}", item.author.handle, item.record.text),
+            date: normalize_date(&item.record.created_at, DateFormat::Rfc3339),
+            url: post_url(item),
+            source: String::from("bluesky"),
+            canonical_url: None,
+        })
+        .collect())
+}
+
+pub fn spawn_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+) -> JoinHandle<()> {
+    base::spawn_fetcher(
+        "Bluesky posts",
+        keyword.clone(),
+        schedule,
+        pool,
+        move |_| fetch(keyword.clone()),
+    )
+}
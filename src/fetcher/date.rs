@@ -0,0 +1,131 @@
+use chrono::{DateTime, NaiveDate, SecondsFormat, TimeZone, Utc};
+
+// How a source encodes its dates. Everything is normalized to RFC 3339 in UTC
+// before it is stored, so dates of different sources sort correctly as strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateFormat {
+    // ISO timestamps with any offset, used by most JSON APIs
+    Rfc3339,
+    // RSS feeds, e.g. "Wed, 01 Jun 2022 12:00:00 GMT"
+    Rfc2822,
+    // Seconds since the epoch, e.g. the Stack Exchange API
+    UnixTimestamp,
+    // Whatever is already stored, including the "2022-06-01UTC" dates the
+    // StackOverflow fetcher used to write
+    Any,
+}
+
+pub fn parse_source_date(raw: &str, format: DateFormat) -> Option<DateTime<Utc>> {
+    let raw = raw.trim();
+    match format {
+        DateFormat::Rfc3339 => DateTime::parse_from_rfc3339(raw)
+            .ok()
+            .map(|date| date.with_timezone(&Utc)),
+        DateFormat::Rfc2822 => DateTime::parse_from_rfc2822(raw)
+            .ok()
+            .map(|date| date.with_timezone(&Utc)),
+        DateFormat::UnixTimestamp => raw
+            .parse::<i64>()
+            .ok()
+            .and_then(|seconds| Utc.timestamp_opt(seconds, 0).single()),
+        DateFormat::Any => parse_source_date(raw, DateFormat::Rfc3339)
+            .or_else(|| parse_source_date(raw, DateFormat::Rfc2822))
+            .or_else(|| parse_source_date(raw, DateFormat::UnixTimestamp))
+            .or_else(|| {
+                NaiveDate::parse_from_str(raw.trim_end_matches("UTC"), "%Y-%m-%d")
+                    .ok()
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+                    .map(|date| Utc.from_utc_datetime(&date))
+            }),
+    }
+}
+
+// The date as it should be stored, unparseable dates are kept as they are
+// rather than dropping the item.
+pub fn normalize_date(raw: &str, format: DateFormat) -> String {
+    match parse_source_date(raw, format) {
+        Some(date) => date.to_rfc3339_opts(SecondsFormat::Secs, true),
+        None => raw.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(raw: &str) -> Option<DateTime<Utc>> {
+        Some(
+            DateTime::parse_from_rfc3339(raw)
+                .unwrap()
+                .with_timezone(&Utc),
+        )
+    }
+
+    #[test]
+    fn parses_rfc3339_with_offsets_and_fractions() {
+        assert_eq!(
+            parse_source_date("2022-06-01T12:00:00.000Z", DateFormat::Rfc3339),
+            utc("2022-06-01T12:00:00Z")
+        );
+        assert_eq!(
+            parse_source_date("2022-06-01T14:00:00+02:00", DateFormat::Rfc3339),
+            utc("2022-06-01T12:00:00Z")
+        );
+        assert_eq!(parse_source_date("yesterday", DateFormat::Rfc3339), None);
+    }
+
+    #[test]
+    fn parses_rfc2822() {
+        assert_eq!(
+            parse_source_date("Wed, 01 Jun 2022 12:00:00 GMT", DateFormat::Rfc2822),
+            utc("2022-06-01T12:00:00Z")
+        );
+        assert_eq!(
+            parse_source_date("Wed, 01 Jun 2022 08:00:00 -0400", DateFormat::Rfc2822),
+            utc("2022-06-01T12:00:00Z")
+        );
+    }
+
+    #[test]
+    fn parses_unix_timestamps() {
+        assert_eq!(
+            parse_source_date("1654084800", DateFormat::UnixTimestamp),
+            utc("2022-06-01T12:00:00Z")
+        );
+        assert_eq!(
+            parse_source_date("2022-06-01", DateFormat::UnixTimestamp),
+            None
+        );
+    }
+
+    #[test]
+    fn any_accepts_all_formats_and_legacy_dates() {
+        for raw in [
+            "2022-06-01T12:00:00Z",
+            "Wed, 01 Jun 2022 12:00:00 GMT",
+            "1654084800",
+        ] {
+            assert_eq!(
+                parse_source_date(raw, DateFormat::Any),
+                utc("2022-06-01T12:00:00Z")
+            );
+        }
+        assert_eq!(
+            parse_source_date("2022-06-01UTC", DateFormat::Any),
+            utc("2022-06-01T00:00:00Z")
+        );
+    }
+
+    #[test]
+    fn normalizes_to_sortable_utc() {
+        assert_eq!(
+            normalize_date("2022-06-01T14:00:00.123+02:00", DateFormat::Rfc3339),
+            "2022-06-01T12:00:00Z"
+        );
+        assert_eq!(
+            normalize_date("1654084800", DateFormat::UnixTimestamp),
+            "2022-06-01T12:00:00Z"
+        );
+        assert_eq!(normalize_date("not a date", DateFormat::Any), "not a date");
+    }
+}
@@ -0,0 +1,108 @@
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::error;
+
+use crate::fetcher::base::{self, Schedule, Shareable, Window};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::{self, FetchError};
+use crate::http;
+
+#[derive(Debug, Deserialize, Clone)]
+struct DevToUser {
+    username: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct DevToArticle {
+    id: u64,
+    title: String,
+    description: String,
+    canonical_url: String,
+    published_at: String,
+    tag_list: Vec<String>,
+    user: DevToUser,
+}
+
+async fn fetch_devto_api(params: &[(&str, &str)]) -> Result<Vec<DevToArticle>, FetchError> {
+    let resp = match http::client()
+        .get("https://dev.to/api/articles")
+        .query(params)
+        .header("Accept", "application/vnd.forem.api-v1+json")
+        .send()
+        .await
+    {
+        Ok(resp) => match error::json::<Vec<DevToArticle>>(resp).await {
+            Ok(json) => json,
+            Err(err) => {
+                error!("Could not parse dev.to API: {}", err);
+                return Err(err);
+            }
+        },
+        Err(e) => {
+            error!("dev.to responded with an Error exit code: {}", e);
+            return Err(FetchError::from(e));
+        }
+    };
+
+    base::log_sample("dev.to response", &resp);
+    Ok(resp)
+}
+
+fn to_shareable(item: DevToArticle) -> Shareable {
+    Shareable {
+        id: base::item_id("devto", item.id),
+        title: format!("{} (by {})", item.title, item.user.username),
+        date: normalize_date(&item.published_at, DateFormat::Rfc3339),
+        url: item.canonical_url,
+        source: String::from("devto"),
+        canonical_url: None,
+    }
+}
+
+// The public API has no full text search, so we combine the articles tagged
+// with the keyword with the latest articles that mention it.
+async fn fetch(keyword: String, window: Window) -> Result<Vec<Shareable>, FetchError> {
+    let tag: String = keyword
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect();
+    let needle = keyword.to_lowercase();
+
+    let mut budget = window.budget();
+    let mut shareables = vec![];
+    budget.next_page();
+    let tagged = fetch_devto_api(&[("tag", tag.as_str()), ("per_page", "100")]).await?;
+    if budget.keep(&mut shareables, tagged.into_iter().map(to_shareable)) && budget.next_page() {
+        let fresh = fetch_devto_api(&[("state", "fresh"), ("per_page", "100")]).await?;
+        budget.keep(
+            &mut shareables,
+            fresh
+                .into_iter()
+                .filter(|article| {
+                    article.title.to_lowercase().contains(&needle)
+                        || article.description.to_lowercase().contains(&needle)
+                        || article.tag_list.contains(&tag)
+                })
+                .map(to_shareable),
+        );
+    }
+    base::dedup_shareables(&mut shareables);
+
+    Ok(shareables)
+}
+
+pub fn spawn_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+) -> JoinHandle<()> {
+    base::spawn_fetcher(
+        "dev.to articles",
+        keyword.clone(),
+        schedule,
+        pool,
+        move |window| fetch(keyword.clone(), window),
+    )
+}
@@ -0,0 +1,274 @@
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::{self, JoinHandle};
+use tokio::time;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tracing::{debug, error, info, warn};
+
+use crate::fetcher::base::{self, Origin, Shareable};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::shutdown;
+
+const GATEWAY_URL: &str = "wss://gateway.discord.gg/?v=10&encoding=json";
+
+// GUILD_MESSAGES and MESSAGE_CONTENT. The latter has to be switched on for the
+// bot in the developer portal, without it every message arrives empty.
+const INTENTS: u64 = (1 << 9) | (1 << 15);
+
+const MIN_BACKOFF_IN_SEC: u64 = 5;
+const MAX_BACKOFF_IN_SEC: u64 = 300;
+
+// Authentication failed, invalid shard, sharding required, invalid API
+// version, invalid intents and disallowed intents. Reconnecting won't help.
+const FATAL_CLOSE_CODES: &[u16] = &[4004, 4010, 4011, 4012, 4013, 4014];
+
+#[derive(Debug, Deserialize)]
+struct GatewayEvent {
+    op: u8,
+    d: Option<Value>,
+    s: Option<u64>,
+    t: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Hello {
+    heartbeat_interval: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscordAuthor {
+    username: String,
+    bot: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscordMessage {
+    id: String,
+    channel_id: String,
+    guild_id: Option<String>,
+    content: String,
+    timestamp: String,
+    author: DiscordAuthor,
+}
+
+#[derive(Debug, Clone)]
+pub struct DiscordFilter {
+    pub guild_ids: Vec<String>,
+    pub channel_ids: Vec<String>,
+}
+
+impl DiscordFilter {
+    // Empty lists allow everything the bot can see.
+    fn allows(&self, guild_id: &str, channel_id: &str) -> bool {
+        (self.guild_ids.is_empty() || self.guild_ids.iter().any(|id| id == guild_id))
+            && (self.channel_ids.is_empty() || self.channel_ids.iter().any(|id| id == channel_id))
+    }
+}
+
+enum Disconnect {
+    Retry(String),
+    Fatal(String),
+}
+
+fn to_shareable(
+    message: DiscordMessage,
+    keyword: &str,
+    filter: &DiscordFilter,
+) -> Option<Shareable> {
+    // Direct messages have no guild and no link to share.
+    let guild_id = message.guild_id?;
+    if message.author.bot.unwrap_or(false)
+        || !filter.allows(&guild_id, &message.channel_id)
+        || !message
+            .content
+            .to_lowercase()
+            .contains(&keyword.to_lowercase())
+    {
+        return None;
+    }
+
+    Some(Shareable {
+        id: base::item_id("discord", &message.id),
+        title: format!("{}: {}", message.author.username, message.content),
+        date: normalize_date(&message.timestamp, DateFormat::Rfc3339),
+        url: format!(
+            "https://discord.com/channels/{}/{}/{}",
+            guild_id, message.channel_id, message.id
+        ),
+        source: String::from("discord"),
+        canonical_url: None,
+    })
+}
+
+fn heartbeat(sequence: Option<u64>) -> Message {
+    Message::Text(json!({ "op": 1, "d": sequence }).to_string())
+}
+
+// One gateway session, from connecting until Discord or the network drops it.
+// There is no session resume, messages sent while reconnecting are missed.
+async fn listen(
+    pool: &mysql::Pool,
+    keyword: &str,
+    bot_token: &str,
+    filter: &DiscordFilter,
+) -> Disconnect {
+    let (mut socket, _) = match connect_async(GATEWAY_URL).await {
+        Ok(connection) => connection,
+        Err(e) => return Disconnect::Retry(format!("could not connect: {}", e)),
+    };
+
+    // The first event is always Hello with the heartbeat interval.
+    let hello = match socket.next().await {
+        Some(Ok(Message::Text(text))) => serde_json::from_str::<GatewayEvent>(&text)
+            .ok()
+            .filter(|event| event.op == 10)
+            .and_then(|event| serde_json::from_value::<Hello>(event.d?).ok()),
+        _ => None,
+    };
+    let hello = match hello {
+        Some(hello) => hello,
+        None => return Disconnect::Retry(String::from("no Hello from the gateway")),
+    };
+
+    let identify = json!({
+        "op": 2,
+        "d": {
+            "token": bot_token,
+            "intents": INTENTS,
+            "properties": {
+                "os": std::env::consts::OS,
+                "browser": "keyword-notifier",
+                "device": "keyword-notifier"
+            }
+        }
+    });
+    if let Err(e) = socket.send(Message::Text(identify.to_string())).await {
+        return Disconnect::Retry(format!("could not identify: {}", e));
+    }
+
+    let mut heartbeats = time::interval(Duration::from_millis(hello.heartbeat_interval));
+    let mut sequence: Option<u64> = None;
+    let mut acked = true;
+
+    loop {
+        tokio::select! {
+            _ = heartbeats.tick() => {
+                // A missing ack means the connection went stale without closing.
+                if !acked {
+                    return Disconnect::Retry(String::from("heartbeat was not acknowledged"));
+                }
+                acked = false;
+                if let Err(e) = socket.send(heartbeat(sequence)).await {
+                    return Disconnect::Retry(format!("could not send heartbeat: {}", e));
+                }
+            }
+            message = socket.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(Some(frame))))
+                        if FATAL_CLOSE_CODES.contains(&u16::from(frame.code)) =>
+                    {
+                        return Disconnect::Fatal(format!("{} {}", frame.code, frame.reason));
+                    }
+                    Some(Ok(Message::Close(frame))) => {
+                        return Disconnect::Retry(format!("closed by Discord: {:?}", frame));
+                    }
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Disconnect::Retry(format!("{}", e)),
+                    None => return Disconnect::Retry(String::from("connection closed")),
+                };
+
+                let event = match serde_json::from_str::<GatewayEvent>(&text) {
+                    Ok(event) => event,
+                    Err(e) => {
+                        error!("Could not parse Discord gateway event: {}", e);
+                        continue;
+                    }
+                };
+                if event.s.is_some() {
+                    sequence = event.s;
+                }
+
+                match (event.op, event.t.as_deref()) {
+                    (0, Some("READY")) => info!("Listening for {} on Discord", keyword),
+                    (0, Some("MESSAGE_CREATE")) => {
+                        let message = match event.d.map(serde_json::from_value::<DiscordMessage>) {
+                            Some(Ok(message)) => message,
+                            Some(Err(e)) => {
+                                error!("Could not parse Discord message: {}", e);
+                                continue;
+                            }
+                            None => continue,
+                        };
+                        if let Some(shareable) = to_shareable(message, keyword, filter) {
+                            debug!("Found Discord message {}", shareable.id);
+                            let origin = Origin {
+                                fetcher: "discord messages",
+                                keyword,
+                            };
+                            base::store_received(pool, origin, &[shareable]).await;
+                        }
+                    }
+                    (0, _) => {}
+                    (1, _) => {
+                        if let Err(e) = socket.send(heartbeat(sequence)).await {
+                            return Disconnect::Retry(format!("could not send heartbeat: {}", e));
+                        }
+                    }
+                    (7, _) => return Disconnect::Retry(String::from("asked to reconnect")),
+                    (9, _) => return Disconnect::Retry(String::from("session invalidated")),
+                    (11, _) => acked = true,
+                    (op, _) => debug!("Ignoring Discord gateway op {}", op),
+                }
+            }
+        }
+    }
+}
+
+// Keeps a gateway connection open instead of polling, new messages are stored
+// as they come in.
+pub fn spawn_listener(
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+    bot_token: String,
+    filter: DiscordFilter,
+) -> JoinHandle<()> {
+    task::spawn(async move {
+        let mut backoff_in_sec = MIN_BACKOFF_IN_SEC;
+        loop {
+            let started = Instant::now();
+            let disconnect = tokio::select! {
+                disconnect = listen(&pool, &keyword, &bot_token, &filter) => disconnect,
+                _ = shutdown::requested() => {
+                    info!("Stopped listening on Discord");
+                    return;
+                }
+            };
+            match disconnect {
+                Disconnect::Fatal(e) => {
+                    error!("Discord rejected the bot, not listening anymore: {}", e);
+                    return;
+                }
+                Disconnect::Retry(e) => {
+                    // Only back off further if the last session did not get going.
+                    if started.elapsed() > Duration::from_secs(MAX_BACKOFF_IN_SEC) {
+                        backoff_in_sec = MIN_BACKOFF_IN_SEC;
+                    }
+                    warn!(
+                        "Lost the Discord gateway, reconnecting in {}s: {}",
+                        backoff_in_sec, e
+                    );
+                }
+            }
+            if !shutdown::sleep_until(time::Instant::now() + Duration::from_secs(backoff_in_sec))
+                .await
+            {
+                return;
+            }
+            backoff_in_sec = (backoff_in_sec * 2).min(MAX_BACKOFF_IN_SEC);
+        }
+    })
+}
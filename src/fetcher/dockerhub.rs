@@ -0,0 +1,126 @@
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::error;
+
+use crate::fetcher::base::{self, Schedule, Shareable, Window};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::{self, FetchError};
+use crate::http;
+
+#[derive(Debug, Deserialize)]
+struct DockerHubRepository {
+    repo_name: String,
+    short_description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DockerHubSearchResponse {
+    results: Vec<DockerHubRepository>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DockerHubTag {
+    name: String,
+    last_updated: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DockerHubTagsResponse {
+    results: Vec<DockerHubTag>,
+}
+
+async fn fetch_dockerhub_api<T: serde::de::DeserializeOwned>(
+    url: String,
+    params: &[(&str, &str)],
+) -> Result<T, FetchError> {
+    match http::client().get(url).query(params).send().await {
+        Ok(resp) => match error::json::<T>(resp).await {
+            Ok(json) => Ok(json),
+            Err(err) => {
+                error!("Could not parse Docker Hub API: {}", err);
+                Err(err)
+            }
+        },
+        Err(e) => {
+            error!("Docker Hub responded with an Error exit code: {}", e);
+            Err(FetchError::from(e))
+        }
+    }
+}
+
+// The search API has no dates, so we look up the most recently pushed tag of
+// every matching repository. A new push shows up as a new item.
+async fn fetch(keyword: String, window: Window) -> Result<Vec<Shareable>, FetchError> {
+    let mut budget = window.budget();
+    budget.next_page();
+    let search: DockerHubSearchResponse = fetch_dockerhub_api(
+        String::from("https://hub.docker.com/v2/search/repositories/"),
+        &[("query", keyword.as_str()), ("page_size", "25")],
+    )
+    .await?;
+    base::log_sample("Docker Hub response", &search.results);
+
+    let mut shareables: Vec<Shareable> = vec![];
+    for repository in search.results {
+        if !budget.next_page() {
+            break;
+        }
+        // Official images are searched without their "library/" namespace.
+        let full_name = if repository.repo_name.contains('/') {
+            repository.repo_name.clone()
+        } else {
+            format!("library/{}", repository.repo_name)
+        };
+        let tags: DockerHubTagsResponse = fetch_dockerhub_api(
+            format!("https://hub.docker.com/v2/repositories/{}/tags", full_name),
+            &[("page_size", "1"), ("ordering", "last_updated")],
+        )
+        .await?;
+
+        let kept = budget.keep(
+            &mut shareables,
+            tags.results.into_iter().map(|tag| {
+                let image = format!("{}:{}", repository.repo_name, tag.name);
+                Shareable {
+                    id: base::item_id(
+                        "dockerhub",
+                        format!("{}-{}", image, tag.last_updated.clone().unwrap_or_default()),
+                    ),
+                    title: match &repository.short_description {
+                        Some(description) if !description.is_empty() => {
+                            format!("{}: {}", image, description)
+                        }
+                        _ => image,
+                    },
+                    date: normalize_date(
+                        &tag.last_updated.unwrap_or_default(),
+                        DateFormat::Rfc3339,
+                    ),
+                    url: format!("https://hub.docker.com/r/{}/tags", full_name),
+                    source: String::from("dockerhub"),
+                    canonical_url: None,
+                }
+            }),
+        );
+        if !kept {
+            break;
+        }
+    }
+
+    Ok(shareables)
+}
+
+pub fn spawn_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+) -> JoinHandle<()> {
+    base::spawn_fetcher(
+        "Docker Hub images",
+        keyword.clone(),
+        schedule,
+        pool,
+        move |window| fetch(keyword.clone(), window),
+    )
+}
@@ -0,0 +1,189 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Mutex;
+use tokio::task;
+
+use crate::bus::{self, Event};
+
+// Why a fetcher run failed, as a label for the error counters and logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ErrorKind {
+    Auth,
+    RateLimit,
+    Network,
+    Parse,
+    Db,
+    Other,
+}
+
+impl ErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::Auth => "auth",
+            ErrorKind::RateLimit => "rate_limit",
+            ErrorKind::Network => "network",
+            ErrorKind::Parse => "parse",
+            ErrorKind::Db => "db",
+            ErrorKind::Other => "other",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FetchError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.kind.as_str())
+    }
+}
+
+impl FetchError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        FetchError {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+// Problems the fetcher found itself, e.g. an API reporting an error in its
+// body. Fetchers that know better use `FetchError::new` with the kind.
+impl From<String> for FetchError {
+    fn from(message: String) -> Self {
+        FetchError::new(ErrorKind::Other, message)
+    }
+}
+
+fn reqwest_kind(e: &reqwest::Error) -> ErrorKind {
+    match e.status().map(|status| status.as_u16()) {
+        Some(401) | Some(403) => ErrorKind::Auth,
+        Some(429) => ErrorKind::RateLimit,
+        Some(status) if status >= 500 => ErrorKind::Network,
+        Some(_) => ErrorKind::Other,
+        None if e.is_decode() => ErrorKind::Parse,
+        None if e.is_builder() => ErrorKind::Other,
+        None => ErrorKind::Network,
+    }
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(e: reqwest::Error) -> Self {
+        FetchError::new(reqwest_kind(&e), format!("{}", e))
+    }
+}
+
+impl From<serde_json::Error> for FetchError {
+    fn from(e: serde_json::Error) -> Self {
+        FetchError::new(ErrorKind::Parse, format!("{}", e))
+    }
+}
+
+impl From<rss::Error> for FetchError {
+    fn from(e: rss::Error) -> Self {
+        FetchError::new(ErrorKind::Parse, format!("{}", e))
+    }
+}
+
+impl From<mysql::Error> for FetchError {
+    fn from(e: mysql::Error) -> Self {
+        FetchError::new(ErrorKind::Db, format!("{}", e))
+    }
+}
+
+// The JSON body of a successful response. Error statuses come first, so an
+// expired token is an auth error and not a body that doesn't parse.
+pub async fn json<T: DeserializeOwned>(resp: reqwest::Response) -> Result<T, FetchError> {
+    Ok(resp.error_for_status()?.json::<T>().await?)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorCount {
+    pub fetcher: String,
+    pub kind: &'static str,
+    pub count: u64,
+}
+
+static ERRORS: Mutex<BTreeMap<(String, ErrorKind), u64>> = Mutex::new(BTreeMap::new());
+
+fn record(fetcher: &str, kind: ErrorKind) {
+    *ERRORS
+        .lock()
+        .unwrap()
+        .entry((fetcher.to_string(), kind))
+        .or_default() += 1;
+}
+
+// Counts the failed runs published on the bus, from startup on.
+pub fn spawn_counter() {
+    let mut events = bus::subscribe("error counter");
+    task::spawn(async move {
+        while let Some(event) = events.recv().await {
+            if let Event::SourceFailed { fetcher, kind } = event {
+                record(fetcher, kind);
+            }
+        }
+    });
+}
+
+// Failed runs since startup, by fetcher and kind.
+pub fn counts() -> Vec<ErrorCount> {
+    ERRORS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|((fetcher, kind), count)| ErrorCount {
+            fetcher: fetcher.clone(),
+            kind: kind.as_str(),
+            count: *count,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifier::tests::serve;
+
+    async fn fetch(url: &str) -> Result<serde_json::Value, FetchError> {
+        json(reqwest::get(url).await?).await
+    }
+
+    #[tokio::test]
+    async fn classifies_http_errors_by_status() {
+        for (status, kind) in [
+            (401, ErrorKind::Auth),
+            (403, ErrorKind::Auth),
+            (429, ErrorKind::RateLimit),
+            (503, ErrorKind::Network),
+            (404, ErrorKind::Other),
+            // The test server answers with an empty body.
+            (200, ErrorKind::Parse),
+        ] {
+            let error = fetch(&serve(vec![status]).await).await.unwrap_err();
+            assert_eq!(error.kind, kind, "{}", status);
+        }
+
+        // Nothing listens on port 1.
+        let error = fetch("http://127.0.0.1:1/").await.unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Network);
+    }
+
+    #[test]
+    fn classifies_errors_by_type() {
+        let parse = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        assert_eq!(FetchError::from(parse).kind, ErrorKind::Parse);
+        let rss = rss::Channel::read_from(&b"<rss>"[..]).unwrap_err();
+        assert_eq!(FetchError::from(rss).kind, ErrorKind::Parse);
+        let db = mysql::Error::from(mysql::Opts::from_url("garbage").unwrap_err());
+        assert_eq!(FetchError::from(db).kind, ErrorKind::Db);
+        // A message that happens to mention a status is not an auth error.
+        let other = FetchError::from(String::from("401 items found"));
+        assert_eq!(other.kind, ErrorKind::Other);
+    }
+}
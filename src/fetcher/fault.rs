@@ -0,0 +1,163 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use tokio::time;
+
+use crate::fetcher::error::{ErrorKind, FetchError};
+
+// How long an injected timeout hangs before failing, like a slow upstream.
+const INJECTED_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Chaos testing for development: `FAULT_TWITTER=timeout:0.2,429:0.1,500:0.05`
+// fails that share of the runs of a source's fetchers the way its API would,
+// to exercise backoff, error counting and alerting. Keys are the prefixes of
+// the source sections, e.g. `FAULT_STACKEXCHANGE`. Only read when built with
+// the `fault-injection` feature.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Faults {
+    timeout: f64,
+    rate_limited: f64,
+    server_error: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    Timeout,
+    RateLimited,
+    ServerError,
+}
+
+impl Faults {
+    pub fn parse(spec: &str) -> Result<Faults, String> {
+        let mut faults = Faults::default();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (kind, share) = entry
+                .split_once(':')
+                .ok_or_else(|| format!("expected kind:share, got {}", entry))?;
+            let share: f64 = share
+                .parse()
+                .ok()
+                .filter(|share| (0.0..=1.0).contains(share))
+                .ok_or_else(|| format!("{}: share has to be between 0 and 1", entry))?;
+            match kind {
+                "timeout" => faults.timeout = share,
+                "429" => faults.rate_limited = share,
+                "500" => faults.server_error = share,
+                other => return Err(format!("unknown fault {}, use timeout, 429 or 500", other)),
+            }
+        }
+        if faults.timeout + faults.rate_limited + faults.server_error > 1.0 {
+            return Err(format!("{}: shares add up to more than 1", spec));
+        }
+        Ok(faults)
+    }
+
+    // The fault to inject into this run, if any.
+    pub fn roll(&self) -> Option<Fault> {
+        self.pick(random())
+    }
+
+    fn pick(&self, roll: f64) -> Option<Fault> {
+        if roll < self.timeout {
+            Some(Fault::Timeout)
+        } else if roll < self.timeout + self.rate_limited {
+            Some(Fault::RateLimited)
+        } else if roll < self.timeout + self.rate_limited + self.server_error {
+            Some(Fault::ServerError)
+        } else {
+            None
+        }
+    }
+}
+
+impl Fault {
+    // Fails like reqwest would, with the kind reqwest's error would get.
+    pub async fn inject(self) -> FetchError {
+        let (kind, message) = match self {
+            Fault::Timeout => {
+                time::sleep(INJECTED_TIMEOUT).await;
+                (
+                    ErrorKind::Network,
+                    "error sending request: operation timed out",
+                )
+            }
+            Fault::RateLimited => (
+                ErrorKind::RateLimit,
+                "HTTP status client error (429 Too Many Requests)",
+            ),
+            Fault::ServerError => (
+                ErrorKind::Network,
+                "HTTP status server error (500 Internal Server Error)",
+            ),
+        };
+        FetchError::new(kind, format!("{} (injected fault)", message))
+    }
+}
+
+// A fresh RandomState is randomly keyed, good enough for dice rolls without
+// pulling in a random number crate.
+fn random() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// `FAULT_<PREFIX>` variables by section prefix, e.g. `TWITTER`.
+#[cfg(feature = "fault-injection")]
+pub fn from_env() -> Result<Vec<(String, Faults)>, String> {
+    let mut faults = vec![];
+    for (name, spec) in std::env::vars() {
+        if let Some(prefix) = name.strip_prefix("FAULT_") {
+            let parsed = Faults::parse(&spec).map_err(|e| format!("{}: {}", name, e))?;
+            tracing::warn!("Injecting faults into {} fetchers: {}", prefix, spec);
+            faults.push((prefix.to_string(), parsed));
+        }
+    }
+    Ok(faults)
+}
+
+#[cfg(not(feature = "fault-injection"))]
+pub fn from_env() -> Result<Vec<(String, Faults)>, String> {
+    if std::env::vars().any(|(name, _)| name.starts_with("FAULT_")) {
+        tracing::warn!("Ignoring FAULT_* variables, build with --features fault-injection");
+    }
+    Ok(vec![])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_fault_specs() {
+        let faults = Faults::parse("timeout:0.2, 429:0.1,500:0.05").unwrap();
+        assert_eq!(
+            faults,
+            Faults {
+                timeout: 0.2,
+                rate_limited: 0.1,
+                server_error: 0.05,
+            }
+        );
+        assert!(Faults::parse("timeout").is_err());
+        assert!(Faults::parse("503:0.1").is_err());
+        assert!(Faults::parse("429:1.5").is_err());
+        assert!(Faults::parse("429:0.6,500:0.6").is_err());
+    }
+
+    #[test]
+    fn picks_faults_by_share() {
+        let faults = Faults::parse("timeout:0.2,429:0.1,500:0.05").unwrap();
+        assert_eq!(faults.pick(0.1), Some(Fault::Timeout));
+        assert_eq!(faults.pick(0.25), Some(Fault::RateLimited));
+        assert_eq!(faults.pick(0.32), Some(Fault::ServerError));
+        assert_eq!(faults.pick(0.5), None);
+        assert_eq!(Faults::default().roll(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn injected_faults_are_classified() {
+        assert_eq!(Fault::RateLimited.inject().await.kind, ErrorKind::RateLimit);
+        assert_eq!(Fault::ServerError.inject().await.kind, ErrorKind::Network);
+        assert_eq!(Fault::Timeout.inject().await.kind, ErrorKind::Network);
+    }
+}
@@ -0,0 +1,158 @@
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::error;
+
+use crate::fetcher::base::{self, Fetched, NewestFirst, Schedule, Shareable, Window};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::{self, FetchError};
+use crate::http;
+
+#[derive(Debug, Deserialize)]
+struct GitHubIssue {
+    html_url: String,
+    title: String,
+    state: String,
+    comments: i32,
+    created_at: String,
+    updated_at: String,
+    pull_request: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubSearchResponse {
+    total_count: usize,
+    items: Vec<GitHubIssue>,
+}
+
+const PER_PAGE: usize = 100;
+// Search never returns more than the first 1000 results of a query.
+const MAX_RESULTS: usize = 1000;
+
+async fn fetch_github_api(
+    token: &str,
+    query: &str,
+    sort: &str,
+    page: usize,
+) -> Result<GitHubSearchResponse, FetchError> {
+    let resp = match http::client()
+        .get("https://api.github.com/search/issues")
+        .query(&[
+            ("q", query),
+            ("sort", sort),
+            ("order", "desc"),
+            ("per_page", &PER_PAGE.to_string()),
+            ("page", &page.to_string()),
+        ])
+        .bearer_auth(token)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await
+    {
+        Ok(resp) => match error::json::<GitHubSearchResponse>(resp).await {
+            Ok(json) => json,
+            Err(err) => {
+                error!("Could not parse GitHub API: {}", err);
+                return Err(err);
+            }
+        },
+        Err(e) => {
+            error!("GitHub responded with an Error exit code: {}", e);
+            return Err(FetchError::from(e));
+        }
+    };
+
+    base::log_sample("GitHub response", &resp.items);
+    Ok(resp)
+}
+
+fn to_shareable(item: &GitHubIssue) -> Shareable {
+    let state = if item.state == "closed" {
+        ":white_check_mark:"
+    } else if item.comments > 0 {
+        ":waiting-spin:"
+    } else {
+        ":question:"
+    };
+    let kind = if item.pull_request.is_some() {
+        "PR"
+    } else {
+        "Issue"
+    };
+
+    Shareable {
+        id: base::item_id("github", &item.html_url),
+        title: format!("{} - {}: {}", state, kind, item.title),
+        date: normalize_date(&item.created_at, DateFormat::Rfc3339),
+        url: item.html_url.clone(),
+        source: String::from("github"),
+        canonical_url: None,
+    }
+}
+
+// With a cursor (the start of the previous run) we only ask for issues
+// updated since then, which also picks up state changes of older ones. The
+// most recently updated come first, runs that stop at their limits fill the
+// gap down to the previous cursor on the next runs.
+async fn fetch(token: String, keyword: String, window: Window) -> Result<Fetched, FetchError> {
+    let started_at = (Utc::now() - Duration::minutes(1))
+        .format("%Y-%m-%dT%H:%M:%SZ")
+        .to_string();
+    let cursor = NewestFirst::<String>::parse(window.cursor.as_deref());
+    let (query, sort) = match (&cursor.after, &cursor.before) {
+        (Some(after), Some(before)) => (
+            format!("{} updated:{}..{}", keyword, after, before),
+            "updated",
+        ),
+        (Some(after), None) => (format!("{} updated:>={}", keyword, after), "updated"),
+        (None, _) => (keyword, "created"),
+    };
+
+    let mut budget = window.budget();
+    let mut shareables = vec![];
+    let mut oldest: Option<String> = None;
+    let mut page = 1;
+    let mut reached_end = true;
+    while budget.next_page() {
+        let data = fetch_github_api(&token, &query, sort, page).await?;
+        for item in data.items.iter() {
+            if !budget.keep(&mut shareables, [to_shareable(item)]) {
+                break;
+            }
+            oldest = Some(match oldest {
+                Some(oldest) => oldest.min(item.updated_at.clone()),
+                None => item.updated_at.clone(),
+            });
+        }
+        if budget.exhausted() || page * PER_PAGE >= data.total_count {
+            break;
+        }
+        if page * PER_PAGE >= MAX_RESULTS {
+            reached_end = false;
+            break;
+        }
+        page += 1;
+    }
+    let complete = reached_end && !budget.exhausted();
+
+    Ok(Fetched {
+        shareables,
+        cursor: cursor.advance(Some(started_at), oldest, complete),
+    })
+}
+
+pub fn spawn_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+    github_token: String,
+) -> JoinHandle<()> {
+    base::spawn_fetcher(
+        "GitHub issues and PRs",
+        keyword.clone(),
+        schedule,
+        pool,
+        move |window| fetch(github_token.clone(), keyword.clone(), window),
+    )
+}
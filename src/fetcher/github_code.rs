@@ -0,0 +1,97 @@
+use chrono::{SecondsFormat, Utc};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::error;
+
+use crate::fetcher::base::{self, Schedule, Shareable};
+use crate::fetcher::error::{self, FetchError};
+use crate::http;
+
+#[derive(Debug, Deserialize)]
+struct GitHubRepository {
+    full_name: String,
+    html_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubCodeResult {
+    path: String,
+    repository: GitHubRepository,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubCodeSearchResponse {
+    items: Vec<GitHubCodeResult>,
+}
+
+// TODO: walk through pagination if needed
+async fn fetch_github_api(
+    token: String,
+    query: String,
+) -> Result<GitHubCodeSearchResponse, FetchError> {
+    let resp = match http::client()
+        .get("https://api.github.com/search/code")
+        .query(&[("q", query.as_str()), ("per_page", "100")])
+        .bearer_auth(token)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await
+    {
+        Ok(resp) => match error::json::<GitHubCodeSearchResponse>(resp).await {
+            Ok(json) => json,
+            Err(err) => {
+                error!("Could not parse GitHub code search API: {}", err);
+                return Err(err);
+            }
+        },
+        Err(e) => {
+            error!(
+                "GitHub code search responded with an Error exit code: {}",
+                e
+            );
+            return Err(FetchError::from(e));
+        }
+    };
+
+    base::log_sample("GitHub code search response", &resp.items);
+    Ok(resp)
+}
+
+// Code search returns single files, we only want to hear about a repository
+// once. Results have no dates, so the date is when we first saw the repository.
+async fn fetch(token: String, keyword: String) -> Result<Vec<Shareable>, FetchError> {
+    let data = fetch_github_api(token, keyword).await?;
+    let seen_at = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+
+    let mut shareables: Vec<Shareable> = data
+        .items
+        .into_iter()
+        .map(|item| Shareable {
+            id: base::item_id("github-code", &item.repository.full_name),
+            title: format!("Code: {} ({})", item.repository.full_name, item.path),
+            date: seen_at.clone(),
+            url: item.repository.html_url,
+            source: String::from("github"),
+            canonical_url: None,
+        })
+        .collect();
+    base::dedup_shareables(&mut shareables);
+
+    Ok(shareables)
+}
+
+pub fn spawn_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+    github_token: String,
+) -> JoinHandle<()> {
+    base::spawn_fetcher(
+        "GitHub code search results",
+        keyword.clone(),
+        schedule,
+        pool,
+        move |_| fetch(github_token.clone(), keyword.clone()),
+    )
+}
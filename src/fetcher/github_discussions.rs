@@ -0,0 +1,180 @@
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::error;
+
+use crate::fetcher::base::{self, Schedule, Shareable, Window};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::{self, ErrorKind, FetchError};
+use crate::http;
+
+const SEARCH_DISCUSSIONS: &str = r"query SearchDiscussions($query: String!) {
+  search(query: $query, type: DISCUSSION, first: 100) {
+    nodes {
+      ... on Discussion {
+        url
+        title
+        createdAt
+        answer { id }
+        comments { totalCount }
+      }
+    }
+  }
+}";
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DiscussionComments {
+    total_count: i32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Discussion {
+    url: String,
+    title: String,
+    created_at: String,
+    answer: Option<serde_json::Value>,
+    comments: DiscussionComments,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscussionSearch {
+    nodes: Vec<Discussion>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscussionData {
+    search: DiscussionSearch,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscussionResponse {
+    data: Option<DiscussionData>,
+    errors: Option<Vec<serde_json::Value>>,
+}
+
+// A scope is either an organization ("hashicorp") or a repository
+// ("hashicorp/terraform-cdk").
+fn search_query(keyword: &str, scope: &str) -> String {
+    if scope.contains('/') {
+        format!("{} repo:{}", keyword, scope)
+    } else {
+        format!("{} org:{}", keyword, scope)
+    }
+}
+
+// TODO: walk through pagination if needed
+async fn fetch_github_graphql(token: String, query: String) -> Result<Vec<Discussion>, FetchError> {
+    let resp = match http::client()
+        .post("https://api.github.com/graphql")
+        .bearer_auth(token)
+        .json(&json!({
+            "query": SEARCH_DISCUSSIONS,
+            "variables": { "query": query },
+        }))
+        .send()
+        .await
+    {
+        Ok(resp) => match error::json::<DiscussionResponse>(resp).await {
+            Ok(json) => json,
+            Err(err) => {
+                error!("Could not parse GitHub GraphQL API: {}", err);
+                return Err(err);
+            }
+        },
+        Err(e) => {
+            error!("GitHub GraphQL responded with an Error exit code: {}", e);
+            return Err(FetchError::from(e));
+        }
+    };
+
+    match resp.data {
+        Some(data) => {
+            base::log_sample("GitHub GraphQL response", &data.search.nodes);
+            Ok(data.search.nodes)
+        }
+        None => {
+            let errors = resp.errors.unwrap_or_default();
+            // GraphQL errors come with a 200, the type says what went wrong.
+            let kind = if errors.iter().any(|error| error["type"] == "RATE_LIMITED") {
+                ErrorKind::RateLimit
+            } else {
+                ErrorKind::Other
+            };
+            Err(FetchError::new(
+                kind,
+                format!("GitHub GraphQL returned errors: {:?}", errors),
+            ))
+        }
+    }
+}
+
+async fn fetch(
+    token: String,
+    keyword: String,
+    scopes: Vec<String>,
+    window: Window,
+) -> Result<Vec<Shareable>, FetchError> {
+    let mut budget = window.budget();
+    let mut shareables: Vec<Shareable> = vec![];
+
+    for scope in scopes {
+        if !budget.next_page() {
+            break;
+        }
+        let discussions =
+            fetch_github_graphql(token.clone(), search_query(&keyword, &scope)).await?;
+
+        let kept = budget.keep(
+            &mut shareables,
+            discussions.into_iter().map(|item| {
+                let state = if item.answer.is_some() {
+                    ":white_check_mark:"
+                } else if item.comments.total_count > 0 {
+                    ":waiting-spin:"
+                } else {
+                    ":question:"
+                };
+
+                Shareable {
+                    id: base::item_id("github-discussion", &item.url),
+                    title: format!("{} - {}", state, item.title),
+                    date: normalize_date(&item.created_at, DateFormat::Rfc3339),
+                    url: item.url,
+                    source: String::from("github"),
+                    canonical_url: None,
+                }
+            }),
+        );
+        if !kept {
+            break;
+        }
+    }
+
+    Ok(shareables)
+}
+
+pub fn spawn_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+    github_token: String,
+    scopes: Vec<String>,
+) -> JoinHandle<()> {
+    base::spawn_fetcher(
+        "GitHub discussions",
+        keyword.clone(),
+        schedule,
+        pool,
+        move |window| {
+            fetch(
+                github_token.clone(),
+                keyword.clone(),
+                scopes.clone(),
+                window,
+            )
+        },
+    )
+}
@@ -0,0 +1,218 @@
+use chrono::{Duration, Utc};
+use reqwest::RequestBuilder;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::error;
+
+use crate::fetcher::base::{self, Schedule, Shareable, Window};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::{self, FetchError};
+use crate::http;
+
+#[derive(Debug, Deserialize)]
+struct GitHubRelease {
+    id: u64,
+    tag_name: String,
+    name: Option<String>,
+    html_url: String,
+    published_at: Option<String>,
+    draft: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubRepository {
+    full_name: String,
+    html_url: String,
+    description: Option<String>,
+    created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubRepositorySearchResponse {
+    items: Vec<GitHubRepository>,
+}
+
+// Works without a token too, but then shares the 60 requests per hour limit.
+async fn fetch_github_api<T: DeserializeOwned>(
+    request: RequestBuilder,
+    token: Option<&str>,
+) -> Result<T, FetchError> {
+    let request = match token {
+        Some(token) => request.bearer_auth(token),
+        None => request,
+    };
+    match request
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+    {
+        Ok(resp) => match error::json::<T>(resp).await {
+            Ok(json) => Ok(json),
+            Err(err) => {
+                error!("Could not parse GitHub API: {}", err);
+                Err(err)
+            }
+        },
+        Err(e) => {
+            error!("GitHub responded with an Error exit code: {}", e);
+            Err(FetchError::from(e))
+        }
+    }
+}
+
+async fn fetch_releases(token: Option<&str>, repo: &str) -> Result<Vec<Shareable>, FetchError> {
+    let releases: Vec<GitHubRelease> = fetch_github_api(
+        http::client()
+            .get(format!("https://api.github.com/repos/{}/releases", repo))
+            .query(&[("per_page", "10")]),
+        token,
+    )
+    .await?;
+    base::log_sample("GitHub releases", &releases);
+
+    Ok(releases
+        .into_iter()
+        .filter(|release| !release.draft)
+        .map(|release| Shareable {
+            id: base::item_id("github-release", release.id),
+            title: format!(
+                "Release: {} {}",
+                repo,
+                release
+                    .name
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or(release.tag_name)
+            ),
+            date: normalize_date(
+                &release.published_at.unwrap_or_default(),
+                DateFormat::Rfc3339,
+            ),
+            url: release.html_url,
+            source: String::from("github"),
+            canonical_url: None,
+        })
+        .collect())
+}
+
+// GitHub has no trending API. New repositories with the keyword in their
+// description, by stars, come closest to the trending page.
+async fn fetch_trending(token: Option<&str>, keyword: &str) -> Result<Vec<Shareable>, FetchError> {
+    let created_after = (Utc::now() - Duration::days(7)).format("%Y-%m-%d");
+    let query = format!("{} in:description created:>{}", keyword, created_after);
+    let resp: GitHubRepositorySearchResponse = fetch_github_api(
+        http::client()
+            .get("https://api.github.com/search/repositories")
+            .query(&[
+                ("q", query.as_str()),
+                ("sort", "stars"),
+                ("order", "desc"),
+                ("per_page", "30"),
+            ]),
+        token,
+    )
+    .await?;
+    base::log_sample("GitHub trending repositories", &resp.items);
+
+    Ok(resp.items.into_iter().map(trending_shareable).collect())
+}
+
+// Nothing that changes between runs, like the star count, goes into the item,
+// so a repository found again is the same item and not an update.
+fn trending_shareable(repo: GitHubRepository) -> Shareable {
+    Shareable {
+        id: base::item_id("github-trending", &repo.full_name),
+        title: format!(
+            "Trending: {} {}",
+            repo.full_name,
+            repo.description.unwrap_or_default()
+        ),
+        date: normalize_date(&repo.created_at, DateFormat::Rfc3339),
+        url: repo.html_url,
+        source: String::from("github"),
+        canonical_url: None,
+    }
+}
+
+async fn fetch(
+    token: Option<String>,
+    keyword: String,
+    repos: Vec<String>,
+    trending: bool,
+    window: Window,
+) -> Result<Vec<Shareable>, FetchError> {
+    let mut budget = window.budget();
+    let mut shareables = vec![];
+    for repo in repos.iter() {
+        if !budget.next_page() {
+            return Ok(shareables);
+        }
+        if !budget.keep(
+            &mut shareables,
+            fetch_releases(token.as_deref(), repo).await?,
+        ) {
+            return Ok(shareables);
+        }
+    }
+    if trending && budget.next_page() {
+        budget.keep(
+            &mut shareables,
+            fetch_trending(token.as_deref(), &keyword).await?,
+        );
+    }
+    Ok(shareables)
+}
+
+pub fn spawn_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+    github_token: Option<String>,
+    repos: Vec<String>,
+    trending: bool,
+) -> JoinHandle<()> {
+    base::spawn_fetcher(
+        "GitHub releases",
+        keyword.clone(),
+        schedule,
+        pool,
+        move |window| {
+            fetch(
+                github_token.clone(),
+                keyword.clone(),
+                repos.clone(),
+                trending,
+                window,
+            )
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trending_items_do_not_change_with_stars() {
+        let repo = |stars: u64| -> GitHubRepository {
+            serde_json::from_value(serde_json::json!({
+                "full_name": "hashicorp/cdktf-provider-foo",
+                "html_url": "https://github.com/hashicorp/cdktf-provider-foo",
+                "description": "A cdktf provider",
+                "stargazers_count": stars,
+                "created_at": "2022-06-01T12:00:00Z",
+            }))
+            .unwrap()
+        };
+        let first = trending_shareable(repo(12));
+        let again = trending_shareable(repo(40));
+        assert_eq!(first.id, again.id);
+        assert_eq!(first.title, again.title);
+        assert_eq!(
+            first.title,
+            "Trending: hashicorp/cdktf-provider-foo A cdktf provider"
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::error;
+
+use crate::fetcher::base::{self, Schedule, Shareable, Window};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::{self, FetchError};
+use crate::http;
+
+#[derive(Debug, Deserialize)]
+struct GitLabIssue {
+    web_url: String,
+    title: String,
+    state: String,
+    user_notes_count: i32,
+    created_at: String,
+}
+
+// Issues and merge requests share the fields we need, only the scope differs.
+// TODO: walk through pagination if needed
+async fn fetch_gitlab_api(
+    base_url: &str,
+    token: &str,
+    scope: &str,
+    keyword: &str,
+) -> Result<Vec<GitLabIssue>, FetchError> {
+    let resp = match http::client()
+        .get(format!("{}/api/v4/search", base_url.trim_end_matches('/')))
+        .query(&[
+            ("scope", scope),
+            ("search", keyword),
+            ("order_by", "created_at"),
+            ("sort", "desc"),
+            ("per_page", "100"),
+        ])
+        .header("PRIVATE-TOKEN", token)
+        .send()
+        .await
+    {
+        Ok(resp) => match error::json::<Vec<GitLabIssue>>(resp).await {
+            Ok(json) => json,
+            Err(err) => {
+                error!("Could not parse GitLab API of {}: {}", base_url, err);
+                return Err(err);
+            }
+        },
+        Err(e) => {
+            error!("{} responded with an Error exit code: {}", base_url, e);
+            return Err(FetchError::from(e));
+        }
+    };
+
+    base::log_sample("GitLab response", &resp);
+    Ok(resp)
+}
+
+async fn fetch(
+    base_url: String,
+    token: String,
+    keyword: String,
+    window: Window,
+) -> Result<Vec<Shareable>, FetchError> {
+    let mut budget = window.budget();
+    let mut shareables: Vec<Shareable> = vec![];
+
+    for (scope, kind) in [("issues", "Issue"), ("merge_requests", "MR")] {
+        if !budget.next_page() {
+            break;
+        }
+        let items = fetch_gitlab_api(&base_url, &token, scope, &keyword).await?;
+
+        let kept = budget.keep(
+            &mut shareables,
+            items.into_iter().map(|item| {
+                let state = if item.state == "closed" || item.state == "merged" {
+                    ":white_check_mark:"
+                } else if item.user_notes_count > 0 {
+                    ":waiting-spin:"
+                } else {
+                    ":question:"
+                };
+
+                Shareable {
+                    id: base::item_id("gitlab", &item.web_url),
+                    title: format!("{} - {}: {}", state, kind, item.title),
+                    date: normalize_date(&item.created_at, DateFormat::Rfc3339),
+                    url: item.web_url,
+                    source: String::from("gitlab"),
+                    canonical_url: None,
+                }
+            }),
+        );
+        if !kept {
+            break;
+        }
+    }
+
+    Ok(shareables)
+}
+
+pub fn spawn_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+    base_url: String,
+    gitlab_token: String,
+) -> JoinHandle<()> {
+    base::spawn_fetcher(
+        "GitLab issues and MRs",
+        keyword.clone(),
+        schedule,
+        pool,
+        move |window| {
+            fetch(
+                base_url.clone(),
+                gitlab_token.clone(),
+                keyword.clone(),
+                window,
+            )
+        },
+    )
+}
@@ -0,0 +1,174 @@
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::error;
+
+use crate::fetcher::base::{self, Fetched, NewestFirst, Schedule, Shareable, Window};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::{self, FetchError};
+use crate::http;
+
+#[derive(Debug, Deserialize)]
+struct HackerNewsComment {
+    #[serde(rename = "objectID")]
+    object_id: String,
+    author: Option<String>,
+    comment_text: Option<String>,
+    story_title: Option<String>,
+    created_at_i: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct HackerNewsResponse {
+    hits: Vec<HackerNewsComment>,
+    #[serde(rename = "nbPages", default)]
+    nb_pages: u32,
+}
+
+// Comments come as HTML, titles are plain text.
+fn plain_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => {
+                in_tag = true;
+                text.push(' ');
+            }
+            '>' => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.replace("&quot;", "\"")
+        .replace("&#x27;", "'")
+        .replace("&#x2F;", "/")
+        .replace("&gt;", ">")
+        .replace("&lt;", "<")
+        .replace("&amp;", "&")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Newest first, comments created after `after` and up to `before`.
+async fn fetch_comments(
+    keyword: &str,
+    after: Option<i64>,
+    before: Option<i64>,
+    page: u32,
+) -> Result<HackerNewsResponse, FetchError> {
+    let mut filters = vec![];
+    if let Some(after) = after {
+        filters.push(format!("created_at_i>{}", after));
+    }
+    // Comments of the same second as the oldest one kept may have been left
+    // out, so the bound is inclusive.
+    if let Some(before) = before {
+        filters.push(format!("created_at_i<={}", before));
+    }
+    let mut query = vec![
+        (String::from("query"), keyword.to_string()),
+        (String::from("tags"), String::from("comment")),
+        (String::from("hitsPerPage"), String::from("100")),
+        (String::from("page"), page.to_string()),
+    ];
+    if !filters.is_empty() {
+        query.push((String::from("numericFilters"), filters.join(",")));
+    }
+
+    match http::client()
+        .get("https://hn.algolia.com/api/v1/search_by_date")
+        .query(&query)
+        .send()
+        .await
+    {
+        Ok(resp) => match error::json::<HackerNewsResponse>(resp).await {
+            Ok(json) => {
+                base::log_sample("Hacker News comments", &json.hits);
+                Ok(json)
+            }
+            Err(err) => {
+                error!("Could not parse Hacker News API: {}", err);
+                Err(err)
+            }
+        },
+        Err(e) => {
+            error!("Hacker News responded with an Error exit code: {}", e);
+            Err(FetchError::from(e))
+        }
+    }
+}
+
+fn to_shareable(comment: HackerNewsComment) -> Shareable {
+    let text = plain_text(&comment.comment_text.unwrap_or_default());
+    let title = match comment.story_title {
+        Some(story) => format!(
+            "{} on \"{}\": {}",
+            comment.author.unwrap_or_default(),
+            story,
+            text
+        ),
+        None => format!("{}: {}", comment.author.unwrap_or_default(), text),
+    };
+
+    Shareable {
+        id: base::item_id("hackernews-comment", &comment.object_id),
+        title,
+        date: normalize_date(&comment.created_at_i.to_string(), DateFormat::UnixTimestamp),
+        url: format!("https://news.ycombinator.com/item?id={}", comment.object_id),
+        source: String::from("hackernews"),
+        canonical_url: None,
+    }
+}
+
+// The cursor is the creation time of the newest comment seen, after a longer
+// gap the catch-up window reaches further back. Runs that stop at their
+// limits fill the gap down to the previous cursor on the next runs.
+async fn fetch(keyword: String, window: Window) -> Result<Fetched, FetchError> {
+    let mut cursor = NewestFirst::<i64>::parse(window.cursor.as_deref());
+    if let Some(since) = window.since {
+        cursor.after = Some(since.timestamp());
+    }
+    let mut budget = window.budget();
+    let mut shareables = vec![];
+    let mut newest = None;
+    let mut oldest = None;
+    let mut page = 0;
+
+    while budget.next_page() {
+        let resp = fetch_comments(&keyword, cursor.after, cursor.before, page).await?;
+        for comment in resp.hits {
+            let created_at = comment.created_at_i;
+            if !budget.keep(&mut shareables, [to_shareable(comment)]) {
+                break;
+            }
+            newest = newest.max(Some(created_at));
+            oldest = Some(oldest.map_or(created_at, |oldest: i64| oldest.min(created_at)));
+        }
+        page += 1;
+        if budget.exhausted() || page >= resp.nb_pages {
+            break;
+        }
+    }
+    let complete = !budget.exhausted();
+
+    Ok(Fetched {
+        shareables,
+        cursor: cursor.advance(newest, oldest, complete),
+    })
+}
+
+pub fn spawn_comment_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+) -> JoinHandle<()> {
+    base::spawn_fetcher(
+        "Hacker News comments",
+        keyword.clone(),
+        schedule,
+        pool,
+        move |window| fetch(keyword.clone(), window),
+    )
+}
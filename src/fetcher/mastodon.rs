@@ -0,0 +1,204 @@
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::{error, info, trace};
+
+use crate::fetcher::base::{self, Schedule, Shareable, Window};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::{self, FetchError};
+use crate::http;
+
+#[derive(Debug, Deserialize, Clone)]
+struct MastodonAccount {
+    acct: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct MastodonStatus {
+    uri: String,
+    url: Option<String>,
+    created_at: String,
+    content: String,
+    account: MastodonAccount,
+    reblog: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MastodonSearchResponse {
+    statuses: Vec<MastodonStatus>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MastodonInstance {
+    pub url: String,
+    pub access_token: Option<String>,
+}
+
+// Toots come as HTML, the dashboard wants plain text.
+fn strip_html(content: &str) -> String {
+    let mut text = String::with_capacity(content.len());
+    let mut in_tag = false;
+    for c in content.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => {
+                in_tag = false;
+                text.push(' ');
+            }
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    text.split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        // Last, so `&amp;lt;` stays the text `&lt;` instead of becoming `<`.
+        .replace("&amp;", "&")
+}
+
+async fn fetch_hashtag(
+    instance: &MastodonInstance,
+    keyword: &str,
+) -> Result<Vec<MastodonStatus>, FetchError> {
+    let hashtag: String = keyword.chars().filter(|c| c.is_alphanumeric()).collect();
+    let mut request = http::client()
+        .get(format!(
+            "{}/api/v1/timelines/tag/{}",
+            instance.url.trim_end_matches('/'),
+            hashtag
+        ))
+        .query(&[("limit", "40")]);
+    if let Some(token) = &instance.access_token {
+        request = request.bearer_auth(token);
+    }
+
+    match request.send().await {
+        Ok(resp) => match error::json::<Vec<MastodonStatus>>(resp).await {
+            Ok(json) => Ok(json),
+            Err(err) => {
+                error!("Could not parse Mastodon API of {}: {}", instance.url, err);
+                Err(err)
+            }
+        },
+        Err(e) => {
+            error!("{} responded with an Error exit code: {}", instance.url, e);
+            Err(FetchError::from(e))
+        }
+    }
+}
+
+// Full text search needs an authenticated user on most instances.
+async fn fetch_search(
+    instance: &MastodonInstance,
+    token: &str,
+    keyword: &str,
+) -> Result<Vec<MastodonStatus>, FetchError> {
+    match http::client()
+        .get(format!(
+            "{}/api/v2/search",
+            instance.url.trim_end_matches('/')
+        ))
+        .query(&[("q", keyword), ("type", "statuses"), ("limit", "40")])
+        .bearer_auth(token)
+        .send()
+        .await
+    {
+        Ok(resp) => match error::json::<MastodonSearchResponse>(resp).await {
+            Ok(json) => Ok(json.statuses),
+            Err(err) => {
+                error!(
+                    "Could not parse Mastodon search of {}: {}",
+                    instance.url, err
+                );
+                Err(err)
+            }
+        },
+        Err(e) => {
+            error!("{} responded with an Error exit code: {}", instance.url, e);
+            Err(FetchError::from(e))
+        }
+    }
+}
+
+async fn fetch(
+    instances: Vec<MastodonInstance>,
+    keyword: String,
+    window: Window,
+) -> Result<Vec<Shareable>, FetchError> {
+    let mut budget = window.budget();
+    let mut shareables: Vec<Shareable> = vec![];
+
+    'instances: for instance in instances.iter() {
+        if !budget.next_page() {
+            break;
+        }
+        let mut statuses = fetch_hashtag(instance, &keyword).await?;
+        if let Some(token) = &instance.access_token {
+            if budget.next_page() {
+                statuses.extend(fetch_search(instance, token, &keyword).await?);
+            }
+        }
+        info!("Found {} toots on {}", statuses.len(), instance.url);
+
+        for item in statuses {
+            let item_id = base::item_id("mastodon", &item.uri);
+
+            if item.reblog.is_some() {
+                trace!("Skipping toot {} because it is a boost", item_id);
+                continue;
+            }
+
+            let shareable = Shareable {
+                id: item_id,
+                title: format!("@{}: {}", item.account.acct, strip_html(&item.content)),
+                date: normalize_date(&item.created_at, DateFormat::Rfc3339),
+                url: item.url.unwrap_or(item.uri),
+                source: String::from("mastodon"),
+                canonical_url: None,
+            };
+            if !budget.keep(&mut shareables, [shareable]) {
+                break 'instances;
+            }
+        }
+    }
+
+    base::dedup_shareables(&mut shareables);
+    Ok(shareables)
+}
+
+pub fn spawn_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+    instances: Vec<MastodonInstance>,
+) -> JoinHandle<()> {
+    base::spawn_fetcher("toots", keyword.clone(), schedule, pool, move |window| {
+        fetch(instances.clone(), keyword.clone(), window)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_tags_and_decodes_entities() {
+        assert_eq!(
+            strip_html("<p>Trying <a href=\"https://cdk.tf\">cdktf</a> &amp; loving it</p>"),
+            "Trying cdktf & loving it"
+        );
+    }
+
+    #[test]
+    fn decodes_entities_only_once() {
+        assert_eq!(
+            strip_html("<p>&amp;lt;script&amp;gt;alert(1)&amp;lt;/script&amp;gt;</p>"),
+            "&lt;script&gt;alert(1)&lt;/script&gt;"
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+use crate::fetcher::base::{self, Fetched, Schedule, Shareable, Window};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::{self, FetchError};
+use crate::http;
+
+#[derive(Debug, Deserialize)]
+struct JoinResponse {
+    room_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MatrixContent {
+    body: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MatrixEvent {
+    event_id: String,
+    sender: String,
+    origin_server_ts: i64,
+    content: MatrixContent,
+}
+
+#[derive(Debug, Deserialize)]
+struct MatrixTimeline {
+    events: Vec<MatrixEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MatrixJoinedRoom {
+    timeline: MatrixTimeline,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct MatrixRooms {
+    #[serde(default)]
+    join: HashMap<String, MatrixJoinedRoom>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SyncResponse {
+    next_batch: String,
+    #[serde(default)]
+    rooms: MatrixRooms,
+}
+
+#[derive(Debug, Clone)]
+pub struct MatrixAccount {
+    pub homeserver: String,
+    pub access_token: String,
+}
+
+// Room ids (!abc:server), aliases (#room:server) and event ids ($abc) all need
+// escaping in paths and matrix.to links.
+fn escape_id(id: &str) -> String {
+    id.replace('!', "%21")
+        .replace('#', "%23")
+        .replace('$', "%24")
+        .replace(':', "%3A")
+}
+
+// Joining a room the bot is already in is a no-op, and it also resolves
+// aliases to room ids.
+async fn join_rooms(account: &MatrixAccount, rooms: &[String]) -> Result<Vec<String>, FetchError> {
+    let client = http::client();
+    let mut room_ids = vec![];
+    for room in rooms {
+        let resp = match client
+            .post(format!(
+                "{}/_matrix/client/v3/join/{}",
+                account.homeserver,
+                escape_id(room)
+            ))
+            .bearer_auth(&account.access_token)
+            .json(&json!({}))
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+        {
+            Ok(resp) => match error::json::<JoinResponse>(resp).await {
+                Ok(json) => json,
+                Err(err) => {
+                    error!("Could not parse Matrix join response: {}", err);
+                    return Err(err);
+                }
+            },
+            Err(e) => {
+                error!("Could not join Matrix room {}: {}", room, e);
+                return Err(FetchError::from(e));
+            }
+        };
+        info!("Joined Matrix room {} ({})", room, resp.room_id);
+        room_ids.push(resp.room_id);
+    }
+    Ok(room_ids)
+}
+
+async fn sync(
+    account: &MatrixAccount,
+    room_ids: &[String],
+    since: Option<&str>,
+    limit: usize,
+) -> Result<SyncResponse, FetchError> {
+    let filter = json!({
+        "room": {
+            "rooms": room_ids,
+            "timeline": { "types": ["m.room.message"], "limit": limit.clamp(1, 100) },
+            "state": { "types": [] },
+            "ephemeral": { "types": [] },
+            "account_data": { "types": [] }
+        },
+        "presence": { "types": [] },
+        "account_data": { "types": [] }
+    })
+    .to_string();
+    let mut query = vec![("timeout", "0"), ("filter", filter.as_str())];
+    if let Some(since) = since {
+        query.push(("since", since));
+    }
+
+    match http::client()
+        .get(format!("{}/_matrix/client/v3/sync", account.homeserver))
+        .query(&query)
+        .bearer_auth(&account.access_token)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+    {
+        Ok(resp) => match error::json::<SyncResponse>(resp).await {
+            Ok(json) => Ok(json),
+            Err(err) => {
+                error!("Could not parse Matrix sync: {}", err);
+                Err(err)
+            }
+        },
+        Err(e) => {
+            error!("Matrix responded with an Error: {}", e);
+            Err(FetchError::from(e))
+        }
+    }
+}
+
+async fn fetch(
+    account: MatrixAccount,
+    keyword: String,
+    room_ids: &[String],
+    window: Window,
+) -> Result<Fetched, FetchError> {
+    // The timeline limit is per room, so together they stay within the run
+    // limits. A sync can't be resumed halfway, what still does not fit is
+    // skipped.
+    let limits = window.limits.split(room_ids.len());
+    let resp = sync(
+        &account,
+        room_ids,
+        window.cursor.as_deref(),
+        limits.max_items,
+    )
+    .await?;
+    let keyword = keyword.to_lowercase();
+
+    let matching = resp
+        .rooms
+        .join
+        .into_iter()
+        // The filter already limits rooms, but the bot may be in others.
+        .filter(|(room_id, _)| room_ids.contains(room_id))
+        .flat_map(|(room_id, room)| {
+            room.timeline
+                .events
+                .into_iter()
+                .map(move |event| (room_id.clone(), event))
+        })
+        .filter_map(|(room_id, event)| {
+            let body = event.content.body?;
+            if !body.to_lowercase().contains(&keyword) {
+                return None;
+            }
+
+            Some(Shareable {
+                id: base::item_id("matrix", &event.event_id),
+                title: format!("{}: {}", event.sender, body),
+                date: normalize_date(
+                    &(event.origin_server_ts / 1000).to_string(),
+                    DateFormat::UnixTimestamp,
+                ),
+                url: format!(
+                    "https://matrix.to/#/{}/{}",
+                    escape_id(&room_id),
+                    escape_id(&event.event_id)
+                ),
+                source: String::from("matrix"),
+                canonical_url: None,
+            })
+        });
+    let mut shareables = vec![];
+    window.budget().keep(&mut shareables, matching);
+
+    Ok(Fetched {
+        shareables,
+        cursor: Some(resp.next_batch),
+    })
+}
+
+pub fn spawn_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+    account: MatrixAccount,
+    rooms: Vec<String>,
+) -> JoinHandle<()> {
+    // Rooms are joined on the first run, and again after a failed attempt.
+    let room_ids: Arc<OnceCell<Vec<String>>> = Arc::new(OnceCell::new());
+    base::spawn_fetcher(
+        "Matrix messages",
+        keyword.clone(),
+        schedule,
+        pool,
+        move |window| {
+            let account = account.clone();
+            let keyword = keyword.clone();
+            let rooms = rooms.clone();
+            let room_ids = room_ids.clone();
+            async move {
+                let room_ids = room_ids
+                    .get_or_try_init(|| join_rooms(&account, &rooms))
+                    .await?;
+                fetch(account, keyword, room_ids, window).await
+            }
+        },
+    )
+}
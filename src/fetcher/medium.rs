@@ -0,0 +1,88 @@
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::{debug, error};
+
+use crate::fetcher::base::{self, Schedule, Shareable};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::FetchError;
+use crate::http;
+
+// Medium has no public search API, the tag feed is the stable way in.
+async fn fetch_medium_feed(tag: &str) -> Result<rss::Channel, FetchError> {
+    let body = match http::client()
+        .get(format!("https://medium.com/feed/tag/{}", tag))
+        .send()
+        .await
+    {
+        Ok(resp) => match resp.bytes().await {
+            Ok(body) => body,
+            Err(err) => {
+                error!("Could not read Medium feed: {}", err);
+                return Err(FetchError::from(err));
+            }
+        },
+        Err(e) => {
+            error!("Medium responded with an Error exit code: {}", e);
+            return Err(FetchError::from(e));
+        }
+    };
+
+    match rss::Channel::read_from(&body[..]) {
+        Ok(channel) => {
+            debug!("Medium feed has {} items", channel.items().len());
+            Ok(channel)
+        }
+        Err(err) => {
+            error!("Could not parse Medium feed: {}", err);
+            Err(FetchError::from(err))
+        }
+    }
+}
+
+async fn fetch(keyword: String) -> Result<Vec<Shareable>, FetchError> {
+    let tag: String = keyword
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-");
+    let channel = fetch_medium_feed(&tag).await?;
+
+    Ok(channel
+        .items()
+        .iter()
+        .filter_map(|item| {
+            let url = item.link()?.to_string();
+            // Links carry tracking parameters, the guid is the stable post URL.
+            let id = item.guid().map(|guid| guid.value()).unwrap_or(&url);
+            let date = normalize_date(item.pub_date().unwrap_or_default(), DateFormat::Rfc2822);
+            let title = match item.dublin_core_ext().and_then(|dc| dc.creators().first()) {
+                Some(author) => format!("{} (by {})", item.title().unwrap_or_default(), author),
+                None => item.title().unwrap_or_default().to_string(),
+            };
+
+            Some(Shareable {
+                id: base::item_id("medium", id),
+                title,
+                date,
+                url,
+                source: String::from("medium"),
+                canonical_url: None,
+            })
+        })
+        .collect())
+}
+
+pub fn spawn_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+) -> JoinHandle<()> {
+    base::spawn_fetcher(
+        "Medium articles",
+        keyword.clone(),
+        schedule,
+        pool,
+        move |_| fetch(keyword.clone()),
+    )
+}
@@ -1,3 +1,29 @@
 pub mod base;
+pub mod bluesky;
+pub mod date;
+pub mod devto;
+pub mod discord;
+pub mod dockerhub;
+pub mod error;
+pub mod fault;
+pub mod github;
+pub mod github_code;
+pub mod github_discussions;
+pub mod github_releases;
+pub mod gitlab;
+pub mod hackernews;
+pub mod mastodon;
+pub mod matrix;
+pub mod medium;
+pub mod news;
+pub mod packages;
+pub mod podcast;
+pub mod quora;
+pub mod slack;
+pub mod spill;
 pub mod stackoverflow;
+pub mod telegram;
+pub mod terraform;
+pub mod twitch;
 pub mod twitter;
+pub mod wikipedia;
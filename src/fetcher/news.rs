@@ -0,0 +1,102 @@
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::{debug, error};
+
+use crate::fetcher::base::{self, Schedule, Shareable};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::FetchError;
+use crate::http;
+
+pub const GOOGLE_NEWS_FEED: &str =
+    "https://news.google.com/rss/search?q={keyword}&hl=en-US&gl=US&ceid=US:en";
+
+// Also used for other RSS sources, e.g. Quora topics.
+pub async fn fetch_feed(url: &str) -> Result<rss::Channel, FetchError> {
+    let body = match http::client().get(url).send().await {
+        Ok(resp) => match resp.bytes().await {
+            Ok(body) => body,
+            Err(err) => {
+                error!("Could not read feed {}: {}", url, err);
+                return Err(FetchError::from(err));
+            }
+        },
+        Err(e) => {
+            error!("Feed {} responded with an Error exit code: {}", url, e);
+            return Err(FetchError::from(e));
+        }
+    };
+
+    match rss::Channel::read_from(&body[..]) {
+        Ok(channel) => {
+            debug!("Feed {} has {} items", url, channel.items().len());
+            Ok(channel)
+        }
+        Err(err) => {
+            error!("Could not parse feed {}: {}", url, err);
+            Err(FetchError::from(err))
+        }
+    }
+}
+
+// Google News appends " - Publisher" to every headline and names the
+// publisher in <source>, other feeds usually have neither.
+fn news_title(item: &rss::Item) -> String {
+    let title = item.title().unwrap_or_default();
+    match item.source().and_then(|source| source.title()) {
+        Some(publisher) => format!(
+            "{} ({})",
+            title.trim_end_matches(&format!(" - {}", publisher)),
+            publisher
+        ),
+        None => title.to_string(),
+    }
+}
+
+// Feed URLs take the keyword as a query parameter.
+pub fn url_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+async fn fetch(feed_url: String, keyword: String) -> Result<Vec<Shareable>, FetchError> {
+    let channel = fetch_feed(&feed_url.replace("{keyword}", &url_encode(&keyword))).await?;
+
+    Ok(channel
+        .items()
+        .iter()
+        .filter_map(|item| {
+            let url = item.link()?.to_string();
+            let id = item.guid().map(|guid| guid.value()).unwrap_or(&url);
+
+            Some(Shareable {
+                id: base::item_id("news", id),
+                title: news_title(item),
+                date: normalize_date(item.pub_date().unwrap_or_default(), DateFormat::Rfc2822),
+                url,
+                source: String::from("news"),
+                canonical_url: None,
+            })
+        })
+        .collect())
+}
+
+pub fn spawn_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+    feed_url: String,
+) -> JoinHandle<()> {
+    base::spawn_fetcher(
+        "news articles",
+        keyword.clone(),
+        schedule,
+        pool,
+        move |_| fetch(feed_url.clone(), keyword.clone()),
+    )
+}
@@ -0,0 +1,177 @@
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::error;
+
+use crate::fetcher::base::{self, Schedule, Shareable, Window};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::{self, FetchError};
+use crate::http;
+
+#[derive(Debug, Deserialize)]
+struct Crate {
+    name: String,
+    max_version: String,
+    description: Option<String>,
+    updated_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CratesResponse {
+    crates: Vec<Crate>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NpmLinks {
+    npm: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct NpmPackage {
+    name: String,
+    version: String,
+    description: Option<String>,
+    date: String,
+    links: NpmLinks,
+}
+
+#[derive(Debug, Deserialize)]
+struct NpmSearchObject {
+    package: NpmPackage,
+}
+
+#[derive(Debug, Deserialize)]
+struct NpmSearchResponse {
+    objects: Vec<NpmSearchObject>,
+}
+
+fn release_title(name: &str, version: &str, description: Option<&str>) -> String {
+    match description {
+        Some(description) if !description.is_empty() => {
+            format!("{} {}: {}", name, version, description)
+        }
+        _ => format!("{} {}", name, version),
+    }
+}
+
+async fn fetch_crates(keyword: &str) -> Result<Vec<Shareable>, FetchError> {
+    let resp = match http::client()
+        .get("https://crates.io/api/v1/crates")
+        .query(&[
+            ("q", keyword),
+            ("sort", "recent-updates"),
+            ("per_page", "50"),
+        ])
+        // crates.io rejects requests without a user agent
+        .send()
+        .await
+    {
+        Ok(resp) => match error::json::<CratesResponse>(resp).await {
+            Ok(json) => json,
+            Err(err) => {
+                error!("Could not parse crates.io API: {}", err);
+                return Err(err);
+            }
+        },
+        Err(e) => {
+            error!("crates.io responded with an Error exit code: {}", e);
+            return Err(FetchError::from(e));
+        }
+    };
+
+    base::log_sample("crates.io response", &resp.crates);
+    Ok(resp
+        .crates
+        .into_iter()
+        .map(|item| Shareable {
+            id: base::item_id("crates", format!("{}-{}", item.name, item.max_version)),
+            title: release_title(&item.name, &item.max_version, item.description.as_deref()),
+            date: normalize_date(&item.updated_at, DateFormat::Rfc3339),
+            url: format!(
+                "https://crates.io/crates/{}/{}",
+                item.name, item.max_version
+            ),
+            source: String::from("crates"),
+            canonical_url: None,
+        })
+        .collect())
+}
+
+async fn fetch_npm(keyword: &str) -> Result<Vec<Shareable>, FetchError> {
+    let resp = match http::client()
+        .get("https://registry.npmjs.org/-/v1/search")
+        .query(&[("text", keyword), ("size", "50")])
+        .send()
+        .await
+    {
+        Ok(resp) => match error::json::<NpmSearchResponse>(resp).await {
+            Ok(json) => json,
+            Err(err) => {
+                error!("Could not parse npm API: {}", err);
+                return Err(err);
+            }
+        },
+        Err(e) => {
+            error!("npm responded with an Error exit code: {}", e);
+            return Err(FetchError::from(e));
+        }
+    };
+
+    base::log_sample("npm response", &resp.objects);
+    Ok(resp
+        .objects
+        .into_iter()
+        .map(|object| object.package)
+        .map(|item| Shareable {
+            id: base::item_id("npm", format!("{}-{}", item.name, item.version)),
+            title: release_title(&item.name, &item.version, item.description.as_deref()),
+            date: normalize_date(&item.date, DateFormat::Rfc3339),
+            url: format!("{}/v/{}", item.links.npm, item.version),
+            source: String::from("npm"),
+            canonical_url: None,
+        })
+        .collect())
+}
+
+// Every release gets its own ID, so a new version of a known package shows
+// up as a new item.
+async fn fetch(
+    keyword: String,
+    registries: Vec<String>,
+    window: Window,
+) -> Result<Vec<Shareable>, FetchError> {
+    let mut budget = window.budget();
+    let mut shareables: Vec<Shareable> = vec![];
+
+    for registry in registries.iter() {
+        let releases = match registry.as_str() {
+            "crates" | "npm" if !budget.next_page() => break,
+            "crates" => fetch_crates(&keyword).await?,
+            "npm" => fetch_npm(&keyword).await?,
+            other => {
+                error!("Unknown package registry {}, skipping", other);
+                continue;
+            }
+        };
+        if !budget.keep(&mut shareables, releases) {
+            break;
+        }
+    }
+
+    Ok(shareables)
+}
+
+pub fn spawn_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+    registries: Vec<String>,
+) -> JoinHandle<()> {
+    base::spawn_fetcher(
+        "package releases",
+        keyword.clone(),
+        schedule,
+        pool,
+        move |window| fetch(keyword.clone(), registries.clone(), window),
+    )
+}
@@ -0,0 +1,103 @@
+use chrono::{SecondsFormat, TimeZone, Utc};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::error;
+
+use crate::fetcher::base::{self, Schedule, Shareable};
+use crate::fetcher::error::{self, FetchError};
+use crate::http;
+
+#[derive(Debug, Deserialize)]
+struct ListenNotesPodcast {
+    title_original: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListenNotesEpisode {
+    id: String,
+    title_original: String,
+    listennotes_url: String,
+    pub_date_ms: i64,
+    podcast: ListenNotesPodcast,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListenNotesSearchResponse {
+    results: Vec<ListenNotesEpisode>,
+}
+
+// Listen Notes searches transcripts too on paid plans, we only want episodes
+// that mention the keyword in their title or show notes.
+async fn fetch_listen_notes_api(
+    api_key: String,
+    keyword: String,
+) -> Result<ListenNotesSearchResponse, FetchError> {
+    let resp = match http::client()
+        .get("https://listen-api.listennotes.com/api/v2/search")
+        .query(&[
+            ("q", keyword.as_str()),
+            ("type", "episode"),
+            ("only_in", "title,description"),
+            ("sort_by_date", "1"),
+        ])
+        .header("X-ListenAPI-Key", api_key)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+    {
+        Ok(resp) => match error::json::<ListenNotesSearchResponse>(resp).await {
+            Ok(json) => json,
+            Err(err) => {
+                error!("Could not parse Listen Notes API: {}", err);
+                return Err(err);
+            }
+        },
+        Err(e) => {
+            error!("Listen Notes responded with an Error exit code: {}", e);
+            return Err(FetchError::from(e));
+        }
+    };
+
+    base::log_sample("Listen Notes response", &resp.results);
+    Ok(resp)
+}
+
+async fn fetch(api_key: String, keyword: String) -> Result<Vec<Shareable>, FetchError> {
+    let data = fetch_listen_notes_api(api_key, keyword).await?;
+
+    Ok(data
+        .results
+        .into_iter()
+        .map(|episode| Shareable {
+            id: base::item_id("podcast", &episode.id),
+            title: format!(
+                "{} ({})",
+                episode.title_original, episode.podcast.title_original
+            ),
+            date: Utc
+                .timestamp_millis_opt(episode.pub_date_ms)
+                .single()
+                .map(|date| date.to_rfc3339_opts(SecondsFormat::Secs, true))
+                .unwrap_or_default(),
+            url: episode.listennotes_url,
+            source: String::from("podcast"),
+            canonical_url: None,
+        })
+        .collect())
+}
+
+pub fn spawn_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+    api_key: String,
+) -> JoinHandle<()> {
+    base::spawn_fetcher(
+        "podcast episodes",
+        keyword.clone(),
+        schedule,
+        pool,
+        move |_| fetch(api_key.clone(), keyword.clone()),
+    )
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+use crate::fetcher::base::{self, Schedule, Shareable, Window};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::FetchError;
+use crate::fetcher::news::{fetch_feed, url_encode};
+
+// Topic feeds list recent questions, with the top answer as description once
+// there is one.
+async fn fetch(
+    topics: Vec<String>,
+    keyword: String,
+    window: Window,
+) -> Result<Vec<Shareable>, FetchError> {
+    let keyword = keyword.to_lowercase();
+    let mut budget = window.budget();
+    let mut shareables: Vec<Shareable> = vec![];
+
+    for topic in topics.iter() {
+        if !budget.next_page() {
+            break;
+        }
+        let channel = fetch_feed(&format!(
+            "https://www.quora.com/topic/{}/rss",
+            url_encode(topic)
+        ))
+        .await?;
+
+        let kept = budget.keep(
+            &mut shareables,
+            channel.items().iter().filter_map(|item| {
+                let url = item.link()?.to_string();
+                let question = item.title()?;
+                let answer = item.description().unwrap_or_default().trim();
+                if !question.to_lowercase().contains(&keyword)
+                    && !answer.to_lowercase().contains(&keyword)
+                {
+                    return None;
+                }
+
+                // Same markers as the Stack Exchange fetcher.
+                let state = if answer.is_empty() {
+                    ":question:"
+                } else {
+                    ":white_check_mark:"
+                };
+
+                Some(Shareable {
+                    id: base::item_id("quora", &url),
+                    title: format!("{} - {}", state, question),
+                    date: normalize_date(item.pub_date().unwrap_or_default(), DateFormat::Rfc2822),
+                    url,
+                    source: String::from("quora"),
+                    canonical_url: None,
+                })
+            }),
+        );
+        if !kept {
+            break;
+        }
+    }
+
+    // A question can be in several of the topics.
+    base::dedup_shareables(&mut shareables);
+    Ok(shareables)
+}
+
+pub fn spawn_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+    topics: Vec<String>,
+) -> JoinHandle<()> {
+    base::spawn_fetcher(
+        "Quora questions",
+        keyword.clone(),
+        schedule,
+        pool,
+        move |window| fetch(topics.clone(), keyword.clone(), window),
+    )
+}
@@ -0,0 +1,185 @@
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::error;
+
+use crate::fetcher::base::{self, Schedule, Shareable};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::{self, ErrorKind, FetchError};
+use crate::http;
+
+#[derive(Debug, Deserialize)]
+struct SlackChannel {
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SlackMessage {
+    ts: String,
+    text: String,
+    permalink: String,
+    username: Option<String>,
+    channel: SlackChannel,
+}
+
+#[derive(Debug, Deserialize)]
+struct SlackMatches {
+    matches: Vec<SlackMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SlackSearchResponse {
+    ok: bool,
+    error: Option<String>,
+    messages: Option<SlackMatches>,
+}
+
+// search.messages only works with a user token (xoxp-...), bot tokens are
+// rejected with "not_allowed_token_type".
+// TODO: walk through pagination if needed
+async fn fetch_slack_api(token: &str, keyword: &str) -> Result<Vec<SlackMessage>, FetchError> {
+    let resp = match http::client()
+        .get("https://slack.com/api/search.messages")
+        .query(&[
+            ("query", keyword),
+            ("sort", "timestamp"),
+            ("sort_dir", "desc"),
+            ("count", "100"),
+        ])
+        .bearer_auth(token)
+        .send()
+        .await
+    {
+        Ok(resp) => match error::json::<SlackSearchResponse>(resp).await {
+            Ok(json) => json,
+            Err(err) => {
+                error!("Could not parse Slack API: {}", err);
+                return Err(err);
+            }
+        },
+        Err(e) => {
+            error!("Slack responded with an Error exit code: {}", e);
+            return Err(FetchError::from(e));
+        }
+    };
+
+    // Slack reports errors with a 200 and ok: false
+    match (resp.ok, resp.messages) {
+        (true, Some(messages)) => {
+            base::log_sample("Slack response", &messages.matches);
+            Ok(messages.matches)
+        }
+        _ => {
+            let error = resp.error.unwrap_or_default();
+            let kind = match error.as_str() {
+                "invalid_auth"
+                | "not_authed"
+                | "token_revoked"
+                | "token_expired"
+                | "account_inactive"
+                | "not_allowed_token_type"
+                | "missing_scope" => ErrorKind::Auth,
+                "ratelimited" => ErrorKind::RateLimit,
+                _ => ErrorKind::Other,
+            };
+            Err(FetchError::new(
+                kind,
+                format!("Slack search failed: {}", error),
+            ))
+        }
+    }
+}
+
+// A reference in a message, e.g. `<https://cdk.tf|cdktf>`, `<@U123|eve>`,
+// `<#C123|general>` or `<!here>`, as the text Slack shows for it.
+fn reference(reference: &str) -> String {
+    let (target, label) = match reference.split_once('|') {
+        Some((target, label)) => (target, Some(label)),
+        None => (reference, None),
+    };
+    match (target.chars().next(), label) {
+        (Some('@'), Some(label)) => format!("@{}", label),
+        (Some('#'), Some(label)) => format!("#{}", label),
+        (Some('!'), _) => format!("@{}", target[1..].split('^').next().unwrap_or_default()),
+        (_, Some(label)) => label.to_string(),
+        (_, None) => target.to_string(),
+    }
+}
+
+// Messages come as Slack markup, the title gets the text without it.
+fn plain_text(text: &str) -> String {
+    let mut plain = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        match rest[start..].find('>') {
+            Some(end) => {
+                plain.push_str(&rest[..start]);
+                plain.push_str(&reference(&rest[start + 1..start + end]));
+                rest = &rest[start + end + 1..];
+            }
+            None => break,
+        }
+    }
+    plain.push_str(rest);
+    plain
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        // Last, so `&amp;lt;` stays the text `&lt;` instead of becoming `<`.
+        .replace("&amp;", "&")
+}
+
+async fn fetch(token: String, keyword: String) -> Result<Vec<Shareable>, FetchError> {
+    let messages = fetch_slack_api(&token, &keyword).await?;
+
+    Ok(messages
+        .into_iter()
+        .map(|item| {
+            // ts is "<unix seconds>.<sequence>"
+            let seconds = item.ts.split('.').next().unwrap_or_default();
+            let author = item.username.unwrap_or_default();
+            let channel = item.channel.name.unwrap_or_default();
+
+            Shareable {
+                id: base::item_id("slack", &item.permalink),
+                title: format!("#{} @{}: {}", channel, author, plain_text(&item.text)),
+                date: normalize_date(seconds, DateFormat::UnixTimestamp),
+                url: item.permalink,
+                source: String::from("slack"),
+                canonical_url: None,
+            }
+        })
+        .collect())
+}
+
+pub fn spawn_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+    slack_user_token: String,
+) -> JoinHandle<()> {
+    base::spawn_fetcher(
+        "Slack messages",
+        keyword.clone(),
+        schedule,
+        pool,
+        move |_| fetch(slack_user_token.clone(), keyword.clone()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_markup() {
+        assert_eq!(
+            plain_text("<!here> <@U123|eve> tried <https://cdk.tf|cdktf> in <#C123|general>"),
+            "@here @eve tried cdktf in #general"
+        );
+        assert_eq!(
+            plain_text("see <https://cdk.tf> &lt;script&gt; &amp;lt;b&amp;gt;"),
+            "see https://cdk.tf <script> &lt;b&gt;"
+        );
+        assert_eq!(plain_text("a < b"), "a < b");
+    }
+}
@@ -0,0 +1,265 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::{task, time};
+use tracing::{error, info, warn};
+
+use crate::fetcher::base::{self, Origin, Shareable};
+use crate::maintenance;
+use crate::migrations;
+use crate::shutdown;
+
+// Batches that could not be stored because the database was unavailable. They
+// are appended to a JSON lines file and drained in order once it is back.
+// None until `configure` is called, fetched items are dropped on DB errors then.
+static SPILL_FILE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Batch {
+    fetcher: String,
+    // Missing in batches spilled by older versions.
+    #[serde(default)]
+    keyword: String,
+    shareables: Vec<Shareable>,
+}
+
+pub fn configure(path: PathBuf) {
+    *SPILL_FILE.lock().unwrap() = Some(path);
+}
+
+// The batches and how many bytes of the file they are, batches pushed later
+// are appended after those.
+fn read_batches(path: &PathBuf) -> std::io::Result<(Vec<Batch>, usize)> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((vec![], 0)),
+        Err(e) => return Err(e),
+    };
+
+    let mut batches = vec![];
+    for line in content.lines() {
+        match serde_json::from_str(line) {
+            Ok(batch) => batches.push(batch),
+            Err(e) => error!("Dropping unreadable spilled batch: {}", e),
+        }
+    }
+    Ok((batches, content.len()))
+}
+
+// Whether batches are waiting, new ones have to queue up behind them to keep
+// the order.
+pub fn is_pending() -> bool {
+    match SPILL_FILE.lock().unwrap().as_ref() {
+        Some(path) => fs::metadata(path).map(|m| m.len() > 0).unwrap_or(false),
+        None => false,
+    }
+}
+
+// Returns false if spilling is not configured or the file is not writable.
+pub fn push(origin: Origin, shareables: &[Shareable]) -> bool {
+    let fetcher = origin.fetcher;
+    let spill_file = SPILL_FILE.lock().unwrap();
+    let path = match spill_file.as_ref() {
+        Some(path) => path,
+        None => return false,
+    };
+
+    let line = match serde_json::to_string(&Batch {
+        fetcher: fetcher.to_string(),
+        keyword: origin.keyword.to_string(),
+        shareables: shareables.to_vec(),
+    }) {
+        Ok(line) => line,
+        Err(e) => {
+            error!("Could not serialize batch of {}: {}", fetcher, e);
+            return false;
+        }
+    };
+    match OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{}", line))
+    {
+        Ok(_) => {
+            warn!(
+                "Spilled {} {} to {} until the database is back",
+                shareables.len(),
+                fetcher,
+                path.display()
+            );
+            true
+        }
+        Err(e) => {
+            error!("Could not spill {} to {}: {}", fetcher, path.display(), e);
+            false
+        }
+    }
+}
+
+// Batches spilled before the id migration have the legacy ids, which would be
+// stored next to the migrated rows as new items.
+fn rekey(batch: &mut Batch) {
+    for shareable in batch.shareables.iter_mut() {
+        if let Some(id) = migrations::rehashed_id(&shareable.id, &shareable.source) {
+            shareable.id = id;
+        }
+    }
+}
+
+// Stores batches oldest first and stops at the first failure. Returns how
+// many were stored.
+fn store(pool: &mysql::Pool, batches: &[Batch]) -> usize {
+    let mut stored = 0;
+    for batch in batches {
+        let origin = Origin {
+            fetcher: &batch.fetcher,
+            keyword: &batch.keyword,
+        };
+        let result = pool
+            .get_conn()
+            .and_then(|mut conn| base::insert_shareables(&mut conn, origin, &batch.shareables));
+        match result {
+            Ok(created) => {
+                base::inserted(origin, &created);
+                info!(
+                    "Stored {} new {} from the spill file",
+                    created.len(),
+                    batch.fetcher
+                );
+                stored += 1;
+            }
+            Err(e) => {
+                warn!("Database still unavailable, keeping spilled batches: {}", e);
+                break;
+            }
+        }
+    }
+    stored
+}
+
+// Replaces the first `taken` bytes of the file, the batches that were read,
+// with the ones not stored. Batches pushed since then stay behind them.
+fn keep(path: &PathBuf, taken: usize, remaining: &[Batch]) -> std::io::Result<()> {
+    let mut content = vec![];
+    for batch in remaining {
+        content.extend(
+            serde_json::to_string(batch)
+                .unwrap_or_default()
+                .into_bytes(),
+        );
+        content.push(b'\n');
+    }
+    let current = match fs::read(path) {
+        Ok(current) => current,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+        Err(e) => return Err(e),
+    };
+    content.extend(current.get(taken..).unwrap_or_default());
+    fs::write(path, content)
+}
+
+// Stores the spilled batches, the rest stays on disk for the next attempt.
+// The file is only locked to read and rewrite it, fetchers can keep spilling
+// while the batches are stored.
+async fn drain(pool: Arc<mysql::Pool>) -> std::io::Result<()> {
+    let (path, mut batches, taken) = {
+        let spill_file = SPILL_FILE.lock().unwrap();
+        let path = match spill_file.as_ref() {
+            Some(path) => path.clone(),
+            None => return Ok(()),
+        };
+        let (batches, taken) = read_batches(&path)?;
+        (path, batches, taken)
+    };
+    if batches.is_empty() {
+        return Ok(());
+    }
+    for batch in batches.iter_mut() {
+        rekey(batch);
+    }
+
+    let (batches, stored) = task::spawn_blocking(move || {
+        let stored = store(&pool, &batches);
+        (batches, stored)
+    })
+    .await
+    .map_err(std::io::Error::other)?;
+    if stored == 0 {
+        return Ok(());
+    }
+
+    let _spill_file = SPILL_FILE.lock().unwrap();
+    keep(&path, taken, &batches[stored..])
+}
+
+pub fn spawn_drainer(pool: Arc<mysql::Pool>) -> JoinHandle<()> {
+    task::spawn(async move {
+        let mut interval = time::interval(Duration::from_secs(10));
+        while shutdown::tick(&mut interval).await {
+            if maintenance::is_active() {
+                continue;
+            }
+            if let Err(e) = drain(pool.clone()).await {
+                error!("Could not drain the spill file: {}", e);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rekeys_legacy_ids() {
+        let shareable = |id: String| Shareable {
+            id,
+            title: String::from("cdktf is great"),
+            date: String::from("2022-06-01T12:00:00Z"),
+            url: String::from("https://twitter.com/twitter/status/1532"),
+            source: String::from("twitter"),
+            canonical_url: None,
+        };
+        let current = base::item_id("twitter", "1532");
+        let mut batch = Batch {
+            fetcher: String::from("tweets"),
+            keyword: String::from("cdktf"),
+            shareables: vec![
+                shareable(String::from("twitter-1532")),
+                shareable(current.clone()),
+            ],
+        };
+        rekey(&mut batch);
+        assert_eq!(batch.shareables[0].id, current);
+        assert_eq!(batch.shareables[1].id, current);
+    }
+
+    #[test]
+    fn keeps_batches_spilled_while_draining() {
+        let batch = |fetcher: &str| Batch {
+            fetcher: String::from(fetcher),
+            keyword: String::from("cdktf"),
+            shareables: vec![],
+        };
+        let path = std::env::temp_dir().join(format!("spill-{}.jsonl", std::process::id()));
+        let line = |fetcher: &str| format!("{}\n", serde_json::to_string(&batch(fetcher)).unwrap());
+        fs::write(&path, line("twitter") + &line("hackernews")).unwrap();
+        let (batches, taken) = read_batches(&path).unwrap();
+        assert_eq!(batches.len(), 2);
+
+        // Pushed while the first batch was stored.
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "{}", line("github")).unwrap();
+        keep(&path, taken, &batches[1..]).unwrap();
+
+        let (batches, _) = read_batches(&path).unwrap();
+        let fetchers: Vec<&str> = batches.iter().map(|batch| batch.fetcher.as_str()).collect();
+        assert_eq!(fetchers, vec!["hackernews", "github"]);
+        fs::remove_file(&path).unwrap();
+    }
+}
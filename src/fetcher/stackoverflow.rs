@@ -1,17 +1,19 @@
-use chrono::{TimeZone, Utc};
+use chrono::Utc;
 use mysql::params;
 use mysql::prelude::*;
 use serde::Deserialize;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::task::JoinError;
-use tokio::{task, time};
-use tracing::{debug, error, info};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
 
-use crate::fetcher::base::Shareable;
+use crate::fetcher::base::{self, Budget, Fetched, NewestFirst, Schedule, Shareable, Window};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::{self, FetchError};
+use crate::http;
 
 #[derive(Debug, Deserialize)]
 struct StackOverflowQuestion {
+    question_id: u64,
     is_answered: bool,
     link: String,
     title: String,
@@ -22,119 +24,289 @@ struct StackOverflowQuestion {
 #[derive(Debug, Deserialize)]
 struct StackOverflowResponse {
     items: Vec<StackOverflowQuestion>,
+    #[serde(default)]
+    has_more: bool,
+    quota_max: Option<i64>,
+    quota_remaining: Option<i64>,
+    // Seconds to wait before calling the same method again.
+    backoff: Option<u64>,
 }
 
-// TODO: walk through pagination if needed
-async fn fetch_stackoverflow_api(query: String) -> Result<StackOverflowResponse, String> {
-    let url = format!(
-        "https://api.stackexchange.com/2.3/search/advanced?order=desc&sort=activity&site=stackoverflow&q={}",
-        query
-    );
-    let resp = match reqwest::Client::builder()
-        .gzip(true)
-        .build()
-        .unwrap()
+impl StackOverflowResponse {
+    // Refreshing stops on a backoff or once less than QUOTA_RESERVE of the
+    // daily quota is left, so searching for new questions keeps working.
+    fn allows_refresh(&self) -> bool {
+        let quota_left = match (self.quota_remaining, self.quota_max) {
+            (Some(remaining), Some(max)) if max > 0 => {
+                remaining as f64 >= max as f64 * QUOTA_RESERVE
+            }
+            _ => true,
+        };
+        quota_left && self.backoff.is_none()
+    }
+}
+
+// The /questions/{ids} endpoint takes up to 100 ids per request.
+const IDS_PER_REQUEST: usize = 100;
+// Open questions refreshed per site and run, newest first.
+const MAX_REFRESHED_QUESTIONS: usize = 500;
+const QUOTA_RESERVE: f64 = 0.25;
+
+async fn call_stackoverflow_api(
+    site: &str,
+    mut url: String,
+    api_key: Option<&str>,
+) -> Result<StackOverflowResponse, FetchError> {
+    // Without a key the quota is 300 requests a day per IP, with one 10,000.
+    if let Some(api_key) = api_key {
+        url.push_str(&format!("&key={}", api_key));
+    }
+    // The API always answers gzipped, the shared client decompresses.
+    let resp = match http::client()
         .get(url)
         .header("Accept", "application/json; charset=utf-8")
         .send()
         .await
     {
-        Ok(resp) => {
-            // debug!("Response: {:?}", resp.json().await.unwrap());
-            match resp.json::<StackOverflowResponse>().await {
-                Ok(json) => json,
-                Err(err) => {
-                    error!("Could not parse stackoverflow API: {}", err);
-                    return Err(format!("{}", err));
-                }
+        Ok(resp) => match error::json::<StackOverflowResponse>(resp).await {
+            Ok(json) => json,
+            Err(err) => {
+                error!("Could not parse stackoverflow API for {}: {}", site, err);
+                return Err(err);
             }
-        }
+        },
         Err(e) => {
             error!("Stackoverflow resopnded with an Error exit code: {}", e);
-            return Err(format!("{}", e));
+            return Err(FetchError::from(e));
         }
     };
 
-    debug!("Stackoverflow response: {:?}", resp);
+    if let (Some(remaining), Some(max)) = (resp.quota_remaining, resp.quota_max) {
+        info!(
+            "Stack Exchange quota for {}: {} of {} requests left",
+            site, remaining, max
+        );
+    }
+    if let Some(backoff) = resp.backoff {
+        warn!("Stack Exchange asks to back off {} for {}s", site, backoff);
+    }
+    base::log_sample("Stackoverflow response", &resp.items);
     Ok(resp)
 }
 
-async fn fetch(mut conn: mysql::PooledConn, keyword: String) -> mysql::Result<()> {
-    info!("Fetching StackOverflow Questions");
+// By default this returns the questions with the most recent activity, with
+// `since` the questions created since then (and up to `until`), newest first.
+async fn search_stackoverflow_api(
+    site: &str,
+    query: &str,
+    api_key: Option<&str>,
+    since: Option<i64>,
+    until: Option<i64>,
+    page: u32,
+) -> Result<StackOverflowResponse, FetchError> {
+    let url = match since {
+        Some(since) => format!(
+            "https://api.stackexchange.com/2.3/search/advanced?order=desc&sort=creation&pagesize=100&page={}&fromdate={}{}&site={}&q={}",
+            page,
+            since,
+            until.map(|until| format!("&todate={}", until)).unwrap_or_default(),
+            site,
+            query
+        ),
+        None => format!(
+            "https://api.stackexchange.com/2.3/search/advanced?order=desc&sort=activity&site={}&q={}",
+            site, query
+        ),
+    };
+    call_stackoverflow_api(site, url, api_key).await
+}
 
-    let so_result = fetch_stackoverflow_api(keyword.to_string()).await;
+async fn load_questions(
+    site: &str,
+    ids: &[u64],
+    api_key: Option<&str>,
+) -> Result<StackOverflowResponse, FetchError> {
+    let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+    let url = format!(
+        "https://api.stackexchange.com/2.3/questions/{}?pagesize=100&site={}",
+        ids.join(";"),
+        site
+    );
+    call_stackoverflow_api(site, url, api_key).await
+}
 
-    let mut shareables: Vec<Shareable> = vec![];
-    match so_result {
-        Ok(data) => {
-            info!("Found {} StackOverflow Questions", data.items.len());
-            data.items.iter().for_each(|item| {
-                let item_id = format!("stackoverflow-{}", item.link.clone());
-
-                let date = Utc.timestamp(item.creation_date, 0);
-                let state = if item.is_answered {
-                    ":white_check_mark:"
-                } else if item.answer_count > 0 {
-                    ":waiting-spin:"
-                } else {
-                    ":question:"
-                };
-
-                shareables.push(Shareable {
-                    id: item_id,
-                    title: format!("{} - {}", state, item.title),
-                    date: date.date().to_string(),
-                    url: item.link.clone(),
-                    source: String::from("stackoverflow"),
-                });
-            });
-        }
-        Err(e) => {
-            error!("Could not fetch StackOverflow Questions, aborting{}", e);
-            return Ok(());
-        }
-    }
+// `https://stackoverflow.com/questions/7243/how-to-cdktf`
+fn question_id(url: &str) -> Option<u64> {
+    url.split_once("/questions/")?
+        .1
+        .split('/')
+        .next()?
+        .parse()
+        .ok()
+}
 
-    conn.exec_batch(
-        r"INSERT IGNORE INTO shareables (id, title, url, date, source)
-      VALUES (:id, :title, :url, :date, :source)",
-        shareables.iter().map(|p| {
-            params! {
-                "id" => p.id.clone(),
-                "title" => p.title.clone(),
-                "url" => p.url.clone(),
-                "date" => p.date.clone(),
-                "source" => p.source.clone()
-            }
-        }),
+// Stored questions that may still get an accepted answer.
+fn open_questions(pool: &mysql::Pool, site: &str) -> mysql::Result<Vec<u64>> {
+    let urls: Vec<String> = pool.get_conn()?.exec(
+        r"SELECT url FROM shareables
+          WHERE source = :site AND title NOT LIKE ':white_check_mark:%'
+          ORDER BY date DESC LIMIT :limit",
+        params! { "site" => site, "limit" => MAX_REFRESHED_QUESTIONS },
     )?;
+    Ok(urls.iter().filter_map(|url| question_id(url)).collect())
+}
+
+fn to_shareable(site: &str, item: &StackOverflowQuestion) -> Shareable {
+    let state = if item.is_answered {
+        ":white_check_mark:"
+    } else if item.answer_count > 0 {
+        ":waiting-spin:"
+    } else {
+        ":question:"
+    };
 
-    info!("Done fetching  StackOverflow Questions");
-    Ok(())
+    Shareable {
+        id: base::item_id(site, item.question_id),
+        title: format!("{} - {}", state, item.title),
+        date: normalize_date(&item.creation_date.to_string(), DateFormat::UnixTimestamp),
+        url: item.link.clone(),
+        source: site.to_string(),
+        canonical_url: None,
+    }
 }
 
-pub async fn spawn_fetcher(
-    interval_in_sec: u64,
+// Sources are the site, so "stackoverflow" items keep the source they had
+// before other Stack Exchange sites were supported.
+// The cursor is the start of the previous run (minus a minute for clock
+// skew), so each run only searches for questions created since then. Every
+// site gets an equal share of the run limits. A site that hit them leaves a
+// gap down to the previous cursor, which the next runs search first. Stored
+// questions without an accepted answer are reloaded by id in batches instead
+// of searching again, which picks up new answers for a fraction of the quota.
+async fn fetch(
     pool: Arc<mysql::Pool>,
     keyword: String,
-) -> Result<(), JoinError> {
-    let forever = task::spawn(async move {
-        let mut interval = time::interval(Duration::from_secs(interval_in_sec));
-
-        loop {
-            let conn = pool.get_conn().expect("Failed to get connection");
-            let res = fetch(conn, keyword.clone()).await;
-            match res {
-                Ok(_) => {
-                    info!("Fetched StackOverflow Questions, waiting...");
+    sites: Vec<String>,
+    api_key: Option<String>,
+    window: Window,
+) -> Result<Fetched, FetchError> {
+    let started_at = Utc::now().timestamp() - 60;
+    let mut cursor = NewestFirst::<i64>::parse(window.cursor.as_deref());
+    if cursor.after.is_none() {
+        cursor.after = window.since.map(|since| since.timestamp());
+    }
+    let limits = window.limits.split(sites.len());
+    let mut shareables: Vec<Shareable> = vec![];
+    // How far back the sites that hit their limits got, the gap has to
+    // reach up to the one that got the least far.
+    let mut reached: Option<i64> = None;
+    let mut complete = true;
+
+    for site in sites.iter() {
+        let mut budget = Budget::new(limits);
+        let mut oldest: Option<i64> = None;
+        let mut page = 1;
+        let mut refresh = true;
+        let mut searched_all = true;
+        while budget.next_page() {
+            let resp = search_stackoverflow_api(
+                site,
+                &keyword,
+                api_key.as_deref(),
+                cursor.after,
+                cursor.before,
+                page,
+            )
+            .await?;
+            refresh = resp.allows_refresh();
+            for item in resp.items.iter() {
+                if !budget.keep(&mut shareables, [to_shareable(site, item)]) {
+                    break;
                 }
+                oldest = Some(
+                    oldest.map_or(item.creation_date, |oldest| oldest.min(item.creation_date)),
+                );
+            }
+            // Without a cursor the search is by activity, one page of it.
+            if budget.exhausted() || !resp.has_more || cursor.after.is_none() {
+                break;
+            }
+            if !refresh {
+                searched_all = false;
+                break;
+            }
+            page += 1;
+        }
+        if !searched_all || budget.exhausted() {
+            complete = false;
+            let site_reached = oldest.or(cursor.before).unwrap_or(started_at);
+            reached = reached.max(Some(site_reached));
+        }
+
+        let open = match open_questions(&pool, site) {
+            Ok(open) => open,
+            Err(e) => {
+                error!("Could not load open questions of {}: {}", site, e);
+                vec![]
+            }
+        };
+        for ids in open.chunks(IDS_PER_REQUEST) {
+            if !refresh {
+                info!(
+                    "Skipping the refresh of open {} questions to save quota",
+                    site
+                );
+                break;
+            }
+            if !budget.next_page() {
+                break;
+            }
+            // The new questions found above are stored either way.
+            let resp = match load_questions(site, ids, api_key.as_deref()).await {
+                Ok(resp) => resp,
                 Err(e) => {
-                    error!("Error: {}", e);
+                    warn!("Could not refresh open {} questions: {}", site, e);
+                    break;
                 }
+            };
+            refresh = resp.allows_refresh();
+            if !budget.keep(
+                &mut shareables,
+                resp.items.iter().map(|item| to_shareable(site, item)),
+            ) {
+                break;
             }
-            interval.tick().await;
         }
-    });
+    }
+
+    base::dedup_shareables(&mut shareables);
+    Ok(Fetched {
+        shareables,
+        cursor: cursor.advance(Some(started_at), reached, complete),
+    })
+}
 
-    forever.await
+pub fn spawn_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+    sites: Vec<String>,
+    api_key: Option<String>,
+) -> JoinHandle<()> {
+    let fetch_pool = pool.clone();
+    base::spawn_fetcher(
+        "StackOverflow Questions",
+        keyword.clone(),
+        schedule,
+        pool,
+        move |window| {
+            fetch(
+                fetch_pool.clone(),
+                keyword.clone(),
+                sites.clone(),
+                api_key.clone(),
+                window,
+            )
+        },
+    )
 }
@@ -0,0 +1,176 @@
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::error;
+
+use crate::fetcher::base::{self, Fetched, Schedule, Shareable, Window};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::{ErrorKind, FetchError};
+use crate::http;
+
+#[derive(Debug, Deserialize)]
+struct TelegramChat {
+    username: Option<String>,
+    title: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramMessage {
+    message_id: i64,
+    date: i64,
+    chat: TelegramChat,
+    text: Option<String>,
+    caption: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramUpdate {
+    update_id: i64,
+    channel_post: Option<TelegramMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramResponse {
+    ok: bool,
+    // The HTTP status of failed requests, e.g. 401 for a revoked token.
+    error_code: Option<u16>,
+    description: Option<String>,
+    result: Option<Vec<TelegramUpdate>>,
+}
+
+// The Bot API only sees posts of channels the bot was added to as an admin.
+// Asking with an offset confirms all updates before it, so we only ever pass
+// the stored cursor and read one page per run, the rest comes next time.
+async fn fetch_updates(
+    token: &str,
+    offset: Option<&str>,
+    limit: usize,
+) -> Result<Vec<TelegramUpdate>, FetchError> {
+    let limit = limit.clamp(1, 100).to_string();
+    let mut query = vec![
+        ("limit", limit.as_str()),
+        ("allowed_updates", "[\"channel_post\"]"),
+    ];
+    if let Some(offset) = offset {
+        query.push(("offset", offset));
+    }
+
+    let resp = match http::client()
+        .get(format!("https://api.telegram.org/bot{}/getUpdates", token))
+        .query(&query)
+        .send()
+        .await
+    {
+        // Errors are logged without the URL, the token is part of it. Failed
+        // requests have a body too, with the reason.
+        Ok(resp) => match resp.json::<TelegramResponse>().await {
+            Ok(json) => json,
+            Err(err) => {
+                let err = err.without_url();
+                error!("Could not parse Telegram API: {}", err);
+                return Err(FetchError::from(err));
+            }
+        },
+        Err(e) => {
+            let e = e.without_url();
+            error!("Telegram responded with an Error: {}", e);
+            return Err(FetchError::from(e));
+        }
+    };
+
+    match (resp.ok, resp.result) {
+        (true, Some(updates)) => {
+            base::log_sample("Telegram updates", &updates);
+            Ok(updates)
+        }
+        _ => Err(FetchError::new(
+            match resp.error_code {
+                Some(401) | Some(403) => ErrorKind::Auth,
+                Some(429) => ErrorKind::RateLimit,
+                _ => ErrorKind::Other,
+            },
+            format!(
+                "Telegram getUpdates failed: {}",
+                resp.description.unwrap_or_default()
+            ),
+        )),
+    }
+}
+
+fn to_shareable(channels: &[String], keyword: &str, update: TelegramUpdate) -> Option<Shareable> {
+    let post = update.channel_post?;
+    // Only public channels have a username and t.me links.
+    let username = post.chat.username?;
+    if !channels
+        .iter()
+        .any(|channel| channel.eq_ignore_ascii_case(&username))
+    {
+        return None;
+    }
+    let text = post.text.or(post.caption)?;
+    if !text.to_lowercase().contains(keyword) {
+        return None;
+    }
+
+    Some(Shareable {
+        id: base::item_id("telegram", format!("{}-{}", username, post.message_id)),
+        title: format!(
+            "{}: {}",
+            post.chat.title.unwrap_or_else(|| username.clone()),
+            text
+        ),
+        date: normalize_date(&post.date.to_string(), DateFormat::UnixTimestamp),
+        url: format!("https://t.me/{}/{}", username, post.message_id),
+        source: String::from("telegram"),
+        canonical_url: None,
+    })
+}
+
+// Updates come oldest first, the cursor only moves past the ones kept.
+async fn fetch(
+    token: String,
+    keyword: String,
+    channels: Vec<String>,
+    window: Window,
+) -> Result<Fetched, FetchError> {
+    let mut budget = window.budget();
+    let updates = fetch_updates(&token, window.cursor.as_deref(), window.limits.max_items).await?;
+    let keyword = keyword.to_lowercase();
+
+    let mut shareables = vec![];
+    let mut cursor = window.cursor;
+    for update in updates {
+        let offset = update.update_id + 1;
+        if let Some(shareable) = to_shareable(&channels, &keyword, update) {
+            if !budget.keep(&mut shareables, [shareable]) {
+                break;
+            }
+        }
+        cursor = Some(offset.to_string());
+    }
+
+    Ok(Fetched { shareables, cursor })
+}
+
+pub fn spawn_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+    telegram_bot_token: String,
+    channels: Vec<String>,
+) -> JoinHandle<()> {
+    base::spawn_fetcher(
+        "Telegram posts",
+        keyword.clone(),
+        schedule,
+        pool,
+        move |window| {
+            fetch(
+                telegram_bot_token.clone(),
+                keyword.clone(),
+                channels.clone(),
+                window,
+            )
+        },
+    )
+}
@@ -0,0 +1,123 @@
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::error;
+
+use crate::fetcher::base::{self, Schedule, Shareable};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::{self, FetchError};
+use crate::http;
+
+// Modules and providers are listed with the same fields, only the URL of the
+// registry page differs.
+#[derive(Debug, Deserialize)]
+struct RegistryEntry {
+    id: String,
+    namespace: String,
+    name: String,
+    provider: Option<String>,
+    version: String,
+    description: Option<String>,
+    published_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModulesResponse {
+    modules: Vec<RegistryEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProvidersResponse {
+    providers: Vec<RegistryEntry>,
+}
+
+async fn fetch_registry_api<T: serde::de::DeserializeOwned>(
+    path: &str,
+    keyword: &str,
+) -> Result<T, FetchError> {
+    match http::client()
+        .get(format!("https://registry.terraform.io{}", path))
+        .query(&[("q", keyword), ("limit", "50")])
+        .send()
+        .await
+    {
+        Ok(resp) => match error::json::<T>(resp).await {
+            Ok(json) => Ok(json),
+            Err(err) => {
+                error!("Could not parse Terraform Registry API: {}", err);
+                Err(err)
+            }
+        },
+        Err(e) => {
+            error!(
+                "Terraform Registry responded with an Error exit code: {}",
+                e
+            );
+            Err(FetchError::from(e))
+        }
+    }
+}
+
+fn to_shareable(entry: RegistryEntry, kind: &str, url: String) -> Shareable {
+    let title = match entry.description.as_deref() {
+        Some(description) if !description.is_empty() => {
+            format!("{} {} {}: {}", kind, entry.id, entry.version, description)
+        }
+        _ => format!("{} {} {}", kind, entry.id, entry.version),
+    };
+
+    // The id already contains the version, so every publication is a new item.
+    Shareable {
+        id: base::item_id("terraform", format!("{}-{}", kind.to_lowercase(), entry.id)),
+        title,
+        date: normalize_date(&entry.published_at, DateFormat::Rfc3339),
+        url,
+        source: String::from("terraform"),
+        canonical_url: None,
+    }
+}
+
+async fn fetch(keyword: String) -> Result<Vec<Shareable>, FetchError> {
+    let modules: ModulesResponse = fetch_registry_api("/v1/modules/search", &keyword).await?;
+    let providers: ProvidersResponse = fetch_registry_api("/v1/providers", &keyword).await?;
+    base::log_sample("Terraform Registry modules", &modules.modules);
+    base::log_sample("Terraform Registry providers", &providers.providers);
+
+    let mut shareables: Vec<Shareable> = modules
+        .modules
+        .into_iter()
+        .map(|entry| {
+            let url = format!(
+                "https://registry.terraform.io/modules/{}/{}/{}/{}",
+                entry.namespace,
+                entry.name,
+                entry.provider.clone().unwrap_or_default(),
+                entry.version
+            );
+            to_shareable(entry, "Module", url)
+        })
+        .collect();
+    shareables.extend(providers.providers.into_iter().map(|entry| {
+        let url = format!(
+            "https://registry.terraform.io/providers/{}/{}/{}",
+            entry.namespace, entry.name, entry.version
+        );
+        to_shareable(entry, "Provider", url)
+    }));
+
+    Ok(shareables)
+}
+
+pub fn spawn_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+) -> JoinHandle<()> {
+    base::spawn_fetcher(
+        "Terraform Registry modules and providers",
+        keyword.clone(),
+        schedule,
+        pool,
+        move |_| fetch(keyword.clone()),
+    )
+}
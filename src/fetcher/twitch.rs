@@ -0,0 +1,179 @@
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+use crate::fetcher::base::{self, Schedule, Shareable};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::{self, ErrorKind, FetchError};
+use crate::http;
+
+#[derive(Debug, Clone)]
+pub struct TwitchApp {
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TwitchToken {
+    access_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TwitchChannel {
+    id: String,
+    broadcaster_login: String,
+    display_name: String,
+    title: String,
+    is_live: bool,
+    started_at: String,
+    game_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TwitchSearchResponse {
+    data: Vec<TwitchChannel>,
+}
+
+pub async fn fetch_app_token(app: &TwitchApp) -> Result<String, FetchError> {
+    match http::client()
+        .post("https://id.twitch.tv/oauth2/token")
+        .form(&[
+            ("client_id", app.client_id.as_str()),
+            ("client_secret", app.client_secret.as_str()),
+            ("grant_type", "client_credentials"),
+        ])
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+    {
+        Ok(resp) => match error::json::<TwitchToken>(resp).await {
+            Ok(json) => Ok(json.access_token),
+            Err(err) => {
+                error!("Could not parse Twitch token: {}", err);
+                Err(err)
+            }
+        },
+        Err(e) => {
+            error!("Could not get a Twitch app token: {}", e);
+            Err(FetchError::from(e))
+        }
+    }
+}
+
+// Searches live channels only. Helix matches the query against channel names
+// too, so titles are checked again after.
+async fn search_channels(
+    app: &TwitchApp,
+    token: &str,
+    keyword: &str,
+) -> Result<Result<Vec<TwitchChannel>, StatusCode>, FetchError> {
+    let resp = match http::client()
+        .get("https://api.twitch.tv/helix/search/channels")
+        .query(&[("query", keyword), ("live_only", "true"), ("first", "100")])
+        .header("Client-Id", &app.client_id)
+        .bearer_auth(token)
+        .send()
+        .await
+    {
+        Ok(resp) => resp,
+        Err(e) => {
+            error!("Twitch responded with an Error: {}", e);
+            return Err(FetchError::from(e));
+        }
+    };
+    if resp.status() == StatusCode::UNAUTHORIZED {
+        return Ok(Err(resp.status()));
+    }
+
+    match resp.error_for_status() {
+        Ok(resp) => match error::json::<TwitchSearchResponse>(resp).await {
+            Ok(json) => {
+                base::log_sample("Twitch channels", &json.data);
+                Ok(Ok(json.data))
+            }
+            Err(err) => {
+                error!("Could not parse Twitch API: {}", err);
+                Err(err)
+            }
+        },
+        Err(e) => {
+            error!("Twitch responded with an Error: {}", e);
+            Err(FetchError::from(e))
+        }
+    }
+}
+
+async fn fetch(
+    app: TwitchApp,
+    token: Arc<Mutex<Option<String>>>,
+    keyword: String,
+) -> Result<Vec<Shareable>, FetchError> {
+    // App tokens live for weeks, a new one is only fetched when Twitch
+    // rejects the cached one.
+    let mut token = token.lock().await;
+    let mut fresh_token = false;
+    let channels = loop {
+        let current = match token.as_ref() {
+            Some(current) => current.clone(),
+            None => {
+                fresh_token = true;
+                token.insert(fetch_app_token(&app).await?).clone()
+            }
+        };
+        match search_channels(&app, &current, &keyword).await? {
+            Ok(channels) => break channels,
+            Err(_) if !fresh_token => {
+                info!("Twitch app token expired, fetching a new one");
+                *token = None;
+            }
+            Err(status) => {
+                return Err(FetchError::new(
+                    ErrorKind::Auth,
+                    format!("Twitch rejected the app token: {}", status),
+                ))
+            }
+        }
+    };
+    let keyword = keyword.to_lowercase();
+
+    Ok(channels
+        .into_iter()
+        .filter(|channel| channel.is_live && channel.title.to_lowercase().contains(&keyword))
+        .map(|channel| {
+            let game = channel
+                .game_name
+                .filter(|game| !game.is_empty())
+                .map(|game| format!(" ({})", game))
+                .unwrap_or_default();
+
+            Shareable {
+                // One item per stream, not per channel.
+                id: base::item_id("twitch", format!("{}-{}", channel.id, channel.started_at)),
+                title: format!("🔴 {}{}: {}", channel.display_name, game, channel.title),
+                date: normalize_date(&channel.started_at, DateFormat::Rfc3339),
+                url: format!("https://www.twitch.tv/{}", channel.broadcaster_login),
+                source: String::from("twitch"),
+                canonical_url: None,
+            }
+        })
+        .collect())
+}
+
+pub fn spawn_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+    app: TwitchApp,
+) -> JoinHandle<()> {
+    let token = Arc::new(Mutex::new(None));
+    base::spawn_fetcher(
+        "Twitch streams",
+        keyword.clone(),
+        schedule,
+        pool,
+        move |_| fetch(app.clone(), token.clone(), keyword.clone()),
+    )
+}
@@ -1,14 +1,13 @@
-use async_recursion::async_recursion;
-use mysql::params;
-use mysql::prelude::*;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
 use serde::Deserialize;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::task::JoinError;
-use tokio::{task, time};
-use tracing::{debug, error, info};
+use tokio::task::JoinHandle;
+use tracing::{info, trace};
 
-use crate::fetcher::base::Shareable;
+use crate::fetcher::base::{self, Fetched, NewestFirst, Schedule, Shareable, Window};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::{self, FetchError};
+use crate::http;
 
 #[derive(Debug, Deserialize, Clone)]
 struct TwitterResponseItem {
@@ -28,130 +27,144 @@ struct TwitterResponse {
     meta: TwitterResponseMeta,
 }
 
-#[async_recursion]
-async fn fetch_twitter_api(
-    token: String,
-    query: String,
-    next_token: Option<String>,
-) -> Result<Vec<Shareable>, String> {
-    let mut shareables: Vec<Shareable> = vec![];
-    let url = if next_token.is_none() {
-        format!(
-        "https://api.twitter.com/2/tweets/search/recent?max_results=100&tweet.fields=created_at&query={}",
-        query
-    )
-    } else {
-        format!(
-        "https://api.twitter.com/2/tweets/search/recent?max_results=100&tweet.fields=created_at&query={}&next_token={}",
-        query,
-        next_token.unwrap()
-    )
-    };
-    let resp = match reqwest::Client::new()
-        .get(url)
-        .bearer_auth(token.clone())
+async fn fetch_page(
+    token: &str,
+    query: &str,
+    window: &[(&str, String)],
+    next_token: Option<&str>,
+) -> Result<TwitterResponse, FetchError> {
+    let mut params = vec![
+        ("max_results", String::from("100")),
+        ("tweet.fields", String::from("created_at")),
+        ("query", query.to_string()),
+    ];
+    params.extend(window.iter().cloned());
+    if let Some(next_token) = next_token {
+        params.push(("next_token", next_token.to_string()));
+    }
+    match http::client()
+        .get("https://api.twitter.com/2/tweets/search/recent")
+        .query(&params)
+        .bearer_auth(token)
         .send()
         .await
     {
-        Ok(resp) => match resp.json::<TwitterResponse>().await {
-            Ok(json) => json,
+        Ok(resp) => match error::json::<TwitterResponse>(resp).await {
+            Ok(json) => Ok(json),
             Err(err) => {
                 info!("{}", err);
-                return Err(format!("{}", err));
+                Err(err)
             }
         },
         Err(e) => {
             info!("{}", e);
-            return Err(format!("{}", e));
-        }
-    };
-
-    resp.data.iter().for_each(|item| {
-        let item_id = format!("twitter-{}", item.id.clone());
-
-        if item.text.contains("RT") {
-            debug!("Skipping tweet {} because it is a retweet", item_id);
-            return;
+            Err(FetchError::from(e))
         }
+    }
+}
 
-        shareables.push(Shareable {
-            id: item_id,
-            title: item.text.clone(),
-            date: item.created_at.clone(),
-            url: format!("https://twitter.com/twitter/status/{}", item.id),
-            source: String::from("twitter"),
-        });
-    });
-
-    if resp.meta.next_token.is_some() {
-        let pagination_result =
-            fetch_twitter_api(token.clone(), query, resp.meta.next_token).await?;
-
-        shareables.extend(pagination_result);
+fn to_shareable(item: &TwitterResponseItem) -> Option<Shareable> {
+    let item_id = base::item_id("twitter", &item.id);
+    if item.text.contains("RT") {
+        trace!("Skipping tweet {} because it is a retweet", item_id);
+        return None;
     }
 
-    Ok(shareables)
+    Some(Shareable {
+        id: item_id,
+        title: item.text.clone(),
+        date: normalize_date(&item.created_at, DateFormat::Rfc3339),
+        url: format!("https://twitter.com/twitter/status/{}", item.id),
+        source: String::from("twitter"),
+        canonical_url: None,
+    })
 }
 
-pub async fn fetch(
-    mut conn: mysql::PooledConn,
-    twitter_api_bearer: String,
-    keyword: String,
-) -> mysql::Result<()> {
-    info!("Fetching tweets");
-    let result = fetch_twitter_api(twitter_api_bearer.clone(), keyword.to_string(), None).await;
+// Recent search only reaches back seven days, older start times are rejected.
+fn start_time(since: Option<DateTime<Utc>>) -> Option<String> {
+    let earliest = Utc::now() - Duration::days(7) + Duration::minutes(1);
+    since.map(|since| {
+        since
+            .max(earliest)
+            .to_rfc3339_opts(SecondsFormat::Secs, true)
+    })
+}
 
-    match result {
-        Ok(shareables) => {
-            info!("Found {} tweets", shareables.len());
-            conn.exec_batch(
-                r"INSERT IGNORE INTO shareables (id, title, url, date, source)
-              VALUES (:id, :title, :url, :date, :source)",
-                shareables.iter().map(|p| {
-                    params! {
-                        "id" => p.id.clone(),
-                        "title" => p.title.clone(),
-                        "url" => p.url.clone(),
-                        "date" => p.date.clone(),
-                        "source" => p.source.clone()
-                    }
-                }),
-            )?;
+// Only asks for tweets newer than the newest one we have seen. Results come
+// newest first, a run that stops at its limits fills the gap down to the
+// previous since_id on the next runs (`until_id`). After a longer gap the
+// since_id may be outside the searchable week, so we fall back to start_time
+// then.
+async fn fetch(
+    token: String,
+    keyword: String,
+    max_pages: Option<u32>,
+    window: Window,
+) -> Result<Fetched, FetchError> {
+    let cursor = NewestFirst::<u64>::parse(window.cursor.as_deref());
+    let mut params = match (start_time(window.since), &cursor.after) {
+        (Some(start_time), _) => vec![("start_time", start_time)],
+        (None, Some(since_id)) => vec![("since_id", since_id.to_string())],
+        (None, None) => vec![],
+    };
+    if let Some(until_id) = cursor.before {
+        params.push(("until_id", until_id.to_string()));
+    }
+    // The stricter of TWITTER_MAX_PAGES and MAX_PAGES_PER_RUN.
+    let mut limits = window.limits;
+    if let Some(max_pages) = max_pages {
+        limits.max_pages = limits.max_pages.min(max_pages);
+    }
+    let mut budget = base::Budget::new(limits);
+
+    let mut shareables = vec![];
+    // Retweets are skipped, but still count for the cursor.
+    let mut newest = None;
+    let mut oldest = None;
+    let mut next_token = None;
+    while budget.next_page() {
+        let resp = fetch_page(&token, &keyword, &params, next_token.as_deref()).await?;
+        let mut complete = true;
+        for item in resp.data.iter() {
+            let id = item.id.parse::<u64>().ok();
+            if let Some(shareable) = to_shareable(item) {
+                if !budget.keep(&mut shareables, [shareable]) {
+                    complete = false;
+                    break;
+                }
+            }
+            newest = newest.max(id);
+            oldest = match (oldest, id) {
+                (Some(oldest), Some(id)) => Some(id.min(oldest)),
+                (oldest, id) => oldest.or(id),
+            };
         }
-        Err(e) => {
-            error!("Could not fetch tweets, aborting{}", e);
-            return Ok(());
+        next_token = resp.meta.next_token;
+        if !complete || next_token.is_none() {
+            break;
         }
     }
+    let complete = next_token.is_none() && !budget.exhausted();
 
-    info!("Done fetching  tweets");
-    Ok(())
+    Ok(Fetched {
+        shareables,
+        cursor: cursor.advance(newest, oldest, complete),
+    })
 }
 
-pub async fn spawn_fetcher(
-    interval_in_sec: u64,
+pub fn spawn_fetcher(
+    schedule: Schedule,
     pool: Arc<mysql::Pool>,
-
     keyword: String,
     twitter_api_bearer: String,
-) -> Result<(), JoinError> {
-    let forever = task::spawn(async move {
-        let mut interval = time::interval(Duration::from_secs(interval_in_sec));
-
-        loop {
-            let conn = pool.get_conn().expect("Failed to get connection");
-            let res = fetch(conn, twitter_api_bearer.clone(), keyword.clone()).await;
-            match res {
-                Ok(_) => {
-                    info!("Fetched Tweets, waiting...");
-                }
-                Err(e) => {
-                    error!("Error: {}", e);
-                }
-            }
-            interval.tick().await;
-        }
-    });
-
-    forever.await
+    max_pages: Option<u32>,
+) -> JoinHandle<()> {
+    base::spawn_fetcher("tweets", keyword.clone(), schedule, pool, move |window| {
+        fetch(
+            twitter_api_bearer.clone(),
+            keyword.clone(),
+            max_pages,
+            window,
+        )
+    })
 }
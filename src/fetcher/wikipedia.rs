@@ -0,0 +1,210 @@
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::error;
+
+use crate::fetcher::base::{self, Schedule, Shareable, Window};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::{self, FetchError};
+use crate::http;
+
+#[derive(Debug, Deserialize)]
+struct WikipediaRevision {
+    revid: u64,
+    parentid: u64,
+    timestamp: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WikipediaPage {
+    title: String,
+    #[serde(default)]
+    revisions: Vec<WikipediaRevision>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WikipediaRecentChange {
+    title: String,
+    revid: u64,
+    timestamp: String,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct WikipediaQuery {
+    #[serde(default)]
+    pages: Vec<WikipediaPage>,
+    #[serde(default)]
+    recentchanges: Vec<WikipediaRecentChange>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WikipediaResponse {
+    // Missing when nothing matched.
+    #[serde(default)]
+    query: WikipediaQuery,
+}
+
+async fn fetch_wikipedia_api<T: DeserializeOwned>(
+    language: &str,
+    query: &[(&str, &str)],
+) -> Result<T, FetchError> {
+    match http::client()
+        .get(format!("https://{}.wikipedia.org/w/api.php", language))
+        .query(&[
+            ("action", "query"),
+            ("format", "json"),
+            ("formatversion", "2"),
+        ])
+        .query(query)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+    {
+        Ok(resp) => match error::json::<T>(resp).await {
+            Ok(json) => Ok(json),
+            Err(err) => {
+                error!("Could not parse Wikipedia API for {}: {}", language, err);
+                Err(err)
+            }
+        },
+        Err(e) => {
+            error!("Wikipedia responded with an Error exit code: {}", e);
+            Err(FetchError::from(e))
+        }
+    }
+}
+
+fn revision_url(language: &str, revid: u64, parentid: u64) -> String {
+    if parentid == 0 {
+        format!(
+            "https://{}.wikipedia.org/w/index.php?oldid={}",
+            language, revid
+        )
+    } else {
+        format!(
+            "https://{}.wikipedia.org/w/index.php?diff={}&oldid={}",
+            language, revid, parentid
+        )
+    }
+}
+
+// Articles mentioning the keyword, most recently edited first, each with a
+// link to the diff of its latest edit. Every edit is a new item.
+async fn fetch_edits(language: &str, keyword: &str) -> Result<Vec<Shareable>, FetchError> {
+    let resp: WikipediaResponse = fetch_wikipedia_api(
+        language,
+        &[
+            ("generator", "search"),
+            ("gsrsearch", keyword),
+            ("gsrsort", "last_edit_desc"),
+            ("gsrlimit", "20"),
+            ("prop", "revisions"),
+            ("rvprop", "ids|timestamp"),
+        ],
+    )
+    .await?;
+    base::log_sample("Wikipedia search response", &resp.query.pages);
+
+    Ok(resp
+        .query
+        .pages
+        .into_iter()
+        .filter_map(|page| {
+            let revision = page.revisions.into_iter().next()?;
+            let kind = if revision.parentid == 0 {
+                "New article"
+            } else {
+                "Edited"
+            };
+            Some(Shareable {
+                id: base::item_id("wikipedia", format!("{}-{}", language, revision.revid)),
+                title: format!("{}: {}", kind, page.title),
+                date: normalize_date(&revision.timestamp, DateFormat::Rfc3339),
+                url: revision_url(language, revision.revid, revision.parentid),
+                source: String::from("wikipedia"),
+                canonical_url: None,
+            })
+        })
+        .collect())
+}
+
+// The search index lags behind, so new articles are also picked from the
+// recent changes feed. It can't be filtered by text, only the titles are
+// matched.
+async fn fetch_new_articles(language: &str, keyword: &str) -> Result<Vec<Shareable>, FetchError> {
+    let resp: WikipediaResponse = fetch_wikipedia_api(
+        language,
+        &[
+            ("list", "recentchanges"),
+            ("rctype", "new"),
+            ("rcnamespace", "0"),
+            ("rcprop", "title|ids|timestamp"),
+            ("rclimit", "500"),
+        ],
+    )
+    .await?;
+    base::log_sample(
+        "Wikipedia recent changes response",
+        &resp.query.recentchanges,
+    );
+
+    let keyword = keyword.to_lowercase();
+    Ok(resp
+        .query
+        .recentchanges
+        .into_iter()
+        .filter(|change| change.title.to_lowercase().contains(&keyword))
+        .map(|change| Shareable {
+            id: base::item_id("wikipedia", format!("{}-{}", language, change.revid)),
+            title: format!("New article: {}", change.title),
+            date: normalize_date(&change.timestamp, DateFormat::Rfc3339),
+            url: revision_url(language, change.revid, 0),
+            source: String::from("wikipedia"),
+            canonical_url: None,
+        })
+        .collect())
+}
+
+async fn fetch(
+    languages: Vec<String>,
+    keyword: String,
+    window: Window,
+) -> Result<Vec<Shareable>, FetchError> {
+    let mut budget = window.budget();
+    let mut shareables = vec![];
+    for language in languages.iter() {
+        if !budget.next_page()
+            || !budget.keep(&mut shareables, fetch_edits(language, &keyword).await?)
+        {
+            break;
+        }
+        if !budget.next_page()
+            || !budget.keep(
+                &mut shareables,
+                fetch_new_articles(language, &keyword).await?,
+            )
+        {
+            break;
+        }
+    }
+
+    // A new article can be in both.
+    base::dedup_shareables(&mut shareables);
+    Ok(shareables)
+}
+
+pub fn spawn_fetcher(
+    schedule: Schedule,
+    pool: Arc<mysql::Pool>,
+    keyword: String,
+    languages: Vec<String>,
+) -> JoinHandle<()> {
+    base::spawn_fetcher(
+        "Wikipedia articles",
+        keyword.clone(),
+        schedule,
+        pool,
+        move |window| fetch(languages.clone(), keyword.clone(), window),
+    )
+}
@@ -0,0 +1,170 @@
+use mysql::Pool;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::{task, time};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+use tracing::{error, info};
+
+use crate::fetcher::base::Shareable;
+use crate::shutdown;
+use crate::storage::{self, Deadline, Filter, Item};
+
+pub mod proto {
+    tonic::include_proto!("keyword_notifier");
+}
+
+use proto::keyword_notifier_server::{KeywordNotifier, KeywordNotifierServer};
+
+impl From<Item> for proto::Shareable {
+    fn from(item: Item) -> Self {
+        proto::Shareable {
+            id: item.shareable.id,
+            title: item.shareable.title,
+            date: item.shareable.date,
+            url: item.shareable.url,
+            source: item.shareable.source,
+            issue_key: item.issue_key,
+            issue_url: item.issue_url,
+            starred: item.starred,
+        }
+    }
+}
+
+impl From<Shareable> for proto::Shareable {
+    fn from(shareable: Shareable) -> Self {
+        proto::Shareable {
+            id: shareable.id,
+            title: shareable.title,
+            date: shareable.date,
+            url: shareable.url,
+            source: shareable.source,
+            ..Default::default()
+        }
+    }
+}
+
+fn internal(e: mysql::Error) -> Status {
+    error!("Error: {}", e);
+    Status::internal(format!("{}", e))
+}
+
+fn matches_source(source: &Option<String>, shareable: &Shareable) -> bool {
+    source.is_none() || source.as_deref() == Some(shareable.source.as_str())
+}
+
+fn list(pool: &Pool, source: &Option<String>) -> mysql::Result<Vec<proto::Shareable>> {
+    let mut items = storage::list_items(pool, Deadline::NONE, &Filter::default())?;
+    items.retain(|item| matches_source(source, &item.shareable));
+    items.sort_by(|a, b| b.cmp(a));
+    Ok(items.into_iter().map(proto::Shareable::from).collect())
+}
+
+pub struct Service {
+    pool: Arc<Pool>,
+}
+
+#[tonic::async_trait]
+impl KeywordNotifier for Service {
+    async fn list_shareables(
+        &self,
+        request: Request<proto::ListShareablesRequest>,
+    ) -> Result<Response<proto::ListShareablesResponse>, Status> {
+        let shareables = list(&self.pool, &request.into_inner().source).map_err(internal)?;
+        Ok(Response::new(proto::ListShareablesResponse { shareables }))
+    }
+
+    type StreamShareablesStream = ReceiverStream<Result<proto::Shareable, Status>>;
+
+    async fn stream_shareables(
+        &self,
+        request: Request<proto::StreamShareablesRequest>,
+    ) -> Result<Response<Self::StreamShareablesStream>, Status> {
+        let request = request.into_inner();
+        let pool = self.pool.clone();
+        let mut cursor = storage::last_event_id(&pool).map_err(internal)?;
+        let existing = list(&pool, &request.source).map_err(internal)?;
+        let (tx, rx) = mpsc::channel(100);
+
+        task::spawn(async move {
+            for shareable in existing {
+                if tx.send(Ok(shareable)).await.is_err() {
+                    return;
+                }
+            }
+            if !request.follow {
+                return;
+            }
+
+            // Follow the item event log for shareables fetched from now on.
+            let mut interval = time::interval(Duration::from_secs(5));
+            loop {
+                // Followers are told to come back, to the next instance when
+                // this one is being replaced.
+                if !shutdown::tick(&mut interval).await {
+                    let _ = tx
+                        .send(Err(Status::unavailable(
+                            "server is shutting down, reconnect",
+                        )))
+                        .await;
+                    return;
+                }
+                let created = match storage::created_since(&pool, cursor) {
+                    Ok((last, created)) => {
+                        cursor = last;
+                        created
+                    }
+                    Err(e) => {
+                        let _ = tx.send(Err(internal(e))).await;
+                        return;
+                    }
+                };
+                for shareable in created {
+                    if !matches_source(&request.source, &shareable) {
+                        continue;
+                    }
+                    if tx.send(Ok(shareable.into())).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn get_stats(
+        &self,
+        _request: Request<proto::GetStatsRequest>,
+    ) -> Result<Response<proto::Stats>, Status> {
+        let stats = storage::stats(&self.pool, Deadline::NONE).map_err(internal)?;
+        Ok(Response::new(proto::Stats {
+            total: stats.total,
+            by_source: stats
+                .by_source
+                .into_iter()
+                .map(|(source, count)| proto::SourceCount { source, count })
+                .collect(),
+            latest_date: stats.latest_date,
+            skipped_runs: stats.skipped_runs,
+        }))
+    }
+}
+
+pub fn spawn_server(port: u16, pool: Arc<Pool>) -> JoinHandle<()> {
+    task::spawn(async move {
+        let addr = SocketAddr::from(([0, 0, 0, 0], port));
+        info!("gRPC listening on {}", addr);
+
+        if let Err(e) = tonic::transport::Server::builder()
+            .add_service(KeywordNotifierServer::new(Service { pool }))
+            .serve_with_shutdown(addr, shutdown::requested())
+            .await
+        {
+            error!("gRPC server failed: {}", e);
+        }
+    })
+}
@@ -0,0 +1,223 @@
+use chrono::{SecondsFormat, Utc};
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::task::{self, JoinHandle};
+use tracing::{error, info};
+
+use crate::fetcher::error::ErrorKind;
+use crate::fetcher::twitch::{fetch_app_token, TwitchApp};
+use crate::http;
+use crate::Config;
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CredentialCheck {
+    pub source: String,
+    pub ok: bool,
+    pub message: String,
+    pub checked_at: String,
+}
+
+// None until the boot check finished. Shown on /status and /readyz, a failed
+// check only means a source rejected its credential.
+static CHECKS: Mutex<Option<Vec<CredentialCheck>>> = Mutex::new(None);
+
+pub fn credential_checks() -> Option<Vec<CredentialCheck>> {
+    CHECKS.lock().unwrap().clone()
+}
+
+#[derive(Debug, Deserialize)]
+struct SlackAuthTest {
+    ok: bool,
+    error: Option<String>,
+}
+
+// Err only if the credential was rejected. A source that is down or can't be
+// reached says nothing about it, that is only noted in the message.
+async fn check_status(request: RequestBuilder) -> Result<String, String> {
+    match request.timeout(CHECK_TIMEOUT).send().await {
+        Ok(resp) if resp.status().is_success() => Ok(String::from("authenticated")),
+        Ok(resp)
+            if matches!(
+                resp.status(),
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+            ) =>
+        {
+            Err(format!("rejected with {}", resp.status()))
+        }
+        Ok(resp) => Ok(format!("could not check, answered {}", resp.status())),
+        Err(e) => Ok(format!("could not check: {}", e.without_url())),
+    }
+}
+
+// Slack answers 200 for bad tokens and puts the problem into the body.
+async fn check_slack(client: &Client, token: &str) -> Result<String, String> {
+    let resp = match client
+        .post("https://slack.com/api/auth.test")
+        .bearer_auth(token)
+        .timeout(CHECK_TIMEOUT)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+    {
+        Ok(resp) => resp.json::<SlackAuthTest>().await,
+        Err(e) => Err(e),
+    };
+    let resp = match resp {
+        Ok(resp) => resp,
+        Err(e) => return Ok(format!("could not check: {}", e.without_url())),
+    };
+    if resp.ok {
+        Ok(String::from("authenticated"))
+    } else {
+        Err(resp.error.unwrap_or_else(|| String::from("unknown error")))
+    }
+}
+
+// One cheap authenticated call per configured credential, so an expired
+// token shows up on deploy and not at the first fetch.
+async fn check_credentials(config: &Config) -> Vec<(String, Result<String, String>)> {
+    let client = http::client();
+    let sources = &config.sources;
+    let mut results = vec![];
+
+    if let Some(token) = sources
+        .twitter
+        .api_bearer
+        .as_ref()
+        .filter(|_| sources.twitter.enabled)
+    {
+        let request = client
+            .get("https://api.twitter.com/2/users/by/username/twitter")
+            .bearer_auth(token);
+        results.push((String::from("twitter"), check_status(request).await));
+    }
+    if let Some(token) = &sources.github.token {
+        let request = client
+            .get("https://api.github.com/user")
+            .bearer_auth(token)
+            .header("Accept", "application/vnd.github+json");
+        results.push((String::from("github"), check_status(request).await));
+    }
+    if let Some(token) = &sources.gitlab.token {
+        let request = client
+            .get(format!("{}/api/v4/user", sources.gitlab.base_url))
+            .header("PRIVATE-TOKEN", token);
+        results.push((String::from("gitlab"), check_status(request).await));
+    }
+    if let Some(token) = &sources.slack.user_token {
+        results.push((String::from("slack"), check_slack(&client, token).await));
+    }
+    for (instance, token) in sources
+        .mastodon
+        .instances
+        .iter()
+        .zip(sources.mastodon.access_tokens.iter())
+        .filter(|(_, token)| !token.is_empty())
+    {
+        let request = client
+            .get(format!("{}/api/v1/accounts/verify_credentials", instance))
+            .bearer_auth(token);
+        results.push((
+            format!("mastodon({})", instance),
+            check_status(request).await,
+        ));
+    }
+    if let Some(token) = &sources.telegram.bot_token {
+        let request = client.get(format!("https://api.telegram.org/bot{}/getMe", token));
+        let result = check_status(request)
+            .await
+            .map_err(|_| String::from("getMe failed"));
+        results.push((String::from("telegram"), result));
+    }
+    if let (Some(client_id), Some(client_secret)) =
+        (&sources.twitch.client_id, &sources.twitch.client_secret)
+    {
+        let app = TwitchApp {
+            client_id: client_id.clone(),
+            client_secret: client_secret.clone(),
+        };
+        let result = match fetch_app_token(&app).await {
+            Ok(_) => Ok(String::from("authenticated")),
+            Err(e) if e.kind == ErrorKind::Auth => Err(e.message),
+            Err(e) => Ok(format!("could not check: {}", e.message)),
+        };
+        results.push((String::from("twitch"), result));
+    }
+    if let Some(token) = &sources.matrix.access_token {
+        let request = client
+            .get(format!(
+                "{}/_matrix/client/v3/account/whoami",
+                sources.matrix.homeserver
+            ))
+            .bearer_auth(token);
+        results.push((String::from("matrix"), check_status(request).await));
+    }
+    if let Some(token) = &sources.discord.bot_token {
+        let request = client
+            .get("https://discord.com/api/v10/users/@me")
+            .header("Authorization", format!("Bot {}", token));
+        results.push((String::from("discord"), check_status(request).await));
+    }
+
+    results
+}
+
+pub fn spawn_credential_check(config: Config) -> JoinHandle<()> {
+    task::spawn(async move {
+        let checks = check_credentials(&config)
+            .await
+            .into_iter()
+            .map(|(source, result)| {
+                let checked_at = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+                match result {
+                    Ok(message) => {
+                        info!("Credentials for {}: {}", source, message);
+                        CredentialCheck {
+                            source,
+                            ok: true,
+                            message,
+                            checked_at,
+                        }
+                    }
+                    Err(message) => {
+                        error!("Credentials for {} failed the check: {}", source, message);
+                        CredentialCheck {
+                            source,
+                            ok: false,
+                            message,
+                            checked_at,
+                        }
+                    }
+                }
+            })
+            .collect();
+        *CHECKS.lock().unwrap() = Some(checks);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifier::tests::serve;
+
+    #[tokio::test]
+    async fn fails_only_on_rejected_credentials() {
+        let check = |status: u16| async move {
+            let url = serve(vec![status]).await;
+            check_status(http::client().get(url)).await
+        };
+        assert_eq!(check(200).await, Ok(String::from("authenticated")));
+        assert!(check(401).await.is_err());
+        assert!(check(403).await.is_err());
+        let unavailable = check(503).await.unwrap();
+        assert!(
+            unavailable.starts_with("could not check"),
+            "{}",
+            unavailable
+        );
+    }
+}
@@ -0,0 +1,49 @@
+use std::sync::OnceLock;
+
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+// Several APIs (Stack Exchange, Wikipedia, RSS hosts) ask for a descriptive
+// user agent with a way to reach the operator.
+pub fn user_agent(configured: Option<&str>, contact: Option<&str>) -> String {
+    match (configured, contact) {
+        (Some(user_agent), _) => user_agent.to_string(),
+        (None, Some(contact)) => format!(
+            "keyword-notifier/{} (+{})",
+            env!("CARGO_PKG_VERSION"),
+            contact
+        ),
+        (None, None) => format!("keyword-notifier/{}", env!("CARGO_PKG_VERSION")),
+    }
+}
+
+// Called once on startup, before any request goes out.
+pub fn configure(user_agent: String) {
+    if CLIENT.set(build(user_agent)).is_err() {
+        tracing::warn!("HTTP client was already in use, keeping its user agent");
+    }
+}
+
+fn build(user_agent: String) -> reqwest::Client {
+    reqwest::Client::builder()
+        .user_agent(user_agent)
+        .build()
+        .expect("Failed to build HTTP client")
+}
+
+// The shared client for all outgoing requests. Clones share one connection
+// pool.
+pub fn client() -> reqwest::Client {
+    CLIENT.get_or_init(|| build(user_agent(None, None))).clone()
+}
+
+// Only the scheme, host and port of a URL, e.g. for logs.
+pub fn redact(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(url) => match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}://{}:{}", url.scheme(), host, port),
+            (Some(host), None) => format!("{}://{}", url.scheme(), host),
+            (None, _) => String::from("[redacted]"),
+        },
+        Err(_) => String::from("[redacted]"),
+    }
+}
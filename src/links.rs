@@ -0,0 +1,193 @@
+use reqwest::Url;
+use std::time::Duration;
+use tracing::debug;
+
+use crate::fetcher::base::Shareable;
+use crate::http;
+
+// Hosts that only redirect somewhere else, resolved before an item is stored.
+const SHORTENERS: &[&str] = &[
+    "bit.ly",
+    "buff.ly",
+    "dlvr.it",
+    "goo.gl",
+    "lnkd.in",
+    "ow.ly",
+    "t.co",
+    "tinyurl.com",
+    "trib.al",
+];
+
+// Query parameters that say where a click came from, not what it points to.
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "msclkid", "igshid", "mc_cid", "mc_eid", "ref_src", "ref_url",
+    "_hsenc", "_hsmi",
+];
+
+const EXPAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn is_tracking_param(name: &str) -> bool {
+    name.starts_with("utm_") || TRACKING_PARAMS.contains(&name)
+}
+
+// The form two links to the same page agree on: no `www.`, fragment, tracking
+// parameters or trailing slash. Anything that is not an http(s) URL is kept
+// as is.
+pub fn normalize(url: &str) -> String {
+    let mut parsed = match Url::parse(url.trim()) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => parsed,
+        _ => return url.to_string(),
+    };
+
+    if let Some(host) = parsed.host_str().and_then(|host| host.strip_prefix("www.")) {
+        let host = host.to_string();
+        if parsed.set_host(Some(&host)).is_err() {
+            return url.to_string();
+        }
+    }
+    parsed.set_fragment(None);
+
+    let params: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(name, _)| !is_tracking_param(name))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    if params.is_empty() {
+        parsed.set_query(None);
+    } else {
+        parsed.query_pairs_mut().clear().extend_pairs(params);
+    }
+
+    let path = parsed.path().to_string();
+    if path.len() > 1 && path.ends_with('/') {
+        parsed.set_path(path.trim_end_matches('/'));
+    }
+
+    parsed.to_string()
+}
+
+fn is_shortened(url: &str) -> bool {
+    match Url::parse(url) {
+        Ok(parsed) => matches!(parsed.host_str(), Some(host) if SHORTENERS.contains(&host)),
+        Err(_) => false,
+    }
+}
+
+// Follows the redirects of a shortened link, keeps the link if that fails.
+async fn expand(url: &str) -> String {
+    if !is_shortened(url) {
+        return url.to_string();
+    }
+    match http::client()
+        .head(url)
+        .timeout(EXPAND_TIMEOUT)
+        .send()
+        .await
+    {
+        Ok(resp) => resp.url().to_string(),
+        Err(e) => {
+            debug!("Could not expand {}: {}", url, e);
+            url.to_string()
+        }
+    }
+}
+
+// Sets the canonical URL of the shareables that don't have one yet.
+pub async fn canonicalize(shareables: &[Shareable]) -> Vec<Shareable> {
+    let mut canonicalized = Vec::with_capacity(shareables.len());
+    for shareable in shareables {
+        let mut shareable = shareable.clone();
+        if shareable.canonical_url.is_none() {
+            shareable.canonical_url = Some(normalize(&expand(&shareable.url).await));
+        }
+        canonicalized.push(shareable);
+    }
+    canonicalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn normalizes_urls() {
+        assert_eq!(
+            normalize("https://www.example.com/post/?utm_source=twitter&id=1#comments"),
+            "https://example.com/post?id=1"
+        );
+        assert_eq!(
+            normalize("HTTPS://Example.com:443/?fbclid=abc"),
+            "https://example.com/"
+        );
+        assert_eq!(
+            normalize("https://twitter.com/twitter/status/1532"),
+            "https://twitter.com/twitter/status/1532"
+        );
+        assert_eq!(normalize("not a url"), "not a url");
+        assert_eq!(
+            normalize("at://did:plc:abc/post/1"),
+            "at://did:plc:abc/post/1"
+        );
+    }
+
+    #[test]
+    fn only_expands_shorteners() {
+        assert!(is_shortened("https://t.co/abc"));
+        assert!(is_shortened("http://bit.ly/abc"));
+        assert!(!is_shortened("https://github.com/hashicorp/terraform-cdk"));
+        assert!(!is_shortened("not a url"));
+    }
+
+    fn any_url() -> impl Strategy<Value = String> {
+        (
+            prop_oneof![Just("http"), Just("https")],
+            prop_oneof![Just(""), Just("www."), Just("WWW.")],
+            "[a-z]{1,10}\\.(com|org|io)",
+            prop::collection::vec("[a-zA-Z0-9%._~-]{0,8}", 0..4),
+            prop::collection::vec(
+                (
+                    prop_oneof![Just("utm_source".to_string()), "[a-z_]{1,6}"],
+                    "[a-zA-Z0-9%+ &=]{0,6}",
+                ),
+                0..4,
+            ),
+            proptest::option::of("[a-z0-9]{0,6}"),
+        )
+            .prop_map(|(scheme, www, host, segments, params, fragment)| {
+                let mut url = format!("{}://{}{}/{}", scheme, www, host, segments.join("/"));
+                if !params.is_empty() {
+                    let query: Vec<String> = params
+                        .iter()
+                        .map(|(name, value)| format!("{}={}", name, value))
+                        .collect();
+                    url = format!("{}?{}", url, query.join("&"));
+                }
+                if let Some(fragment) = fragment {
+                    url = format!("{}#{}", url, fragment);
+                }
+                url
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn normalizing_is_idempotent(url in any_url()) {
+            let normalized = normalize(&url);
+            prop_assert_eq!(normalize(&normalized), normalized);
+        }
+
+        #[test]
+        fn normalized_urls_have_no_tracking(url in any_url()) {
+            let normalized = Url::parse(&normalize(&url)).unwrap();
+            prop_assert!(normalized.fragment().is_none());
+            prop_assert!(!normalized.host_str().unwrap().starts_with("www."));
+            prop_assert!(normalized.query_pairs().all(|(name, _)| !is_tracking_param(&name)));
+        }
+
+        #[test]
+        fn normalizing_never_panics(url in "\\PC{0,40}") {
+            normalize(&url);
+        }
+    }
+}
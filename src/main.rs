@@ -1,26 +1,65 @@
+mod build_info;
+mod bus;
+mod cli;
+mod clock;
+mod config;
+mod events;
+mod export;
+mod feed;
 mod fetcher;
-use askama::Template;
-use axum::{
-    error_handling::HandleErrorLayer,
-    extract::Extension,
-    http::StatusCode,
-    response::{Html, IntoResponse, Response},
-    routing::get,
-    Router,
-};
-
-use fetcher::base::Shareable;
-use mysql::prelude::*;
-use mysql::*;
-use serde::{Deserialize, Serialize};
-use std::time::Duration;
-use std::{net::SocketAddr, sync::Arc};
-use tower::{BoxError, ServiceBuilder};
-use tower_http::{add_extension::AddExtensionLayer, trace::TraceLayer};
-use tracing::{debug, error, info};
+#[cfg(feature = "grpc")]
+mod grpc;
+mod health;
+mod http;
+mod links;
+mod maintenance;
+mod migrations;
+mod notifier;
+mod readlater;
+mod reshare;
+mod share;
+mod shutdown;
+mod snooze;
+mod source;
+mod storage;
+mod templating;
+mod text;
+mod tracker;
+mod web;
+mod webhook;
 
+use serde::Serialize;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+use tokio::task::{self, JoinError, JoinHandle};
+use tokio::time;
+use tracing::{error, info, warn};
+
+pub use self::config::Config;
+use self::fetcher::bluesky::spawn_fetcher as fetch_bluesky;
+use self::fetcher::devto::spawn_fetcher as fetch_devto;
+use self::fetcher::discord::{spawn_listener as listen_discord, DiscordFilter};
+use self::fetcher::dockerhub::spawn_fetcher as fetch_dockerhub;
+use self::fetcher::github::spawn_fetcher as fetch_github;
+use self::fetcher::github_code::spawn_fetcher as fetch_github_code;
+use self::fetcher::github_discussions::spawn_fetcher as fetch_github_discussions;
+use self::fetcher::github_releases::spawn_fetcher as fetch_github_releases;
+use self::fetcher::gitlab::spawn_fetcher as fetch_gitlab;
+use self::fetcher::hackernews::spawn_comment_fetcher as fetch_hackernews_comments;
+use self::fetcher::mastodon::{spawn_fetcher as fetch_mastodon, MastodonInstance};
+use self::fetcher::matrix::{spawn_fetcher as fetch_matrix, MatrixAccount};
+use self::fetcher::medium::spawn_fetcher as fetch_medium;
+use self::fetcher::news::{spawn_fetcher as fetch_news, GOOGLE_NEWS_FEED};
+use self::fetcher::packages::spawn_fetcher as fetch_packages;
+use self::fetcher::podcast::spawn_fetcher as fetch_podcasts;
+use self::fetcher::quora::spawn_fetcher as fetch_quora;
+use self::fetcher::slack::spawn_fetcher as fetch_slack;
 use self::fetcher::stackoverflow::spawn_fetcher as fetch_stackoverflow;
+use self::fetcher::telegram::spawn_fetcher as fetch_telegram;
+use self::fetcher::terraform::spawn_fetcher as fetch_terraform;
+use self::fetcher::twitch::{spawn_fetcher as fetch_twitch, TwitchApp};
 use self::fetcher::twitter::spawn_fetcher as fetch_twitter;
+use self::fetcher::wikipedia::spawn_fetcher as fetch_wikipedia;
+use self::notifier::digest::DigestSchedule;
 
 #[derive(Debug, Serialize, Clone)]
 struct Reponse {
@@ -28,167 +67,438 @@ struct Reponse {
     count: Option<i32>,
 }
 
-fn default_port() -> u16 {
-    3000
-}
-#[derive(Deserialize, Debug, Clone)]
-struct Config {
-    database_url: String,
-    twitter_api_bearer: String,
-    keyword: String,
-    interval_in_sec: u64,
-    #[serde(default = "default_port")]
-    port: u16,
-}
-
 #[tokio::main]
 async fn main() {
     // initialize tracing
     tracing_subscriber::fmt::init();
 
+    // `app query ...` only needs the database, not the full service config
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("query") {
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL is not set");
+        std::process::exit(cli::query(&connect(&database_url, None), &args[2..]));
+    }
+
+    if args.get(1).map(String::as_str) == Some("config") {
+        std::process::exit(config::command(&args[2..]));
+    }
+
     // load config
-    let config = envy::from_env::<Config>().expect("Failed to load config");
-
-    let builder =
-        mysql::OptsBuilder::from_opts(mysql::Opts::from_url(&config.database_url).unwrap());
-    let pool = mysql::Pool::new(builder.ssl_opts(mysql::SslOpts::default()))
-        .expect("Failed to initialize mysql");
-    let pool_arc = Arc::new(pool);
-
-    let app = Router::new().route("/", get(root)).layer(
-        ServiceBuilder::new()
-            .layer(HandleErrorLayer::new(|error: BoxError| async move {
-                if error.is::<tower::timeout::error::Elapsed>() {
-                    Ok(StatusCode::REQUEST_TIMEOUT)
-                } else {
-                    Err((
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        format!("Unhandled internal error: {}", error),
-                    ))
-                }
-            }))
-            .timeout(Duration::from_secs(5))
-            .layer(TraceLayer::new_for_http())
-            .layer(AddExtensionLayer::new(config.clone()))
-            .layer(AddExtensionLayer::new(pool_arc.clone()))
-            .into_inner(),
+    let config = Config::from_env().expect("Failed to load config");
+    let sources = &config.sources;
+
+    http::configure(http::user_agent(
+        config.user_agent.as_deref(),
+        config.contact_url.as_deref(),
+    ));
+    source::configure(&config.source_icons, &sources.stackexchange.sites);
+    let pool_arc = Arc::new(connect(&config.database_url, config.database_pool_size));
+    if config.database_read_url.is_some() || config.database_read_pool_size.is_some() {
+        storage::use_read_pool(connect(
+            config
+                .database_read_url
+                .as_deref()
+                .unwrap_or(&config.database_url),
+            config.database_read_pool_size,
+        ));
+    }
+
+    if let Err(e) = migrations::check_version(&pool_arc) {
+        error!("Refusing to start: {}", e);
+        std::process::exit(1);
+    }
+    if config.run_migrations {
+        migrations::run(&pool_arc).expect("Failed to migrate database");
+    } else if let Err(e) = migrations::check(&pool_arc) {
+        error!(
+            "Database schema does not match this version ({}), apply the migrations or start with RUN_MIGRATIONS=true",
+            e
+        );
+        std::process::exit(1);
+    }
+
+    maintenance::set(config.maintenance_mode);
+    if let Some(dir) = &config.templates_dir {
+        // validated with the config
+        if let Ok(Some(overrides)) = web::overrides::load(dir) {
+            web::overrides::configure(overrides);
+        }
+    }
+    fetcher::error::spawn_counter();
+    notifier::configure(
+        notifier::from_config(&config, pool_arc.clone()).expect("Failed to set up notifiers"),
     );
 
+    let app = web::router(config.clone(), pool_arc.clone());
+
     let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
-    tracing::debug!("listening on {}", addr);
-    let web_task = axum::Server::bind(&addr).serve(app.into_make_service());
+    config::log_summary(&config, addr);
+    // Stops accepting connections on shutdown, requests in flight are answered.
+    let mut web_task = task::spawn(
+        axum::Server::bind(&addr)
+            .serve(app.into_make_service())
+            .with_graceful_shutdown(shutdown::requested()),
+    );
 
-    match tokio::join!(
-        web_task,
-        fetch_twitter(
-            config.interval_in_sec,
+    if let Some(spill_file) = &config.spill_file {
+        fetcher::spill::configure(spill_file.into());
+    }
+
+    let mut tasks = vec![health::spawn_credential_check(config.clone())];
+    if sources.twitter.enabled {
+        tasks.push(fetch_twitter(
+            config.schedule("TWITTER", sources.twitter.interval_in_sec),
             pool_arc.clone(),
             config.keyword.clone(),
-            config.twitter_api_bearer.clone()
-        ),
-        fetch_stackoverflow(
-            config.interval_in_sec,
-            pool_arc.clone(),
-            config.keyword.clone()
-        )
-    ) {
-        (Ok(_), Ok(_), Ok(_)) => info!("Done without errors"),
-        (a, b, c) => error!(
-            "Error found, web: {:#?}, twitter: {:#?}, stackoverflow: {:#?}",
-            a, b, c
-        ),
+            // validated in Config::from_env
+            sources.twitter.api_bearer.clone().unwrap(),
+            sources.twitter.max_pages,
+        ));
     }
-}
 
-#[derive(Template)]
-#[template(path = "base.html", escape = "none")]
-struct BaseTemplate {
-    title: String,
-}
+    if sources.stackexchange.enabled {
+        tasks.push(fetch_stackoverflow(
+            config.schedule("STACKEXCHANGE", sources.stackexchange.interval_in_sec),
+            pool_arc.clone(),
+            config.keyword.clone(),
+            sources.stackexchange.sites.clone(),
+            sources.stackexchange.api_key.clone(),
+        ));
+    }
 
-#[derive(Template)]
-#[template(path = "index.html", escape = "none")]
-struct IndexTemplate {
-    items: Vec<Shareable>,
-}
+    if let Some(github_token) = sources.github.token.clone() {
+        let schedule = config.schedule("GITHUB", sources.github.interval_in_sec);
+        tasks.push(fetch_github(
+            schedule,
+            pool_arc.clone(),
+            config.keyword.clone(),
+            github_token.clone(),
+        ));
 
-#[derive(Template)]
-#[template(path = "error.html")]
-struct ErrorTemplate {
-    message: String,
-}
+        if sources.github.code_search_enabled {
+            tasks.push(fetch_github_code(
+                schedule,
+                pool_arc.clone(),
+                config.keyword.clone(),
+                github_token.clone(),
+            ));
+        }
 
-struct HtmlTemplate<T>(T);
-
-impl<T> IntoResponse for HtmlTemplate<T>
-where
-    T: Template,
-{
-    fn into_response(self) -> Response {
-        match self.0.render() {
-            Ok(html) => Html(html).into_response(),
-            Err(err) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to render template. Error: {}", err),
-            )
-                .into_response(),
+        if !sources.github.discussion_scopes.is_empty() {
+            tasks.push(fetch_github_discussions(
+                schedule,
+                pool_arc.clone(),
+                config.keyword.clone(),
+                github_token,
+                sources.github.discussion_scopes.clone(),
+            ));
         }
     }
-}
 
-#[tracing::instrument]
-async fn root(
-    Extension(config): Extension<Config>,
-    Extension(pool): Extension<Arc<Pool>>,
-) -> impl IntoResponse {
-    let mut conn = pool.get_conn().expect("Failed to get connection");
-    let query_result = conn.query_map(
-        "SELECT id, title, url, date, source from shareables",
-        |(id, title, url, date, source)| Shareable {
-            id,
-            title,
-            date,
-            url,
-            source,
-        },
-    );
+    if !sources.github.release_repos.is_empty() || sources.github.trending_enabled {
+        tasks.push(fetch_github_releases(
+            config.schedule("GITHUB", sources.github.interval_in_sec),
+            pool_arc.clone(),
+            config.keyword.clone(),
+            sources.github.token.clone(),
+            sources.github.release_repos.clone(),
+            sources.github.trending_enabled,
+        ));
+    }
+
+    if let Some(gitlab_token) = sources.gitlab.token.clone() {
+        tasks.push(fetch_gitlab(
+            config.schedule("GITLAB", sources.gitlab.interval_in_sec),
+            pool_arc.clone(),
+            config.keyword.clone(),
+            sources.gitlab.base_url.clone(),
+            gitlab_token,
+        ));
+    }
+
+    if sources.bluesky.enabled {
+        tasks.push(fetch_bluesky(
+            config.schedule("BLUESKY", sources.bluesky.interval_in_sec),
+            pool_arc.clone(),
+            config.keyword.clone(),
+        ));
+    }
 
-    match query_result {
-        Ok(shareables) => {
-            info!("Fetched {} items", shareables.len());
-            debug!("Items: {:?}", shareables);
-
-            let mut sanitized_shareable = shareables
-                .into_iter()
-                .map(|item| Shareable {
-                    id: item.id,
-                    title: item
-                        .title
-                        .replace(":question:", "❓")
-                        .replace(":white_check_mark:", "✅")
-                        .replace(":waiting-spin:", "🔄"),
-                    date: item.date,
-                    url: item.url,
-                    source: item.source,
-                })
-                .filter(|item| !item.title.contains("[Dependency Updated]"))
-                .collect::<Vec<Shareable>>();
-
-                sanitized_shareable.sort_by(|a, b| b.cmp(a));
-
-
-
-            HtmlTemplate(IndexTemplate {
-                items: sanitized_shareable,
+    if sources.devto.enabled {
+        tasks.push(fetch_devto(
+            config.schedule("DEVTO", sources.devto.interval_in_sec),
+            pool_arc.clone(),
+            config.keyword.clone(),
+        ));
+    }
+
+    if sources.dockerhub.enabled {
+        tasks.push(fetch_dockerhub(
+            config.schedule("DOCKERHUB", sources.dockerhub.interval_in_sec),
+            pool_arc.clone(),
+            config.keyword.clone(),
+        ));
+    }
+
+    if sources.medium.enabled {
+        tasks.push(fetch_medium(
+            config.schedule("MEDIUM", sources.medium.interval_in_sec),
+            pool_arc.clone(),
+            config.keyword.clone(),
+        ));
+    }
+
+    if !sources.packages.registries.is_empty() {
+        tasks.push(fetch_packages(
+            config.schedule("PACKAGE", sources.packages.interval_in_sec),
+            pool_arc.clone(),
+            config.keyword.clone(),
+            sources.packages.registries.clone(),
+        ));
+    }
+
+    if sources.news.enabled {
+        tasks.push(fetch_news(
+            config.schedule("NEWS", sources.news.interval_in_sec),
+            pool_arc.clone(),
+            config.keyword.clone(),
+            sources
+                .news
+                .feed_url
+                .clone()
+                .unwrap_or_else(|| String::from(GOOGLE_NEWS_FEED)),
+        ));
+    }
+
+    if let Some(slack_user_token) = sources.slack.user_token.clone() {
+        tasks.push(fetch_slack(
+            config.schedule("SLACK", sources.slack.interval_in_sec),
+            pool_arc.clone(),
+            config.keyword.clone(),
+            slack_user_token,
+        ));
+    }
+
+    if sources.terraform_registry.enabled {
+        tasks.push(fetch_terraform(
+            config.schedule(
+                "TERRAFORM_REGISTRY",
+                sources.terraform_registry.interval_in_sec,
+            ),
+            pool_arc.clone(),
+            config.keyword.clone(),
+        ));
+    }
+
+    if !sources.mastodon.instances.is_empty() {
+        let instances = sources
+            .mastodon
+            .instances
+            .iter()
+            .enumerate()
+            .map(|(i, url)| MastodonInstance {
+                url: url.clone(),
+                access_token: sources
+                    .mastodon
+                    .access_tokens
+                    .get(i)
+                    .filter(|token| !token.is_empty())
+                    .cloned(),
             })
-            .into_response()
+            .collect();
+        tasks.push(fetch_mastodon(
+            config.schedule("MASTODON", sources.mastodon.interval_in_sec),
+            pool_arc.clone(),
+            config.keyword.clone(),
+            instances,
+        ));
+    }
+
+    if let Some(telegram_bot_token) = sources.telegram.bot_token.clone() {
+        tasks.push(fetch_telegram(
+            config.schedule("TELEGRAM", sources.telegram.interval_in_sec),
+            pool_arc.clone(),
+            config.keyword.clone(),
+            telegram_bot_token,
+            sources.telegram.channels.clone(),
+        ));
+    }
+
+    if sources.hackernews.comments_enabled {
+        tasks.push(fetch_hackernews_comments(
+            config.schedule("HACKERNEWS", sources.hackernews.interval_in_sec),
+            pool_arc.clone(),
+            config.keyword.clone(),
+        ));
+    }
+
+    if !sources.quora.topics.is_empty() {
+        tasks.push(fetch_quora(
+            config.schedule("QUORA", sources.quora.interval_in_sec),
+            pool_arc.clone(),
+            config.keyword.clone(),
+            sources.quora.topics.clone(),
+        ));
+    }
+
+    if let (Some(client_id), Some(client_secret)) = (
+        sources.twitch.client_id.clone(),
+        sources.twitch.client_secret.clone(),
+    ) {
+        tasks.push(fetch_twitch(
+            config.schedule("TWITCH", sources.twitch.interval_in_sec),
+            pool_arc.clone(),
+            config.keyword.clone(),
+            TwitchApp {
+                client_id,
+                client_secret,
+            },
+        ));
+    }
+
+    if let Some(access_token) = sources.matrix.access_token.clone() {
+        tasks.push(fetch_matrix(
+            config.schedule("MATRIX", sources.matrix.interval_in_sec),
+            pool_arc.clone(),
+            config.keyword.clone(),
+            MatrixAccount {
+                homeserver: sources.matrix.homeserver.clone(),
+                access_token,
+            },
+            sources.matrix.rooms.clone(),
+        ));
+    }
+
+    if let Some(api_key) = sources.podcast.api_key.clone() {
+        tasks.push(fetch_podcasts(
+            config.schedule("PODCAST", sources.podcast.interval_in_sec),
+            pool_arc.clone(),
+            config.keyword.clone(),
+            api_key,
+        ));
+    }
+
+    if !sources.wikipedia.languages.is_empty() {
+        tasks.push(fetch_wikipedia(
+            config.schedule("WIKIPEDIA", sources.wikipedia.interval_in_sec),
+            pool_arc.clone(),
+            config.keyword.clone(),
+            sources.wikipedia.languages.clone(),
+        ));
+    }
+
+    if let Some(bot_token) = sources.discord.bot_token.clone() {
+        tasks.push(listen_discord(
+            pool_arc.clone(),
+            config.keyword.clone(),
+            bot_token,
+            DiscordFilter {
+                guild_ids: sources.discord.guild_ids.clone(),
+                channel_ids: sources.discord.channel_ids.clone(),
+            },
+        ));
+    }
+
+    if reshare::Target::Mastodon.is_configured(&config)
+        || reshare::Target::Bluesky.is_configured(&config)
+    {
+        tasks.push(reshare::spawn_publisher(config.clone(), pool_arc.clone()));
+    }
+
+    #[cfg(feature = "grpc")]
+    if let Some(grpc_port) = config.grpc_port {
+        tasks.push(grpc::spawn_server(grpc_port, pool_arc.clone()));
+    }
+
+    if config.spill_file.is_some() {
+        tasks.push(fetcher::spill::spawn_drainer(pool_arc.clone()));
+    }
+
+    if !config.webhook_urls.is_empty() {
+        tasks.push(webhook::spawn_dispatcher(config.clone(), pool_arc.clone()));
+    }
+
+    if let Some(dir) = config.export_dir.as_deref() {
+        tasks.push(export::jobs::spawn_worker(
+            pool_arc.clone(),
+            dir.into(),
+            Duration::from_secs(config.export_ttl_in_hours * 60 * 60),
+            &clock::SYSTEM,
+        ));
+    }
+
+    if notifier::has_channels() {
+        tasks.push(notifier::outbox::spawn_retrier(pool_arc.clone()));
+    }
+
+    if notifier::is_throttled() {
+        tasks.push(notifier::spawn_flusher());
+    }
+
+    if let Some(schedule) = config.notify_digest.as_deref() {
+        tasks.push(notifier::digest::spawn_scheduler(
+            // validated in Config::from_env
+            DigestSchedule::parse(schedule).unwrap(),
+            pool_arc.clone(),
+            &clock::SYSTEM,
+        ));
+    }
+
+    let drain_timeout = Duration::from_secs(config.shutdown_timeout_in_sec);
+    tokio::select! {
+        _ = shutdown::wait_for_signal() => {}
+        // The server only ends by itself if it failed or panicked. The other
+        // tasks still get to finish, then we exit so we get restarted.
+        result = &mut web_task => {
+            error!("Web server stopped: {:?}", result);
+            shutdown::request();
+            if time::timeout(drain_timeout, wait_for_tasks(tasks)).await.is_err() {
+                warn!(
+                    "Still busy after {}s, exiting anyway",
+                    config.shutdown_timeout_in_sec
+                );
+            }
+            std::process::exit(1);
         }
-        Err(e) => {
-            error!("Error loading data: {}", e);
-            HtmlTemplate(ErrorTemplate {
-                message: format!("{}", e),
-            })
-            .into_response()
+    }
+    match time::timeout(drain_timeout, async {
+        tokio::join!(web_task, wait_for_tasks(tasks))
+    })
+    .await
+    {
+        Ok((Ok(Ok(_)), Ok(_))) => info!("Done without errors"),
+        Ok((a, b)) => error!("Error found, web: {:#?}, tasks: {:#?}", a, b),
+        Err(_) => warn!(
+            "Still busy after {}s, exiting anyway",
+            config.shutdown_timeout_in_sec
+        ),
+    }
+}
+
+fn connect(database_url: &str, max_connections: Option<usize>) -> mysql::Pool {
+    let builder = mysql::OptsBuilder::from_opts(mysql::Opts::from_url(database_url).unwrap());
+    // Ping pooled connections on checkout so long idle fetchers get a live one.
+    let mut pool_opts = mysql::PoolOpts::default().with_check_health(true);
+    if let Some(max) = max_connections {
+        let min = mysql::PoolConstraints::DEFAULT.min().min(max);
+        match mysql::PoolConstraints::new(min, max) {
+            Some(constraints) => pool_opts = pool_opts.with_constraints(constraints),
+            None => error!("Ignoring invalid pool size {}", max),
         }
     }
+    mysql::Pool::new(
+        builder
+            .ssl_opts(mysql::SslOpts::default())
+            // Emoji need 4 bytes, whatever the server's default charset is.
+            .init(vec!["SET NAMES utf8mb4 COLLATE utf8mb4_unicode_ci"])
+            .pool_opts(pool_opts),
+    )
+    .expect("Failed to initialize mysql")
+}
+
+async fn wait_for_tasks(tasks: Vec<JoinHandle<()>>) -> Result<(), JoinError> {
+    for task in tasks {
+        task.await?;
+    }
+    Ok(())
 }
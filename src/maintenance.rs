@@ -0,0 +1,49 @@
+use std::sync::OnceLock;
+use tokio::sync::watch;
+use tracing::info;
+
+use crate::shutdown;
+
+// While on, e.g. during schema migrations on a shared database, the UI and
+// API answer 503 and fetchers and background jobs skip their runs. Per
+// process, set it on every instance.
+static MAINTENANCE: OnceLock<watch::Sender<bool>> = OnceLock::new();
+
+fn sender() -> &'static watch::Sender<bool> {
+    MAINTENANCE.get_or_init(|| watch::channel(false).0)
+}
+
+pub fn is_active() -> bool {
+    *sender().borrow()
+}
+
+pub fn set(active: bool) {
+    if sender().send_replace(active) != active {
+        if active {
+            info!("Maintenance mode on, pausing fetchers and notifiers");
+        } else {
+            info!("Maintenance mode off");
+        }
+    }
+}
+
+async fn over() {
+    let mut receiver = sender().subscribe();
+    while *receiver.borrow_and_update() {
+        if receiver.changed().await.is_err() {
+            return;
+        }
+    }
+}
+
+// Returns right away outside of maintenance, otherwise once it is over.
+// False if shutdown was requested first.
+pub async fn wait_until_over() -> bool {
+    if !is_active() {
+        return true;
+    }
+    tokio::select! {
+        _ = over() => true,
+        _ = shutdown::requested() => false,
+    }
+}
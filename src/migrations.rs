@@ -0,0 +1,636 @@
+use mysql::prelude::*;
+use mysql::{params, PooledConn, TxOpts};
+use tracing::info;
+
+use crate::fetcher::base;
+
+// Applied in order, each exactly once. Never edit an entry that has shipped,
+// append a new one instead.
+const MIGRATIONS: &[(u32, &str)] = &[
+    (
+        1,
+        r"CREATE TABLE IF NOT EXISTS shareables (
+            id VARCHAR(512) NOT NULL PRIMARY KEY,
+            title TEXT NOT NULL,
+            url TEXT NOT NULL,
+            date VARCHAR(64) NOT NULL,
+            source VARCHAR(64) NOT NULL
+        )",
+    ),
+    (
+        2,
+        r"ALTER TABLE shareables
+            ADD COLUMN issue_key VARCHAR(255) NULL,
+            ADD COLUMN issue_url TEXT NULL",
+    ),
+    (
+        3,
+        r"CREATE TABLE IF NOT EXISTS reshares (
+            id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+            shareable_id VARCHAR(512) NOT NULL,
+            text TEXT NOT NULL,
+            target VARCHAR(32) NOT NULL,
+            scheduled_at DATETIME NOT NULL,
+            posted_at DATETIME NULL,
+            post_url TEXT NULL,
+            error TEXT NULL,
+            INDEX reshares_due (posted_at, scheduled_at)
+        )",
+    ),
+    (
+        4,
+        r"ALTER TABLE shareables ADD COLUMN starred_at DATETIME NULL",
+    ),
+    (
+        5,
+        r"CREATE TABLE IF NOT EXISTS item_events (
+            id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+            event_type VARCHAR(64) NOT NULL,
+            shareable_id VARCHAR(512) NOT NULL,
+            payload TEXT NOT NULL,
+            occurred_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            INDEX item_events_occurred_at (occurred_at)
+        )",
+    ),
+    (
+        6,
+        r"CREATE TABLE IF NOT EXISTS webhook_cursors (
+            url VARCHAR(512) NOT NULL PRIMARY KEY,
+            last_event_id BIGINT UNSIGNED NOT NULL
+        )",
+    ),
+    (
+        7,
+        r"CREATE TABLE IF NOT EXISTS shareable_tags (
+            shareable_id VARCHAR(512) NOT NULL,
+            tag VARCHAR(64) NOT NULL,
+            PRIMARY KEY (shareable_id, tag),
+            INDEX shareable_tags_tag (tag)
+        )",
+    ),
+    (
+        8,
+        r"CREATE TABLE IF NOT EXISTS fetch_checkpoints (
+            fetcher VARCHAR(512) NOT NULL PRIMARY KEY,
+            last_success_at DATETIME NOT NULL
+        )",
+    ),
+    (
+        9,
+        r"CREATE TABLE IF NOT EXISTS source_cursors (
+            fetcher VARCHAR(512) NOT NULL PRIMARY KEY,
+            cursor_value VARCHAR(255) NOT NULL,
+            updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+        )",
+    ),
+    // Tables created with the server default (often 3 byte utf8) reject emoji
+    // and other characters outside the BMP.
+    (
+        10,
+        r"ALTER TABLE shareables CONVERT TO CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci",
+    ),
+    (
+        11,
+        r"ALTER TABLE reshares CONVERT TO CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci",
+    ),
+    (
+        12,
+        r"ALTER TABLE item_events CONVERT TO CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci",
+    ),
+    (
+        13,
+        r"ALTER TABLE webhook_cursors CONVERT TO CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci",
+    ),
+    (
+        14,
+        r"ALTER TABLE shareable_tags CONVERT TO CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci",
+    ),
+    (
+        15,
+        r"ALTER TABLE fetch_checkpoints CONVERT TO CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci",
+    ),
+    (
+        16,
+        r"ALTER TABLE source_cursors CONVERT TO CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci",
+    ),
+    (
+        17,
+        r"CREATE TABLE IF NOT EXISTS push_subscriptions (
+            endpoint VARCHAR(512) NOT NULL PRIMARY KEY,
+            p256dh VARCHAR(128) NOT NULL,
+            auth VARCHAR(64) NOT NULL,
+            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+        ) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci",
+    ),
+    (
+        18,
+        r"CREATE TABLE IF NOT EXISTS notification_digests (
+            id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+            last_event_id BIGINT UNSIGNED NOT NULL,
+            item_count INT UNSIGNED NOT NULL,
+            sent_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        ) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci",
+    ),
+    // 19 is a data migration, see DATA_MIGRATIONS.
+    (
+        20,
+        r"ALTER TABLE shareables
+            ADD COLUMN canonical_url TEXT NULL,
+            ADD INDEX shareables_canonical_url (canonical_url(255))",
+    ),
+    (
+        21,
+        r"ALTER TABLE shareables
+            ADD COLUMN ingested_by VARCHAR(255) NULL,
+            ADD COLUMN ingested_keyword VARCHAR(255) NULL,
+            ADD COLUMN ingested_version VARCHAR(128) NULL,
+            ADD COLUMN ingested_at DATETIME NULL",
+    ),
+    (
+        22,
+        r"CREATE TABLE IF NOT EXISTS notification_outbox (
+            id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+            channel VARCHAR(64) NOT NULL,
+            digest BOOLEAN NOT NULL,
+            payload MEDIUMTEXT NOT NULL,
+            attempts INT UNSIGNED NOT NULL,
+            last_error TEXT NULL,
+            next_attempt_at DATETIME NOT NULL,
+            failed_at DATETIME NULL,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            INDEX notification_outbox_due (failed_at, next_attempt_at)
+        ) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci",
+    ),
+    (
+        23,
+        r"CREATE TABLE IF NOT EXISTS export_jobs (
+            id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+            status VARCHAR(16) NOT NULL,
+            source VARCHAR(64) NULL,
+            date_from VARCHAR(32) NULL,
+            date_to VARCHAR(32) NULL,
+            scanned BIGINT UNSIGNED NOT NULL DEFAULT 0,
+            total BIGINT UNSIGNED NULL,
+            written BIGINT UNSIGNED NOT NULL DEFAULT 0,
+            attempts INT UNSIGNED NOT NULL DEFAULT 0,
+            error TEXT NULL,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            finished_at DATETIME NULL,
+            INDEX export_jobs_status (status, id)
+        ) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci",
+    ),
+    (
+        24,
+        r"ALTER TABLE shareables ADD FULLTEXT INDEX shareables_title_fulltext (title)",
+    ),
+    (
+        25,
+        r"ALTER TABLE shareables ADD COLUMN read_at DATETIME NULL",
+    ),
+    (
+        26,
+        r"CREATE TABLE IF NOT EXISTS snoozes (
+            kind VARCHAR(16) NOT NULL,
+            name VARCHAR(255) NOT NULL,
+            until DATETIME NOT NULL,
+            PRIMARY KEY (kind, name)
+        ) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci",
+    ),
+    (
+        28,
+        r"ALTER TABLE notification_outbox ADD COLUMN target TEXT NULL AFTER digest",
+    ),
+];
+
+type DataMigration = fn(&mut PooledConn) -> mysql::Result<()>;
+
+// Rewrites of existing rows that SQL alone can't express, applied after the
+// schema migrations and numbered along with them.
+const DATA_MIGRATIONS: &[(u32, DataMigration)] =
+    &[(19, rehash_item_ids), (27, rehash_event_payloads)];
+
+// Id prefixes from before `base::item_id`. Stack Exchange items were prefixed
+// with their site, which is also their source.
+const LEGACY_KINDS: &[&str] = &[
+    "bluesky",
+    "crates",
+    "devto",
+    "discord",
+    "dockerhub",
+    "github",
+    "github-code",
+    "github-discussion",
+    "github-release",
+    "github-trending",
+    "gitlab",
+    "hackernews-comment",
+    "mastodon",
+    "matrix",
+    "medium",
+    "news",
+    "npm",
+    "podcast",
+    "quora",
+    "slack",
+    "telegram",
+    "terraform",
+    "twitch",
+    "twitter",
+    "wikipedia",
+];
+
+// Whether `id` is already what `base::item_id` creates, a kind and 32 hex digits.
+fn is_hashed(id: &str) -> bool {
+    match id.rsplit_once('-') {
+        Some((_, hash)) => {
+            hash.len() == 32 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        }
+        None => false,
+    }
+}
+
+// The id the fetchers create now for an item stored as `<kind>-<platform id>`,
+// None if the kind is unknown or the id is current already.
+pub fn rehashed_id(id: &str, source: &str) -> Option<String> {
+    if is_hashed(id) {
+        return None;
+    }
+    let kind = LEGACY_KINDS
+        .iter()
+        .copied()
+        .chain(std::iter::once(source))
+        .filter(|kind| id.starts_with(kind) && id[kind.len()..].starts_with('-'))
+        .max_by_key(|kind| kind.len())?;
+    let platform_id = &id[kind.len() + 1..];
+    // Stack Exchange questions are identified by their id, not their URL.
+    let question_id = platform_id
+        .split_once("/questions/")
+        .and_then(|(_, path)| path.split('/').next())
+        .filter(|question_id| question_id.parse::<u64>().is_ok());
+    match question_id {
+        Some(question_id) if kind == source => Some(base::item_id(kind, question_id)),
+        _ => Some(base::item_id(kind, platform_id)),
+    }
+}
+
+// Ids used to be the kind and the platform id concatenated, often a full URL.
+// Items that end up with the same id, e.g. a question whose URL changed, are
+// merged into the first one.
+fn rehash_item_ids(conn: &mut PooledConn) -> mysql::Result<()> {
+    let rows: Vec<(String, String)> = conn.query("SELECT id, source FROM shareables")?;
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    for (old_id, source) in rows {
+        let new_id = match rehashed_id(&old_id, &source) {
+            Some(new_id) if new_id != old_id => new_id,
+            _ => continue,
+        };
+        for statement in [
+            "UPDATE IGNORE shareables SET id = :new_id WHERE id = :old_id",
+            "DELETE FROM shareables WHERE id = :old_id",
+            "UPDATE IGNORE shareable_tags SET shareable_id = :new_id WHERE shareable_id = :old_id",
+            "DELETE FROM shareable_tags WHERE shareable_id = :old_id",
+            "UPDATE reshares SET shareable_id = :new_id WHERE shareable_id = :old_id",
+            // The payload is what digests, gRPC and webhook replays send.
+            r"UPDATE item_events SET shareable_id = :new_id,
+                payload = IF(JSON_VALID(payload), JSON_SET(payload, '$.id', :new_id), payload)
+              WHERE shareable_id = :old_id",
+        ] {
+            tx.exec_drop(
+                statement,
+                params! { "old_id" => &old_id, "new_id" => &new_id },
+            )?;
+        }
+    }
+    tx.commit()
+}
+
+// The first version of migration 19 left the old id in the event payloads.
+fn rehash_event_payloads(conn: &mut PooledConn) -> mysql::Result<()> {
+    conn.query_drop(
+        r"UPDATE item_events SET payload = JSON_SET(payload, '$.id', shareable_id)
+          WHERE JSON_VALID(payload)
+            AND JSON_UNQUOTE(JSON_EXTRACT(payload, '$.id')) <> shareable_id",
+    )
+}
+
+// Tables with the columns and indexes the code relies on, as the migrations
+// above leave them. Update together with new migrations.
+const EXPECTED_SCHEMA: &[(&str, &[&str], &[&str])] = &[
+    (
+        "shareables",
+        &[
+            "id",
+            "title",
+            "url",
+            "date",
+            "source",
+            "issue_key",
+            "issue_url",
+            "starred_at",
+            "read_at",
+            "canonical_url",
+            "ingested_by",
+            "ingested_keyword",
+            "ingested_version",
+            "ingested_at",
+        ],
+        &[
+            "PRIMARY",
+            "shareables_canonical_url",
+            "shareables_title_fulltext",
+        ],
+    ),
+    (
+        "reshares",
+        &[
+            "id",
+            "shareable_id",
+            "text",
+            "target",
+            "scheduled_at",
+            "posted_at",
+            "post_url",
+            "error",
+        ],
+        &["PRIMARY", "reshares_due"],
+    ),
+    (
+        "item_events",
+        &["id", "event_type", "shareable_id", "payload", "occurred_at"],
+        &["PRIMARY", "item_events_occurred_at"],
+    ),
+    ("webhook_cursors", &["url", "last_event_id"], &["PRIMARY"]),
+    (
+        "shareable_tags",
+        &["shareable_id", "tag"],
+        &["PRIMARY", "shareable_tags_tag"],
+    ),
+    (
+        "fetch_checkpoints",
+        &["fetcher", "last_success_at"],
+        &["PRIMARY"],
+    ),
+    (
+        "source_cursors",
+        &["fetcher", "cursor_value", "updated_at"],
+        &["PRIMARY"],
+    ),
+    (
+        "push_subscriptions",
+        &["endpoint", "p256dh", "auth", "created_at"],
+        &["PRIMARY"],
+    ),
+    (
+        "notification_digests",
+        &["id", "last_event_id", "item_count", "sent_at"],
+        &["PRIMARY"],
+    ),
+    (
+        "notification_outbox",
+        &[
+            "id",
+            "channel",
+            "digest",
+            "target",
+            "payload",
+            "attempts",
+            "last_error",
+            "next_attempt_at",
+            "failed_at",
+            "created_at",
+        ],
+        &["PRIMARY", "notification_outbox_due"],
+    ),
+    (
+        "export_jobs",
+        &[
+            "id",
+            "status",
+            "source",
+            "date_from",
+            "date_to",
+            "scanned",
+            "total",
+            "written",
+            "attempts",
+            "error",
+            "created_at",
+            "finished_at",
+        ],
+        &["PRIMARY", "export_jobs_status"],
+    ),
+    ("snoozes", &["kind", "name", "until"], &["PRIMARY"]),
+];
+
+fn known_versions() -> impl Iterator<Item = u32> {
+    MIGRATIONS
+        .iter()
+        .map(|(version, _)| *version)
+        .chain(DATA_MIGRATIONS.iter().map(|(version, _)| *version))
+}
+
+// Applied versions this build doesn't know, the database was migrated by a
+// newer one.
+fn unknown_versions(applied: &[u32]) -> Vec<u32> {
+    let mut unknown: Vec<u32> = applied
+        .iter()
+        .filter(|version| !known_versions().any(|known| known == **version))
+        .copied()
+        .collect();
+    unknown.sort_unstable();
+    unknown
+}
+
+fn pending_versions(applied: &[u32]) -> Vec<u32> {
+    let mut pending: Vec<u32> = known_versions()
+        .filter(|version| !applied.contains(version))
+        .collect();
+    pending.sort_unstable();
+    pending
+}
+
+fn join(versions: &[u32]) -> String {
+    versions
+        .iter()
+        .map(|version| version.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Empty on a fresh database.
+fn applied_versions(conn: &mut PooledConn) -> mysql::Result<Vec<u32>> {
+    let table: Option<String> = conn.query_first("SHOW TABLES LIKE 'schema_migrations'")?;
+    match table {
+        Some(_) => conn.query("SELECT version FROM schema_migrations"),
+        None => Ok(vec![]),
+    }
+}
+
+// Every process checks this before touching data. An older build running next
+// to a newer one (e.g. during a rollout or after a rollback) could write rows
+// the newer schema means differently, so it refuses to start instead.
+pub fn check_version(pool: &mysql::Pool) -> Result<(), String> {
+    let mut conn = pool.get_conn().map_err(|e| format!("{}", e))?;
+    let applied = applied_versions(&mut conn).map_err(|e| format!("{}", e))?;
+    let unknown = unknown_versions(&applied);
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "the database has migrations {} this build doesn't know, it was migrated by a newer version",
+            join(&unknown)
+        ))
+    }
+}
+
+// For deployments that apply migrations out of band. Lists everything that is
+// missing, instead of failing on the first query that touches it.
+pub fn check(pool: &mysql::Pool) -> Result<(), String> {
+    let mut conn = pool.get_conn().map_err(|e| format!("{}", e))?;
+    let applied = applied_versions(&mut conn).map_err(|e| format!("{}", e))?;
+    let columns: Vec<(String, String)> = conn
+        .query(
+            r"SELECT TABLE_NAME, COLUMN_NAME FROM information_schema.COLUMNS
+              WHERE TABLE_SCHEMA = DATABASE()",
+        )
+        .map_err(|e| format!("{}", e))?;
+    let indexes: Vec<(String, String)> = conn
+        .query(
+            r"SELECT DISTINCT TABLE_NAME, INDEX_NAME FROM information_schema.STATISTICS
+              WHERE TABLE_SCHEMA = DATABASE()",
+        )
+        .map_err(|e| format!("{}", e))?;
+
+    let mut problems = vec![];
+    // Data migrations leave no trace in the schema.
+    let pending = pending_versions(&applied);
+    if !pending.is_empty() {
+        problems.push(format!("pending migrations {}", join(&pending)));
+    }
+    for (table, expected_columns, expected_indexes) in EXPECTED_SCHEMA {
+        if !columns.iter().any(|(t, _)| t == table) {
+            problems.push(format!("missing table {}", table));
+            continue;
+        }
+        let missing_columns: Vec<&str> = expected_columns
+            .iter()
+            .filter(|column| !columns.iter().any(|(t, c)| t == table && c == *column))
+            .copied()
+            .collect();
+        if !missing_columns.is_empty() {
+            problems.push(format!(
+                "{}: missing columns {}",
+                table,
+                missing_columns.join(", ")
+            ));
+        }
+        let missing_indexes: Vec<&str> = expected_indexes
+            .iter()
+            .filter(|index| !indexes.iter().any(|(t, i)| t == table && i == *index))
+            .copied()
+            .collect();
+        if !missing_indexes.is_empty() {
+            problems.push(format!(
+                "{}: missing indexes {}",
+                table,
+                missing_indexes.join(", ")
+            ));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems.join("; "))
+    }
+}
+
+pub fn run(pool: &mysql::Pool) -> mysql::Result<()> {
+    let mut conn = pool.get_conn()?;
+    conn.query_drop(
+        r"CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INT UNSIGNED NOT NULL PRIMARY KEY,
+            applied_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+    )?;
+
+    let applied: Vec<u32> = conn.query("SELECT version FROM schema_migrations")?;
+    for (version, statement) in MIGRATIONS {
+        if applied.contains(version) {
+            continue;
+        }
+
+        info!("Applying migration {}", version);
+        conn.query_drop(statement)?;
+        record(&mut conn, *version)?;
+    }
+    for (version, migrate) in DATA_MIGRATIONS {
+        if applied.contains(version) {
+            continue;
+        }
+
+        info!("Applying data migration {}", version);
+        migrate(&mut conn)?;
+        record(&mut conn, *version)?;
+    }
+
+    Ok(())
+}
+
+fn record(conn: &mut PooledConn, version: u32) -> mysql::Result<()> {
+    conn.exec_drop(
+        "INSERT INTO schema_migrations (version) VALUES (:version)",
+        params! { "version" => version },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_applied_versions() {
+        let all: Vec<u32> = known_versions().collect();
+        assert!(unknown_versions(&all).is_empty());
+        assert!(pending_versions(&all).is_empty());
+
+        let mut newer = all.clone();
+        newer.extend([1001, 1000]);
+        assert_eq!(unknown_versions(&newer), vec![1000, 1001]);
+
+        let older: Vec<u32> = all
+            .iter()
+            .copied()
+            .filter(|version| *version != 19)
+            .collect();
+        assert_eq!(pending_versions(&older), vec![19]);
+    }
+
+    #[test]
+    fn rehashes_legacy_ids() {
+        assert_eq!(
+            rehashed_id("twitter-1532", "twitter"),
+            Some(base::item_id("twitter", "1532"))
+        );
+        assert_eq!(
+            rehashed_id("github-release-42", "github"),
+            Some(base::item_id("github-release", "42"))
+        );
+        assert_eq!(
+            rehashed_id(
+                "stackoverflow-https://stackoverflow.com/questions/7243/how-to-cdktf",
+                "stackoverflow"
+            ),
+            Some(base::item_id("stackoverflow", 7243))
+        );
+        assert_eq!(rehashed_id("unknown-1", "unknown-source"), None);
+    }
+
+    #[test]
+    fn keeps_current_ids() {
+        let id = base::item_id("twitter", "1532");
+        assert_eq!(rehashed_id(&id, "twitter"), None);
+        let id = base::item_id("stackoverflow", 7243);
+        assert_eq!(rehashed_id(&id, "stackoverflow"), None);
+    }
+}
@@ -0,0 +1,230 @@
+use chrono::{DateTime, Datelike, NaiveTime, TimeZone, Utc, Weekday};
+use mysql::prelude::*;
+use mysql::*;
+use std::sync::Arc;
+use tokio::task::{self, JoinHandle};
+use tokio::time::Instant;
+use tracing::{error, info};
+
+use crate::clock::Clock;
+use crate::maintenance;
+use crate::notifier;
+use crate::shutdown;
+use crate::snooze;
+use crate::storage;
+
+// When digests go out, in UTC: `daily 08:00` or `weekly mon 08:00`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DigestSchedule {
+    weekday: Option<Weekday>,
+    at: NaiveTime,
+}
+
+impl DigestSchedule {
+    pub fn parse(schedule: &str) -> Result<DigestSchedule, String> {
+        let parts: Vec<&str> = schedule.split_whitespace().collect();
+        let (weekday, at) = match parts.as_slice() {
+            ["daily", at] => (None, at),
+            ["weekly", weekday, at] => (
+                Some(
+                    weekday
+                        .parse::<Weekday>()
+                        .map_err(|_| format!("NOTIFY_DIGEST: unknown weekday {}", weekday))?,
+                ),
+                at,
+            ),
+            _ => {
+                return Err(format!(
+                    "NOTIFY_DIGEST: expected `daily HH:MM` or `weekly mon HH:MM`, got {}",
+                    schedule
+                ))
+            }
+        };
+        let at = NaiveTime::parse_from_str(at, "%H:%M")
+            .map_err(|e| format!("NOTIFY_DIGEST: {}: {}", at, e))?;
+        Ok(DigestSchedule { weekday, at })
+    }
+
+    // The first scheduled time strictly after `now`.
+    pub fn next_after(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let mut day = now.naive_utc().date();
+        loop {
+            let candidate = Utc.from_utc_datetime(&day.and_time(self.at));
+            let on_day = match self.weekday {
+                Some(weekday) => day.weekday() == weekday,
+                None => true,
+            };
+            if candidate > now && on_day {
+                return candidate;
+            }
+            day = day
+                .succ_opt()
+                .expect("digest schedule ran past the end of time");
+        }
+    }
+}
+
+// The item event the last digest went up to. Without any digest yet, the first
+// one starts from now instead of from the beginning of the event log.
+fn cursor(pool: &Pool) -> mysql::Result<u64> {
+    let mut conn = pool.get_conn()?;
+    let last: Option<u64> = conn
+        .query_first("SELECT last_event_id FROM notification_digests ORDER BY id DESC LIMIT 1")?;
+    match last {
+        Some(last) => Ok(last),
+        None => {
+            let last = storage::last_event_id(pool)?;
+            record(pool, last, 0)?;
+            Ok(last)
+        }
+    }
+}
+
+fn record(pool: &Pool, last_event_id: u64, item_count: usize) -> mysql::Result<()> {
+    let mut conn = pool.get_conn()?;
+    conn.exec_drop(
+        r"INSERT INTO notification_digests (last_event_id, item_count)
+          VALUES (:last_event_id, :item_count)",
+        params! {
+            "last_event_id" => last_event_id,
+            "item_count" => item_count,
+        },
+    )
+}
+
+fn send(pool: &Pool) -> mysql::Result<()> {
+    let mut cursor = cursor(pool)?;
+    let mut shareables = vec![];
+    loop {
+        let (last, created) = storage::created_since(pool, cursor)?;
+        if last == cursor {
+            break;
+        }
+        cursor = last;
+        shareables.extend(created);
+    }
+    let shareables = snooze::unmuted(pool, shareables)?;
+
+    info!("Sending a digest of {} new items", shareables.len());
+    notifier::dispatch_digest(&shareables);
+    record(pool, cursor, shareables.len())
+}
+
+// The next digest and how long until then.
+fn next_digest(
+    schedule: &DigestSchedule,
+    clock: &dyn Clock,
+) -> (DateTime<Utc>, std::time::Duration) {
+    let now = clock.now();
+    let next = schedule.next_after(now);
+    (next, (next - now).to_std().unwrap_or_default())
+}
+
+// Everything stored since the last digest goes out in one notification per
+// channel, instead of one per fetcher run.
+pub fn spawn_scheduler(
+    schedule: DigestSchedule,
+    pool: Arc<Pool>,
+    clock: &'static dyn Clock,
+) -> JoinHandle<()> {
+    task::spawn(async move {
+        // Makes sure the first digest covers the items from now on.
+        if let Err(e) = cursor(&pool) {
+            error!("Could not load the last digest: {}", e);
+        }
+
+        loop {
+            let (next, wait) = next_digest(&schedule, clock);
+            info!("Next digest at {}", next);
+            if !shutdown::sleep_until(Instant::now() + wait).await {
+                return;
+            }
+            if !maintenance::wait_until_over().await {
+                return;
+            }
+            if let Err(e) = send(&pool) {
+                error!("Could not send the digest: {}", e);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::TestClock;
+    use std::time::Duration;
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn parses_schedules() {
+        assert_eq!(
+            DigestSchedule::parse("daily 08:00"),
+            Ok(DigestSchedule {
+                weekday: None,
+                at: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            })
+        );
+        assert_eq!(
+            DigestSchedule::parse("weekly Mon 17:30"),
+            Ok(DigestSchedule {
+                weekday: Some(Weekday::Mon),
+                at: NaiveTime::from_hms_opt(17, 30, 0).unwrap(),
+            })
+        );
+        assert!(DigestSchedule::parse("hourly").is_err());
+        assert!(DigestSchedule::parse("daily 25:00").is_err());
+        assert!(DigestSchedule::parse("weekly someday 08:00").is_err());
+    }
+
+    #[test]
+    fn next_daily_digest() {
+        let schedule = DigestSchedule::parse("daily 08:00").unwrap();
+        assert_eq!(
+            schedule.next_after(at("2022-06-01T07:59:00Z")),
+            at("2022-06-01T08:00:00Z")
+        );
+        assert_eq!(
+            schedule.next_after(at("2022-06-01T08:00:00Z")),
+            at("2022-06-02T08:00:00Z")
+        );
+    }
+
+    #[test]
+    fn next_weekly_digest() {
+        // 2022-06-01 was a Wednesday.
+        let schedule = DigestSchedule::parse("weekly mon 08:00").unwrap();
+        assert_eq!(
+            schedule.next_after(at("2022-06-01T12:00:00Z")),
+            at("2022-06-06T08:00:00Z")
+        );
+        assert_eq!(
+            schedule.next_after(at("2022-06-06T09:00:00Z")),
+            at("2022-06-13T08:00:00Z")
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_the_next_digest_by_the_clock() {
+        let schedule = DigestSchedule::parse("daily 08:00").unwrap();
+        let clock = TestClock::new(at("2022-06-01T07:59:00Z"));
+        assert_eq!(
+            next_digest(&schedule, &clock),
+            (at("2022-06-01T08:00:00Z"), Duration::from_secs(60))
+        );
+
+        tokio::time::advance(Duration::from_secs(61)).await;
+        assert_eq!(
+            next_digest(&schedule, &clock),
+            (
+                at("2022-06-02T08:00:00Z"),
+                Duration::from_secs(24 * 60 * 60 - 1)
+            )
+        );
+    }
+}
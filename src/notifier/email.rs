@@ -0,0 +1,141 @@
+use async_trait::async_trait;
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use crate::fetcher::base::Shareable;
+use crate::notifier::template::{self, ItemTemplate};
+use crate::notifier::{Delivery, Notifier, Undelivered};
+use crate::Config;
+
+pub struct EmailNotifier {
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    keyword: String,
+    per_item: bool,
+    template: Option<ItemTemplate>,
+}
+
+fn mailbox(address: &str) -> Result<Mailbox, String> {
+    address
+        .parse::<Mailbox>()
+        .map_err(|e| format!("Invalid email address {}: {}", address, e))
+}
+
+impl EmailNotifier {
+    pub fn from_config(config: &Config, host: &str) -> Result<EmailNotifier, String> {
+        let builder = match config.smtp_tls.as_str() {
+            // Implicit TLS, usually port 465.
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(host),
+            // Plain connection upgraded with STARTTLS, usually port 587.
+            "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host),
+            // Local relays only, e.g. a sidecar on port 25.
+            "none" => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                host,
+            )),
+            other => {
+                return Err(format!(
+                    "SMTP_TLS: unknown mode {}, use tls, starttls or none",
+                    other
+                ))
+            }
+        }
+        .map_err(|e| format!("{}", e))?;
+
+        let builder = match config.smtp_port {
+            Some(port) => builder.port(port),
+            None => builder,
+        };
+        let builder = match (&config.smtp_username, &config.smtp_password) {
+            (Some(username), password) => builder.credentials(Credentials::new(
+                username.clone(),
+                password.clone().unwrap_or_default(),
+            )),
+            (None, _) => builder,
+        };
+
+        Ok(EmailNotifier {
+            mailer: builder.build(),
+            from: mailbox(config.notify_email_from.as_deref().unwrap_or_default())?,
+            to: config
+                .notify_email_to
+                .iter()
+                .map(|address| mailbox(address))
+                .collect::<Result<_, _>>()?,
+            keyword: config.keyword.clone(),
+            per_item: config.notify_email_per_item,
+            template: template::for_channel(config, "email")?,
+        })
+    }
+
+    async fn send(&self, subject: String, shareables: &[Shareable]) -> Result<(), String> {
+        let body = shareables
+            .iter()
+            .map(|shareable| match &self.template {
+                Some(template) => format!("{}\n", template.render(shareable, str::to_string)),
+                None => format!(
+                    "{}\n{}\n{} · {}\n",
+                    shareable.display_title(),
+                    shareable.url,
+                    shareable.source,
+                    shareable.date
+                ),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut message = Message::builder()
+            .from(self.from.clone())
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN);
+        for to in self.to.iter() {
+            message = message.to(to.clone());
+        }
+        let message = message.body(body).map_err(|e| format!("{}", e))?;
+
+        self.mailer
+            .send(message)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("{}", e))
+    }
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &'static str {
+        "email"
+    }
+
+    async fn notify(&self, shareables: &[Shareable], _target: Option<&str>) -> Delivery {
+        if !self.per_item {
+            let subject = format!("{} new mentions of {}", shareables.len(), self.keyword);
+            return self
+                .send(subject, shareables)
+                .await
+                .map_err(|e| Undelivered::all(shareables, e));
+        }
+
+        for (i, shareable) in shareables.iter().enumerate() {
+            let subject = format!("[{}] {}", shareable.source, shareable.display_title());
+            self.send(subject, std::slice::from_ref(shareable))
+                .await
+                // The emails before went out.
+                .map_err(|e| Undelivered::all(&shareables[i..], e))?;
+        }
+        Ok(())
+    }
+
+    // Always one email, even with NOTIFY_EMAIL_PER_ITEM.
+    async fn notify_digest(&self, shareables: &[Shareable], _target: Option<&str>) -> Delivery {
+        let subject = format!(
+            "Digest: {} new mentions of {}",
+            shareables.len(),
+            self.keyword
+        );
+        self.send(subject, shareables)
+            .await
+            .map_err(|e| Undelivered::all(shareables, e))
+    }
+}
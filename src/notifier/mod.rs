@@ -0,0 +1,493 @@
+pub mod digest;
+pub mod email;
+pub mod ntfy;
+pub mod outbox;
+pub mod slack;
+pub mod spike;
+pub mod template;
+pub mod throttle;
+pub mod webhook;
+pub mod webpush;
+
+use async_trait::async_trait;
+use mysql::Pool;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::task::{self, JoinHandle};
+use tokio::time;
+use tracing::{debug, error, info, warn};
+
+use self::throttle::Throttle;
+use crate::bus::{self, Event};
+use crate::clock::{self, Clock};
+use crate::fetcher::base::Shareable;
+use crate::maintenance;
+use crate::shutdown;
+use crate::snooze;
+use crate::Config;
+
+// What a delivery did not get to. Only this goes to the outbox, so whoever
+// already got the items doesn't get them again on the retry.
+#[derive(Debug, Clone)]
+pub struct Undelivered {
+    // For channels with several endpoints, e.g. webhook URLs or push
+    // subscriptions, the one that missed the items. None for all of them.
+    pub target: Option<String>,
+    pub shareables: Vec<Shareable>,
+    pub error: String,
+}
+
+impl Undelivered {
+    // Nothing from `shareables` on went out, e.g. for channels that send
+    // items one after the other and stop at the first failure.
+    pub fn all(shareables: &[Shareable], error: String) -> Vec<Undelivered> {
+        vec![Undelivered {
+            target: None,
+            shareables: shareables.to_vec(),
+            error,
+        }]
+    }
+}
+
+pub type Delivery = Result<(), Vec<Undelivered>>;
+
+// The page of the item on the dashboard, which notifications link to instead
+// of the item itself. Relative without PUBLIC_URL.
+pub fn item_page(public_url: Option<&str>, shareable: &Shareable) -> String {
+    format!(
+        "{}/item/{}",
+        public_url.unwrap_or_default().trim_end_matches('/'),
+        shareable.id
+    )
+}
+
+fn errors(undelivered: &[Undelivered]) -> String {
+    undelivered
+        .iter()
+        .map(|undelivered| undelivered.error.as_str())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+// A channel new items are announced on. Every batch of newly created items is
+// handed to each notifier once, right after it was stored.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // `target` is set for retries of what one endpoint missed, only that one
+    // is sent to then.
+    async fn notify(&self, shareables: &[Shareable], target: Option<&str>) -> Delivery;
+
+    // Everything since the last digest, see `NOTIFY_DIGEST`. Channels that
+    // already summarize big batches can leave this as is.
+    async fn notify_digest(&self, shareables: &[Shareable], target: Option<&str>) -> Delivery {
+        self.notify(shareables, target).await
+    }
+}
+
+// A notifier together with its quiet hours and rate limit.
+struct Channel {
+    notifier: Arc<dyn Notifier>,
+    throttle: Throttle,
+}
+
+pub struct Dispatcher {
+    channels: Vec<Channel>,
+    // See every new item as it is stored, no matter the throttles or
+    // digests, a held back batch would look like a spike.
+    alerts: Vec<Arc<dyn Notifier>>,
+    // New items wait for the next digest instead of being sent right away.
+    digest_only: bool,
+    // Failed deliveries go to the outbox to be retried.
+    pool: Option<Arc<Pool>>,
+    // For the time of day of the quiet hours.
+    clock: &'static dyn Clock,
+}
+
+impl Dispatcher {
+    pub fn new(
+        notifiers: Vec<(Arc<dyn Notifier>, Throttle)>,
+        alerts: Vec<Arc<dyn Notifier>>,
+        digest_only: bool,
+        pool: Option<Arc<Pool>>,
+        clock: &'static dyn Clock,
+    ) -> Dispatcher {
+        Dispatcher {
+            channels: notifiers
+                .into_iter()
+                .map(|(notifier, throttle)| Channel { notifier, throttle })
+                .collect(),
+            alerts,
+            digest_only,
+            pool,
+            clock,
+        }
+    }
+
+    fn channel(&self, name: &str) -> Option<Arc<dyn Notifier>> {
+        self.channels
+            .iter()
+            .map(|channel| &channel.notifier)
+            .chain(self.alerts.iter())
+            .find(|notifier| notifier.name() == name)
+            .cloned()
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.channels
+            .iter()
+            .map(|channel| channel.notifier.name())
+            .chain(self.alerts.iter().map(|alert| alert.name()))
+            .collect()
+    }
+
+    pub fn is_throttled(&self) -> bool {
+        self.channels
+            .iter()
+            .any(|channel| channel.throttle.is_active())
+    }
+
+    pub fn dispatch(&self, shareables: &[Shareable]) {
+        if !shareables.is_empty() {
+            for alert in self.alerts.iter() {
+                self.send(alert.clone(), shareables.to_vec(), false);
+            }
+        }
+        if self.digest_only {
+            return;
+        }
+        self.release(shareables);
+    }
+
+    // Sends items held back by quiet hours or rate limits once they allow it.
+    pub fn flush(&self) {
+        self.release(&[]);
+    }
+
+    fn release(&self, shareables: &[Shareable]) {
+        let at = self.clock.now().time();
+        let now = Instant::now();
+        for channel in self.channels.iter() {
+            match channel.throttle.admit(shareables, at, now) {
+                Some(batch) => self.send(channel.notifier.clone(), batch, false),
+                None if !shareables.is_empty() => debug!(
+                    "Holding back {} new items for {}",
+                    channel.throttle.held(),
+                    channel.notifier.name()
+                ),
+                None => {}
+            }
+        }
+    }
+
+    // Digests are scheduled explicitly and skip the throttles.
+    pub fn dispatch_digest(&self, shareables: &[Shareable]) {
+        if shareables.is_empty() {
+            return;
+        }
+        for channel in self.channels.iter() {
+            self.send(channel.notifier.clone(), shareables.to_vec(), true);
+        }
+    }
+
+    // Every notifier runs in its own task, so a slow or failing channel holds
+    // up neither the fetcher nor the other channels.
+    fn send(&self, notifier: Arc<dyn Notifier>, shareables: Vec<Shareable>, digest: bool) {
+        let pool = self.pool.clone();
+        task::spawn(async move {
+            let result = if digest {
+                notifier.notify_digest(&shareables, None).await
+            } else {
+                notifier.notify(&shareables, None).await
+            };
+            match (result, pool) {
+                (Ok(()), _) => debug!("Sent {} new items to {}", shareables.len(), notifier.name()),
+                (Err(undelivered), Some(pool)) => {
+                    warn!(
+                        "Could not send all of {} new items to {}, retrying later: {}",
+                        shareables.len(),
+                        notifier.name(),
+                        errors(&undelivered)
+                    );
+                    for undelivered in undelivered.iter() {
+                        if let Err(e) = outbox::enqueue(&pool, notifier.name(), undelivered, digest)
+                        {
+                            error!("Could not queue the retry for {}: {}", notifier.name(), e);
+                        }
+                    }
+                }
+                (Err(undelivered), None) => error!(
+                    "Could not send all of {} new items to {}: {}",
+                    shareables.len(),
+                    notifier.name(),
+                    errors(&undelivered)
+                ),
+            }
+        });
+    }
+
+    fn held(&self) -> usize {
+        self.channels
+            .iter()
+            .map(|channel| channel.throttle.held())
+            .sum()
+    }
+}
+
+// The notifiers of every channel that is configured.
+pub fn from_config(config: &Config, pool: Arc<Pool>) -> Result<Dispatcher, String> {
+    let mut notifiers: Vec<Arc<dyn Notifier>> = vec![];
+    if let Some(webhook_url) = config.notify_slack_webhook_url.clone() {
+        notifiers.push(Arc::new(slack::SlackNotifier {
+            webhook_url,
+            template: template::for_channel(config, "slack")?,
+        }));
+    }
+    if let Some(topic_url) = config.notify_ntfy_url.clone() {
+        notifiers.push(Arc::new(ntfy::NtfyNotifier {
+            topic_url,
+            access_token: config.notify_ntfy_token.clone(),
+            template: template::for_channel(config, "ntfy")?,
+            public_url: config.public_url.clone(),
+        }));
+    }
+    if let Some(host) = config.smtp_host.as_deref() {
+        notifiers.push(Arc::new(email::EmailNotifier::from_config(config, host)?));
+    }
+    if !config.notify_webhook_urls.is_empty() {
+        notifiers.push(Arc::new(webhook::WebhookNotifier::from_config(config)?));
+    }
+    if config.webpush_vapid_private_key.is_some() {
+        notifiers.push(Arc::new(webpush::WebPushNotifier::from_config(
+            config,
+            pool.clone(),
+        )?));
+    }
+    let notifiers = notifiers
+        .into_iter()
+        .map(|notifier| {
+            let throttle = Throttle::for_channel(notifier.name(), config)?;
+            Ok((notifier, throttle))
+        })
+        .collect::<Result<_, String>>()?;
+    let mut alerts: Vec<Arc<dyn Notifier>> = vec![];
+    if let Some(spike_alert) = spike::SpikeAlert::from_config(config)? {
+        alerts.push(Arc::new(spike_alert));
+    }
+    Ok(Dispatcher::new(
+        notifiers,
+        alerts,
+        config.notify_digest.is_some(),
+        Some(pool),
+        &clock::SYSTEM,
+    ))
+}
+
+static DISPATCHER: OnceLock<Dispatcher> = OnceLock::new();
+
+// Called once on startup, before the fetchers start.
+// Also starts listening for new items. The listener is not part of the
+// shutdown drain, items stored by the last fetch runs still go out while the
+// process exits.
+pub fn configure(dispatcher: Dispatcher) {
+    info!("Notifying on: {:?}", dispatcher.names());
+    if DISPATCHER.set(dispatcher).is_err() {
+        warn!("Notifiers were already configured");
+        return;
+    }
+    let mut events = bus::subscribe("notifier");
+    task::spawn(async move {
+        while let Some(event) = events.recv().await {
+            if let (
+                Event::ItemsInserted {
+                    fetcher,
+                    shareables,
+                },
+                Some(dispatcher),
+            ) = (event, DISPATCHER.get())
+            {
+                // Held back in the bus until maintenance is over.
+                maintenance::wait_until_over().await;
+                let shareables = match &dispatcher.pool {
+                    Some(pool) => snooze::mute(pool, &shareables).unwrap_or_else(|e| {
+                        error!("Could not check snoozes, notifying anyway: {}", e);
+                        shareables
+                    }),
+                    None => shareables,
+                };
+                debug!("Notifying about {} new {}", shareables.len(), fetcher);
+                dispatcher.dispatch(&shareables);
+            }
+        }
+    });
+}
+
+// Checks every minute whether held back items can go out now.
+pub fn spawn_flusher() -> JoinHandle<()> {
+    task::spawn(async move {
+        let mut interval = time::interval(Duration::from_secs(60));
+        while shutdown::tick(&mut interval).await {
+            if maintenance::is_active() {
+                continue;
+            }
+            if let Some(dispatcher) = DISPATCHER.get() {
+                dispatcher.flush();
+            }
+        }
+        if let Some(dispatcher) = DISPATCHER.get() {
+            let held = dispatcher.held();
+            if held > 0 {
+                warn!("Shutting down with {} held back notifications unsent", held);
+            }
+        }
+    })
+}
+
+// The channel failed deliveries are retried on.
+pub fn channel(name: &str) -> Option<Arc<dyn Notifier>> {
+    DISPATCHER.get()?.channel(name)
+}
+
+pub fn has_channels() -> bool {
+    matches!(DISPATCHER.get(), Some(dispatcher) if !dispatcher.names().is_empty())
+}
+
+pub fn is_throttled() -> bool {
+    matches!(DISPATCHER.get(), Some(dispatcher) if dispatcher.is_throttled())
+}
+
+// Called by the digest scheduler.
+pub fn dispatch_digest(shareables: &[Shareable]) {
+    if let Some(dispatcher) = DISPATCHER.get() {
+        dispatcher.dispatch_digest(shareables);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::clock::TestClock;
+    use std::sync::Mutex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    pub(super) fn shareables(count: usize) -> Vec<Shareable> {
+        (1..=count)
+            .map(|id| Shareable {
+                id: format!("twitter-{}", id),
+                title: String::from("cdktf is great"),
+                date: String::from("2022-06-01T12:00:00Z"),
+                url: format!("https://twitter.com/twitter/status/{}", id),
+                source: String::from("twitter"),
+                canonical_url: None,
+            })
+            .collect()
+    }
+
+    pub(super) fn ids(undelivered: &Undelivered) -> Vec<&str> {
+        undelivered
+            .shareables
+            .iter()
+            .map(|shareable| shareable.id.as_str())
+            .collect()
+    }
+
+    // Answers the requests in order with these statuses, the last one for all
+    // further requests.
+    pub(crate) async fn serve(statuses: Vec<u16>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        task::spawn(async move {
+            for i in 0.. {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = vec![0; 64 * 1024];
+                let mut read = 0;
+                // Headers and the small bodies of these tests come in one go.
+                while !String::from_utf8_lossy(&request[..read]).contains("\r\n\r\n") {
+                    read += socket.read(&mut request[read..]).await.unwrap();
+                }
+                let status = statuses[i.min(statuses.len() - 1)];
+                let response = format!(
+                    "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn keeps_only_items_after_the_failure() {
+        let ntfy = ntfy::NtfyNotifier {
+            topic_url: serve(vec![200, 500]).await,
+            access_token: None,
+            template: None,
+            public_url: None,
+        };
+        let undelivered = ntfy.notify(&shareables(3), None).await.unwrap_err();
+        assert_eq!(undelivered.len(), 1);
+        assert_eq!(undelivered[0].target, None);
+        assert_eq!(ids(&undelivered[0]), vec!["twitter-2", "twitter-3"]);
+    }
+
+    // Counts the items it was handed.
+    #[derive(Default)]
+    struct Recorder(Mutex<usize>);
+
+    #[async_trait]
+    impl Notifier for Recorder {
+        fn name(&self) -> &'static str {
+            "recorder"
+        }
+
+        async fn notify(&self, shareables: &[Shareable], _target: Option<&str>) -> Delivery {
+            *self.0.lock().unwrap() += shareables.len();
+            Ok(())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn quiet_hours_follow_the_clock() {
+        let config: Config = envy::from_iter(
+            [
+                ("DATABASE_URL", "mysql://root@db:3306/notifier"),
+                ("KEYWORD", "cdktf"),
+                ("INTERVAL_IN_SEC", "300"),
+                ("NOTIFY_QUIET_HOURS", "22:00-07:00"),
+            ]
+            .into_iter()
+            .map(|(name, value)| (String::from(name), String::from(value))),
+        )
+        .unwrap();
+        let recorder = Arc::new(Recorder::default());
+        let notifier: Arc<dyn Notifier> = recorder.clone();
+        let clock = Box::leak(Box::new(TestClock::new(
+            "2022-06-01T23:00:00Z".parse().unwrap(),
+        )));
+        let dispatcher = Dispatcher::new(
+            vec![(
+                notifier,
+                Throttle::for_channel("recorder", &config).unwrap(),
+            )],
+            vec![],
+            false,
+            None,
+            clock,
+        );
+
+        dispatcher.dispatch(&shareables(2));
+        assert_eq!(dispatcher.held(), 2);
+        time::advance(Duration::from_secs(7 * 60 * 60)).await;
+        dispatcher.flush();
+        assert_eq!(dispatcher.held(), 2);
+
+        time::advance(Duration::from_secs(60 * 60)).await;
+        dispatcher.flush();
+        assert_eq!(dispatcher.held(), 0);
+        // Let the delivery task run.
+        time::sleep(Duration::from_millis(1)).await;
+        assert_eq!(*recorder.0.lock().unwrap(), 2);
+    }
+}
@@ -0,0 +1,176 @@
+use async_trait::async_trait;
+use std::time::Duration;
+
+use crate::fetcher::base::Shareable;
+use crate::http;
+use crate::notifier::template::ItemTemplate;
+use crate::notifier::{self, Delivery, Notifier, Undelivered};
+
+// Bigger batches, e.g. after a catch-up, get one summary instead of a burst
+// of pushes.
+const MAX_PUSHES_PER_BATCH: usize = 5;
+
+pub struct NtfyNotifier {
+    pub topic_url: String,
+    pub access_token: Option<String>,
+    pub template: Option<ItemTemplate>,
+    pub public_url: Option<String>,
+}
+
+// ntfy priorities go from 1 (min) to 5 (max), 3 is the default. Questions
+// nobody answered yet are the ones worth a louder push.
+fn priority(shareable: &Shareable) -> &'static str {
+    if shareable.title.contains(":question:") {
+        "4"
+    } else if shareable.title.contains(":white_check_mark:") {
+        "2"
+    } else {
+        "3"
+    }
+}
+
+// The first items and how many more there are.
+fn summary(shareables: &[Shareable]) -> String {
+    let mut lines: Vec<String> = shareables
+        .iter()
+        .take(MAX_PUSHES_PER_BATCH)
+        .map(|shareable| format!("[{}] {}", shareable.source, shareable.display_title()))
+        .collect();
+    if shareables.len() > MAX_PUSHES_PER_BATCH {
+        lines.push(format!(
+            "and {} more",
+            shareables.len() - MAX_PUSHES_PER_BATCH
+        ));
+    }
+    lines.join("\n")
+}
+
+impl NtfyNotifier {
+    // ntfy opens the link in a browser, so it has to be absolute. Without
+    // PUBLIC_URL that is only the item itself.
+    fn click(&self, shareable: &Shareable) -> String {
+        match &self.public_url {
+            Some(public_url) => notifier::item_page(Some(public_url), shareable),
+            None => shareable.link().to_string(),
+        }
+    }
+
+    // Summaries open the dashboard, if there is one to open.
+    async fn push_summary(&self, title: &str, shareables: &[Shareable]) -> Delivery {
+        let priority = shareables.iter().map(priority).max().unwrap_or("3");
+        let click = self
+            .public_url
+            .as_deref()
+            .map(|public_url| format!("{}/", public_url.trim_end_matches('/')));
+        self.push(title, &summary(shareables), priority, click.as_deref())
+            .await
+            .map_err(|e| Undelivered::all(shareables, e))
+    }
+
+    async fn push(
+        &self,
+        title: &str,
+        message: &str,
+        priority: &str,
+        click: Option<&str>,
+    ) -> Result<(), String> {
+        let request = http::client()
+            .post(&self.topic_url)
+            .timeout(Duration::from_secs(10))
+            .header("Title", title)
+            .header("Priority", priority)
+            .body(message.to_string());
+        let request = match &self.access_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        };
+        let request = match click {
+            Some(url) => request.header("Click", url),
+            None => request,
+        };
+
+        request
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map(|_| ())
+            .map_err(|e| format!("{}", e.without_url()))
+    }
+}
+
+#[async_trait]
+impl Notifier for NtfyNotifier {
+    fn name(&self) -> &'static str {
+        "ntfy"
+    }
+
+    async fn notify(&self, shareables: &[Shareable], _target: Option<&str>) -> Delivery {
+        if shareables.len() > MAX_PUSHES_PER_BATCH {
+            let title = format!("{} new mentions", shareables.len());
+            return self.push_summary(&title, shareables).await;
+        }
+
+        for (i, shareable) in shareables.iter().enumerate() {
+            // Header values have to be ASCII-safe for some proxies, the title
+            // goes into the body as well.
+            let title = format!("New on {}", shareable.source);
+            let message = match &self.template {
+                Some(template) => template.render(shareable, str::to_string),
+                None => shareable.display_title(),
+            };
+            self.push(
+                &title,
+                &message,
+                priority(shareable),
+                Some(&self.click(shareable)),
+            )
+            .await
+            // The items before went out.
+            .map_err(|e| Undelivered::all(&shareables[i..], e))?;
+        }
+        Ok(())
+    }
+
+    // One push, however many items there are.
+    async fn notify_digest(&self, shareables: &[Shareable], _target: Option<&str>) -> Delivery {
+        let title = format!("Digest: {} new mentions", shareables.len());
+        self.push_summary(&title, shareables).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifier::tests::shareables;
+
+    #[test]
+    fn links_to_item_pages() {
+        let mut ntfy = NtfyNotifier {
+            topic_url: String::from("https://ntfy.sh/cdktf"),
+            access_token: None,
+            template: None,
+            public_url: Some(String::from("https://news.example.com/")),
+        };
+        let shareable = &shareables(1)[0];
+        assert_eq!(
+            ntfy.click(shareable),
+            "https://news.example.com/item/twitter-1"
+        );
+        ntfy.public_url = None;
+        assert_eq!(
+            ntfy.click(shareable),
+            "https://twitter.com/twitter/status/1"
+        );
+    }
+
+    #[test]
+    fn summarizes_the_first_items() {
+        assert_eq!(
+            summary(&shareables(2)),
+            "[twitter] cdktf is great\n[twitter] cdktf is great"
+        );
+        let message = summary(&shareables(8));
+        assert_eq!(message.lines().count(), MAX_PUSHES_PER_BATCH + 1);
+        assert!(message.ends_with("and 3 more"), "{}", message);
+    }
+}
@@ -0,0 +1,245 @@
+use mysql::prelude::*;
+use mysql::*;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::{self, JoinHandle};
+use tokio::time;
+use tracing::{error, info, warn};
+
+use crate::fetcher::base::Shareable;
+use crate::maintenance;
+use crate::notifier::{self, Undelivered};
+use crate::shutdown;
+
+// Deliveries are given up after this many attempts, the row stays for
+// inspection.
+const MAX_ATTEMPTS: u32 = 8;
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+// One minute after the first failure, doubling up to an hour.
+fn backoff(attempts: u32) -> Duration {
+    Duration::from_secs(60 * 2u64.pow(attempts.saturating_sub(1).min(6))).min(MAX_BACKOFF)
+}
+
+struct Entry {
+    id: u64,
+    channel: String,
+    digest: bool,
+    // The endpoint of the channel that failed, None for all of them.
+    target: Option<String>,
+    shareables: Vec<Shareable>,
+    attempts: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Counts {
+    pub pending: u64,
+    pub failed: u64,
+}
+
+fn insert(
+    pool: &Pool,
+    channel: &str,
+    undelivered: &Undelivered,
+    digest: bool,
+    attempts: u32,
+) -> mysql::Result<()> {
+    let statement = if attempts >= MAX_ATTEMPTS {
+        r"INSERT INTO notification_outbox
+            (channel, digest, target, payload, attempts, last_error, next_attempt_at, failed_at)
+          VALUES (:channel, :digest, :target, :payload, :attempts, :error,
+            UTC_TIMESTAMP(), UTC_TIMESTAMP())"
+    } else {
+        r"INSERT INTO notification_outbox
+            (channel, digest, target, payload, attempts, last_error, next_attempt_at)
+          VALUES (:channel, :digest, :target, :payload, :attempts, :error,
+            UTC_TIMESTAMP() + INTERVAL :backoff SECOND)"
+    };
+    pool.get_conn()?.exec_drop(
+        statement,
+        params! {
+            "channel" => channel,
+            "digest" => digest,
+            "target" => &undelivered.target,
+            "payload" => serde_json::to_string(&undelivered.shareables).unwrap_or_default(),
+            "attempts" => attempts,
+            "error" => &undelivered.error,
+            "backoff" => backoff(attempts).as_secs(),
+        },
+    )
+}
+
+// What a delivery did not get to, retried by the worker.
+pub fn enqueue(
+    pool: &Pool,
+    channel: &str,
+    undelivered: &Undelivered,
+    digest: bool,
+) -> mysql::Result<()> {
+    insert(pool, channel, undelivered, digest, 1)
+}
+
+fn due(pool: &Pool) -> mysql::Result<Vec<Entry>> {
+    pool.get_conn()?.exec_map(
+        r"SELECT id, channel, digest, target, payload, attempts FROM notification_outbox
+          WHERE failed_at IS NULL AND next_attempt_at <= UTC_TIMESTAMP()
+          ORDER BY id LIMIT 100",
+        (),
+        |(id, channel, digest, target, payload, attempts): (
+            u64,
+            String,
+            bool,
+            Option<String>,
+            String,
+            u32,
+        )| Entry {
+            id,
+            channel,
+            digest,
+            target,
+            shareables: serde_json::from_str(&payload).unwrap_or_default(),
+            attempts,
+        },
+    )
+}
+
+fn delivered(pool: &Pool, id: u64) -> mysql::Result<()> {
+    pool.get_conn()?.exec_drop(
+        "DELETE FROM notification_outbox WHERE id = :id",
+        params! { "id" => id },
+    )
+}
+
+// Keeps only what is still undelivered, items that went out on this attempt
+// are not sent again.
+fn failed_again(pool: &Pool, entry: &Entry, undelivered: &Undelivered) -> mysql::Result<()> {
+    let attempts = entry.attempts + 1;
+    let statement = if attempts >= MAX_ATTEMPTS {
+        r"UPDATE notification_outbox
+          SET attempts = :attempts, last_error = :error, target = :target, payload = :payload,
+            failed_at = UTC_TIMESTAMP()
+          WHERE id = :id"
+    } else {
+        r"UPDATE notification_outbox
+          SET attempts = :attempts, last_error = :error, target = :target, payload = :payload,
+            next_attempt_at = UTC_TIMESTAMP() + INTERVAL :backoff SECOND
+          WHERE id = :id"
+    };
+    pool.get_conn()?.exec_drop(
+        statement,
+        params! {
+            "id" => entry.id,
+            "attempts" => attempts,
+            "error" => &undelivered.error,
+            "target" => &undelivered.target,
+            "payload" => serde_json::to_string(&undelivered.shareables).unwrap_or_default(),
+            "backoff" => backoff(attempts).as_secs(),
+        },
+    )
+}
+
+pub fn counts(pool: &Pool) -> mysql::Result<Counts> {
+    let row: Option<(Option<u64>, Option<u64>)> = pool.get_conn()?.query_first(
+        r"SELECT SUM(failed_at IS NULL), SUM(failed_at IS NOT NULL) FROM notification_outbox",
+    )?;
+    let (pending, failed) = row.unwrap_or_default();
+    Ok(Counts {
+        pending: pending.unwrap_or_default(),
+        failed: failed.unwrap_or_default(),
+    })
+}
+
+async fn retry(pool: &Pool) -> mysql::Result<()> {
+    for entry in due(pool)? {
+        let notifier = match notifier::channel(&entry.channel) {
+            Some(notifier) => notifier,
+            None => {
+                let undelivered = Undelivered {
+                    target: entry.target.clone(),
+                    shareables: entry.shareables.clone(),
+                    error: String::from("channel is not configured anymore"),
+                };
+                failed_again(pool, &entry, &undelivered)?;
+                continue;
+            }
+        };
+        let target = entry.target.as_deref();
+        let result = if entry.digest {
+            notifier.notify_digest(&entry.shareables, target).await
+        } else {
+            notifier.notify(&entry.shareables, target).await
+        };
+        match result {
+            Ok(()) => {
+                info!(
+                    "Sent {} new items to {} on attempt {}",
+                    entry.shareables.len(),
+                    entry.channel,
+                    entry.attempts + 1
+                );
+                delivered(pool, entry.id)?;
+            }
+            Err(undelivered) => {
+                let mut undelivered = undelivered.iter();
+                // Retries without a target, queued before targets were kept,
+                // can still fail on several endpoints.
+                if let Some(first) = undelivered.next() {
+                    warn!(
+                        "Attempt {} to send {} new items to {} failed: {}",
+                        entry.attempts + 1,
+                        first.shareables.len(),
+                        entry.channel,
+                        first.error
+                    );
+                    failed_again(pool, &entry, first)?;
+                }
+                for rest in undelivered {
+                    insert(pool, &entry.channel, rest, entry.digest, entry.attempts + 1)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Retries failed deliveries every 30 seconds, once they are due.
+pub fn spawn_retrier(pool: Arc<Pool>) -> JoinHandle<()> {
+    task::spawn(async move {
+        let mut interval = time::interval(Duration::from_secs(30));
+        let mut last = Counts::default();
+        while shutdown::tick(&mut interval).await {
+            if maintenance::is_active() {
+                continue;
+            }
+            if let Err(e) = retry(&pool).await {
+                error!("Could not retry notifications: {}", e);
+            }
+            match counts(&pool) {
+                Ok(counts) if counts.pending != last.pending || counts.failed != last.failed => {
+                    info!(
+                        "Notification outbox: {} pending, {} failed",
+                        counts.pending, counts.failed
+                    );
+                    last = counts;
+                }
+                Ok(_) => {}
+                Err(e) => error!("Could not count notifications: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_exponentially() {
+        assert_eq!(backoff(1), Duration::from_secs(60));
+        assert_eq!(backoff(2), Duration::from_secs(120));
+        assert_eq!(backoff(4), Duration::from_secs(480));
+        assert_eq!(backoff(7), MAX_BACKOFF);
+        assert_eq!(backoff(20), MAX_BACKOFF);
+    }
+}
@@ -0,0 +1,118 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::fetcher::base::Shareable;
+use crate::http;
+use crate::notifier::template::ItemTemplate;
+use crate::notifier::{Delivery, Notifier, Undelivered};
+
+// Slack takes at most 50 blocks per message, each item uses two.
+const ITEMS_PER_MESSAGE: usize = 20;
+
+pub struct SlackNotifier {
+    pub webhook_url: String,
+    pub template: Option<ItemTemplate>,
+}
+
+// Titles go into mrkdwn, where these three are control characters.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn blocks(shareables: &[Shareable], template: Option<&ItemTemplate>) -> Vec<Value> {
+    shareables
+        .iter()
+        .flat_map(|shareable| {
+            let text = match template {
+                Some(template) => template.render(shareable, escape),
+                None => format!(
+                    "*<{}|{}>*",
+                    shareable.url,
+                    escape(&shareable.display_title())
+                ),
+            };
+            [
+                json!({
+                    "type": "section",
+                    "text": {
+                        "type": "mrkdwn",
+                        "text": text,
+                    },
+                }),
+                json!({
+                    "type": "context",
+                    "elements": [{
+                        "type": "mrkdwn",
+                        "text": format!("{} · {}", escape(&shareable.source), shareable.date),
+                    }],
+                }),
+            ]
+        })
+        .collect()
+}
+
+impl SlackNotifier {
+    async fn post(&self, message: &Value) -> Result<(), String> {
+        http::client()
+            .post(&self.webhook_url)
+            .timeout(Duration::from_secs(10))
+            .json(message)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map(|_| ())
+            .map_err(|e| format!("{}", e.without_url()))
+    }
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &'static str {
+        "slack"
+    }
+
+    async fn notify(&self, shareables: &[Shareable], _target: Option<&str>) -> Delivery {
+        for (i, chunk) in shareables.chunks(ITEMS_PER_MESSAGE).enumerate() {
+            self.post(&json!({
+                // Shown in push notifications, which don't render blocks.
+                "text": format!("{} new mentions", chunk.len()),
+                "blocks": blocks(chunk, self.template.as_ref()),
+            }))
+            .await
+            // The chunks before went out.
+            .map_err(|e| Undelivered::all(&shareables[i * ITEMS_PER_MESSAGE..], e))?;
+        }
+        Ok(())
+    }
+
+    // One message with the newest items, the rest is only counted.
+    async fn notify_digest(&self, shareables: &[Shareable], _target: Option<&str>) -> Delivery {
+        let shown = &shareables[..shareables.len().min(ITEMS_PER_MESSAGE - 1)];
+        let mut blocks = vec![json!({
+            "type": "header",
+            "text": {
+                "type": "plain_text",
+                "text": format!("Digest: {} new mentions", shareables.len()),
+            },
+        })];
+        blocks.extend(self::blocks(shown, self.template.as_ref()));
+        if shareables.len() > shown.len() {
+            blocks.push(json!({
+                "type": "context",
+                "elements": [{
+                    "type": "mrkdwn",
+                    "text": format!("and {} more", shareables.len() - shown.len()),
+                }],
+            }));
+        }
+        self.post(&json!({
+            "text": format!("Digest: {} new mentions", shareables.len()),
+            "blocks": blocks,
+        }))
+        .await
+        .map_err(|e| Undelivered::all(shareables, e))
+    }
+}
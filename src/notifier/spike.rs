@@ -0,0 +1,242 @@
+use async_trait::async_trait;
+use serde_json::json;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::fetcher::base::Shareable;
+use crate::http;
+use crate::notifier::throttle::RateLimit;
+use crate::notifier::{Delivery, Notifier, Undelivered};
+use crate::Config;
+
+enum Service {
+    PagerDuty { routing_key: String },
+    Opsgenie { api_key: String },
+}
+
+impl Service {
+    fn name(&self) -> &'static str {
+        match self {
+            Service::PagerDuty { .. } => "pagerduty",
+            Service::Opsgenie { .. } => "opsgenie",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    // When items arrived and how many, within the last window.
+    seen: VecDeque<(Instant, usize)>,
+    alerted_at: Option<Instant>,
+}
+
+// Pages the on-call when as many new items as ALERT_SPIKE_THRESHOLD allows
+// arrive within its window, e.g. after a breaking release. One alert per window, the
+// services group repeated ones by keyword anyway.
+pub struct SpikeAlert {
+    services: Vec<Service>,
+    threshold: RateLimit,
+    keyword: String,
+    state: Mutex<State>,
+}
+
+impl SpikeAlert {
+    // None without a threshold or a service to alert.
+    pub fn from_config(config: &Config) -> Result<Option<SpikeAlert>, String> {
+        let threshold = match config.alert_spike_threshold.as_deref() {
+            Some(threshold) => {
+                RateLimit::parse(threshold).map_err(|e| format!("ALERT_SPIKE_THRESHOLD: {}", e))?
+            }
+            None => return Ok(None),
+        };
+        let mut services = vec![];
+        if let Some(routing_key) = config.pagerduty_routing_key.clone() {
+            services.push(Service::PagerDuty { routing_key });
+        }
+        if let Some(api_key) = config.opsgenie_api_key.clone() {
+            services.push(Service::Opsgenie { api_key });
+        }
+        if services.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(SpikeAlert {
+            services,
+            threshold,
+            keyword: config.keyword.clone(),
+            state: Mutex::default(),
+        }))
+    }
+
+    // The number of items in the window if this batch makes it a spike that
+    // was not alerted yet.
+    fn record(&self, count: usize, now: Instant) -> Option<usize> {
+        let mut state = self.state.lock().unwrap();
+        state.seen.push_back((now, count));
+        let in_window = self.in_window(&mut state, now);
+        if in_window < self.threshold.max {
+            return None;
+        }
+        if matches!(state.alerted_at, Some(at) if now.duration_since(at) < self.threshold.per) {
+            return None;
+        }
+        state.alerted_at = Some(now);
+        Some(in_window)
+    }
+
+    fn in_window(&self, state: &mut State, now: Instant) -> usize {
+        while let Some((at, _)) = state.seen.front() {
+            if now.duration_since(*at) < self.threshold.per {
+                break;
+            }
+            state.seen.pop_front();
+        }
+        state.seen.iter().map(|(_, count)| count).sum()
+    }
+
+    async fn alert(&self, service: &Service, summary: &str, sample: &str) -> Result<(), String> {
+        // Repeated alerts for the same keyword go into one incident.
+        let dedup_key = format!("keyword-notifier-spike-{}", self.keyword);
+        let request = match service {
+            Service::PagerDuty { routing_key } => http::client()
+                .post("https://events.pagerduty.com/v2/enqueue")
+                .json(&json!({
+                    "routing_key": routing_key,
+                    "event_action": "trigger",
+                    "dedup_key": dedup_key,
+                    "payload": {
+                        "summary": summary,
+                        "source": "keyword-notifier",
+                        "severity": "warning",
+                        "custom_details": { "latest": sample },
+                    },
+                })),
+            Service::Opsgenie { api_key } => http::client()
+                .post("https://api.opsgenie.com/v2/alerts")
+                .header("Authorization", format!("GenieKey {}", api_key))
+                .json(&json!({
+                    "message": summary,
+                    "alias": dedup_key,
+                    "description": sample,
+                    "priority": "P3",
+                })),
+        };
+        request
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map(|_| ())
+            .map_err(|e| format!("{}", e.without_url()))
+    }
+}
+
+fn window(per: Duration) -> String {
+    match per.as_secs() {
+        60 => String::from("minute"),
+        3600 => String::from("hour"),
+        86400 => String::from("day"),
+        secs => format!("{}s", secs),
+    }
+}
+
+#[async_trait]
+impl Notifier for SpikeAlert {
+    fn name(&self) -> &'static str {
+        "spike-alert"
+    }
+
+    // Retries only resend the alert to the service that failed, the items
+    // were counted the first time.
+    async fn notify(&self, shareables: &[Shareable], target: Option<&str>) -> Delivery {
+        let in_window = match target {
+            None => match self.record(shareables.len(), Instant::now()) {
+                Some(in_window) => in_window,
+                None => return Ok(()),
+            },
+            Some(_) => {
+                let mut state = self.state.lock().unwrap();
+                // After a restart the window is empty.
+                self.in_window(&mut state, Instant::now())
+                    .max(shareables.len())
+            }
+        };
+        let summary = format!(
+            "{} new mentions of {} in the last {}",
+            in_window,
+            self.keyword,
+            window(self.threshold.per)
+        );
+        warn!("Mention spike: {}", summary);
+        let sample = shareables
+            .iter()
+            .take(5)
+            .map(|shareable| format!("[{}] {}", shareable.source, shareable.display_title()))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut undelivered = vec![];
+        for service in self
+            .services
+            .iter()
+            .filter(|service| target.is_none() || target == Some(service.name()))
+        {
+            if let Err(e) = self.alert(service, &summary, &sample).await {
+                undelivered.push(Undelivered {
+                    target: Some(String::from(service.name())),
+                    shareables: shareables.to_vec(),
+                    error: e,
+                });
+            }
+        }
+        if undelivered.is_empty() {
+            Ok(())
+        } else {
+            Err(undelivered)
+        }
+    }
+
+    // Digests are no spikes.
+    async fn notify_digest(&self, _shareables: &[Shareable], _target: Option<&str>) -> Delivery {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spike_alert(threshold: &str) -> SpikeAlert {
+        SpikeAlert {
+            services: vec![],
+            threshold: RateLimit::parse(threshold).unwrap(),
+            keyword: String::from("cdktf"),
+            state: Mutex::default(),
+        }
+    }
+
+    #[test]
+    fn alerts_once_per_window() {
+        let alert = spike_alert("10/h");
+        let start = Instant::now();
+        let minutes = |minutes: u64| start + Duration::from_secs(minutes * 60);
+        assert_eq!(alert.record(4, start), None);
+        assert_eq!(alert.record(5, minutes(10)), None);
+        assert_eq!(alert.record(1, minutes(20)), Some(10));
+        assert_eq!(alert.record(5, minutes(30)), None);
+        // The first batches dropped out of the window.
+        assert_eq!(alert.record(1, minutes(75)), None);
+        assert_eq!(alert.record(9, minutes(85)), Some(15));
+    }
+
+    #[tokio::test]
+    async fn retries_do_not_count_again() {
+        let alert = spike_alert("10/h");
+        let shareables = crate::notifier::tests::shareables(4);
+        assert!(alert.notify(&shareables, Some("pagerduty")).await.is_ok());
+        assert!(alert.state.lock().unwrap().seen.is_empty());
+        assert!(alert.notify(&shareables, None).await.is_ok());
+        assert_eq!(alert.state.lock().unwrap().seen.len(), 1);
+    }
+}
@@ -0,0 +1,126 @@
+use minijinja::{context, Environment, Value};
+use std::path::Path;
+use tracing::warn;
+
+use crate::fetcher::base::Shareable;
+use crate::templating;
+use crate::Config;
+
+const NAME: &str = "item.txt";
+
+// The fields of an item, `escape`d.
+fn fields(shareable: &Shareable, escape: fn(&str) -> String) -> Value {
+    context! {
+        id => escape(&shareable.id),
+        title => escape(&shareable.display_title()),
+        url => escape(&shareable.url),
+        link => escape(shareable.link()),
+        source => escape(&shareable.source),
+        date => escape(&shareable.date),
+    }
+}
+
+// The text a channel sends for one item, a minijinja template with `title`,
+// `url`, `link` (the canonical URL), `source`, `date` and `id` of the item.
+#[derive(Debug, Clone)]
+pub struct ItemTemplate {
+    env: Environment<'static>,
+}
+
+impl ItemTemplate {
+    // Compiled once and rendered with an item, so syntax errors and unknown
+    // fields fail here instead of on the first notification.
+    pub fn parse(template: &str) -> Result<ItemTemplate, String> {
+        let mut env = templating::environment();
+        env.add_template_owned(NAME, template.to_string())
+            .map_err(|e| e.to_string())?;
+        let sample = Shareable {
+            id: String::from("twitter-1"),
+            title: String::from("cdktf is great"),
+            date: String::from("2022-06-01T12:00:00Z"),
+            url: String::from("https://twitter.com/twitter/status/1"),
+            source: String::from("twitter"),
+            canonical_url: None,
+        };
+        templating::render(&env, NAME, fields(&sample, str::to_string))?;
+        Ok(ItemTemplate { env })
+    }
+
+    // `escape` is applied to the values only, e.g. for Slack's mrkdwn.
+    pub fn render(&self, shareable: &Shareable, escape: fn(&str) -> String) -> String {
+        templating::render(&self.env, NAME, fields(shareable, escape)).unwrap_or_else(|e| {
+            // Every field is always there, only filters can still fail.
+            warn!("Notification template failed for {}: {}", shareable.id, e);
+            format!(
+                "{} {}",
+                escape(&shareable.display_title()),
+                escape(shareable.link())
+            )
+        })
+    }
+}
+
+// `<NOTIFY_TEMPLATE_DIR>/<channel>.txt`, channels without one keep their
+// built-in format.
+pub fn for_channel(config: &Config, channel: &str) -> Result<Option<ItemTemplate>, String> {
+    let dir = match &config.notify_template_dir {
+        Some(dir) => dir,
+        None => return Ok(None),
+    };
+    let path = Path::new(dir).join(format!("{}.txt", channel));
+    if !path.exists() {
+        return Ok(None);
+    }
+    let template = std::fs::read_to_string(&path)
+        .map_err(|e| format!("NOTIFY_TEMPLATE_DIR: {}: {}", path.display(), e))?;
+    ItemTemplate::parse(template.trim_end_matches('\n'))
+        .map(Some)
+        .map_err(|e| format!("NOTIFY_TEMPLATE_DIR: {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shareable() -> Shareable {
+        Shareable {
+            id: String::from("stackoverflow-1"),
+            title: String::from(":question: How do I use cdktf?"),
+            date: String::from("2022-06-01T12:00:00Z"),
+            url: String::from("https://stackoverflow.com/questions/1/cdktf?utm_source=feed"),
+            source: String::from("stackoverflow"),
+            canonical_url: Some(String::from("https://stackoverflow.com/questions/1/cdktf")),
+        }
+    }
+
+    #[test]
+    fn renders_fields() {
+        let template = ItemTemplate::parse(
+            "{{title}} on {{ source }}{% if url != link %} (via {{ url }}){% endif %}\n{{ link }}",
+        )
+        .unwrap();
+        assert_eq!(
+            template.render(&shareable(), str::to_string),
+            "❓ How do I use cdktf? on stackoverflow (via https://stackoverflow.com/questions/1/cdktf?utm_source=feed)\nhttps://stackoverflow.com/questions/1/cdktf"
+        );
+        assert_eq!(
+            ItemTemplate::parse("<{{ url }}>")
+                .unwrap()
+                .render(&shareable(), |value| value.replace('?', "%3F")),
+            "<https://stackoverflow.com/questions/1/cdktf%3Futm_source=feed>"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        assert!(ItemTemplate::parse("{{ author }}").is_err());
+        assert!(ItemTemplate::parse("{{ title").is_err());
+        assert!(ItemTemplate::parse("{% for tag in tags %}{{ tag }}{% endfor %}").is_err());
+        assert_eq!(
+            ItemTemplate::parse("no fields")
+                .unwrap()
+                .render(&shareable(), str::to_string),
+            "no fields"
+        );
+    }
+}
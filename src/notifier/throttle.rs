@@ -0,0 +1,251 @@
+use chrono::NaiveTime;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::fetcher::base::Shareable;
+use crate::Config;
+
+// UTC times a channel stays silent, may wrap around midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuietHours {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl QuietHours {
+    // `22:00-07:00`
+    pub fn parse(hours: &str) -> Result<QuietHours, String> {
+        let (start, end) = hours
+            .split_once('-')
+            .ok_or_else(|| format!("expected HH:MM-HH:MM, got {}", hours))?;
+        let time = |at: &str| {
+            NaiveTime::parse_from_str(at.trim(), "%H:%M").map_err(|e| format!("{}: {}", at, e))
+        };
+        Ok(QuietHours {
+            start: time(start)?,
+            end: time(end)?,
+        })
+    }
+
+    fn contains(&self, at: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= at && at < self.end
+        } else {
+            at >= self.start || at < self.end
+        }
+    }
+}
+
+// At most `max` notifications per `per`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub max: usize,
+    pub per: Duration,
+}
+
+impl RateLimit {
+    // `5/h`, `1/m` or `100/d`
+    pub fn parse(limit: &str) -> Result<RateLimit, String> {
+        let (max, per) = limit
+            .split_once('/')
+            .ok_or_else(|| format!("expected count/unit, e.g. 5/h, got {}", limit))?;
+        let max = max
+            .trim()
+            .parse()
+            .ok()
+            .filter(|max| *max > 0)
+            .ok_or_else(|| format!("{}: count has to be a positive number", limit))?;
+        let per = match per.trim() {
+            "m" => Duration::from_secs(60),
+            "h" => Duration::from_secs(60 * 60),
+            "d" => Duration::from_secs(24 * 60 * 60),
+            other => return Err(format!("{}: unknown unit {}, use m, h or d", limit, other)),
+        };
+        Ok(RateLimit { max, per })
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    held: Vec<Shareable>,
+    sent: VecDeque<Instant>,
+}
+
+// Holds items back while a channel is in its quiet hours or over its rate
+// limit, they go out together with the next batch that is let through.
+#[derive(Debug, Default)]
+pub struct Throttle {
+    quiet_hours: Option<QuietHours>,
+    rate_limit: Option<RateLimit>,
+    state: Mutex<State>,
+}
+
+// `name=value` entries apply to that channel, a bare value to all of them.
+fn setting<'a>(entries: &'a [String], channel: &str) -> Option<&'a str> {
+    let mut fallback = None;
+    for entry in entries.iter() {
+        match entry.split_once('=') {
+            Some((name, value)) if name.trim() == channel => return Some(value.trim()),
+            Some(_) => {}
+            None => fallback = Some(entry.trim()),
+        }
+    }
+    fallback
+}
+
+impl Throttle {
+    pub fn for_channel(channel: &str, config: &Config) -> Result<Throttle, String> {
+        Ok(Throttle {
+            quiet_hours: setting(&config.notify_quiet_hours, channel)
+                .map(QuietHours::parse)
+                .transpose()
+                .map_err(|e| format!("NOTIFY_QUIET_HOURS: {}", e))?,
+            rate_limit: setting(&config.notify_rate_limits, channel)
+                .map(RateLimit::parse)
+                .transpose()
+                .map_err(|e| format!("NOTIFY_RATE_LIMITS: {}", e))?,
+            state: Mutex::default(),
+        })
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.quiet_hours.is_some() || self.rate_limit.is_some()
+    }
+
+    pub fn held(&self) -> usize {
+        self.state.lock().unwrap().held.len()
+    }
+
+    // The batch to send now, held items included, or None if the channel has
+    // to wait. `at` is the UTC time of day for the quiet hours.
+    pub fn admit(
+        &self,
+        shareables: &[Shareable],
+        at: NaiveTime,
+        now: Instant,
+    ) -> Option<Vec<Shareable>> {
+        let mut state = self.state.lock().unwrap();
+        state.held.extend_from_slice(shareables);
+        if state.held.is_empty() {
+            return None;
+        }
+        if matches!(self.quiet_hours, Some(quiet_hours) if quiet_hours.contains(at)) {
+            return None;
+        }
+        if let Some(rate_limit) = self.rate_limit {
+            while let Some(sent) = state.sent.front() {
+                if now.duration_since(*sent) < rate_limit.per {
+                    break;
+                }
+                state.sent.pop_front();
+            }
+            if state.sent.len() >= rate_limit.max {
+                return None;
+            }
+            state.sent.push_back(now);
+        }
+        Some(std::mem::take(&mut state.held))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shareable(id: &str) -> Shareable {
+        Shareable {
+            id: String::from(id),
+            title: String::from("cdktf"),
+            date: String::from("2022-06-01T12:00:00Z"),
+            url: format!("https://example.com/{}", id),
+            source: String::from("twitter"),
+            canonical_url: None,
+        }
+    }
+
+    fn at(time: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(time, "%H:%M").unwrap()
+    }
+
+    fn ids(batch: Option<Vec<Shareable>>) -> Option<Vec<String>> {
+        batch.map(|batch| batch.into_iter().map(|shareable| shareable.id).collect())
+    }
+
+    #[test]
+    fn parses_settings() {
+        assert!(QuietHours::parse("22:00-07:00").is_ok());
+        assert!(QuietHours::parse("22:00").is_err());
+        assert_eq!(
+            RateLimit::parse("5/h"),
+            Ok(RateLimit {
+                max: 5,
+                per: Duration::from_secs(3600),
+            })
+        );
+        assert!(RateLimit::parse("0/h").is_err());
+        assert!(RateLimit::parse("5/w").is_err());
+
+        let entries = vec![
+            String::from("22:00-07:00"),
+            String::from("slack=23:00-06:00"),
+        ];
+        assert_eq!(setting(&entries, "slack"), Some("23:00-06:00"));
+        assert_eq!(setting(&entries, "ntfy"), Some("22:00-07:00"));
+        assert_eq!(setting(&entries[1..], "ntfy"), None);
+    }
+
+    #[test]
+    fn quiet_hours_wrap_around_midnight() {
+        let overnight = QuietHours::parse("22:00-07:00").unwrap();
+        assert!(overnight.contains(at("23:30")));
+        assert!(overnight.contains(at("03:00")));
+        assert!(!overnight.contains(at("07:00")));
+        assert!(!overnight.contains(at("12:00")));
+
+        let lunch = QuietHours::parse("12:00-13:00").unwrap();
+        assert!(lunch.contains(at("12:30")));
+        assert!(!lunch.contains(at("13:30")));
+    }
+
+    #[test]
+    fn holds_items_during_quiet_hours() {
+        let throttle = Throttle {
+            quiet_hours: Some(QuietHours::parse("22:00-07:00").unwrap()),
+            ..Throttle::default()
+        };
+        let now = Instant::now();
+        assert_eq!(
+            ids(throttle.admit(&[shareable("1")], at("03:00"), now)),
+            None
+        );
+        assert_eq!(
+            ids(throttle.admit(&[shareable("2")], at("04:00"), now)),
+            None
+        );
+        assert_eq!(throttle.held(), 2);
+        assert_eq!(
+            ids(throttle.admit(&[], at("07:00"), now)),
+            Some(vec![String::from("1"), String::from("2")])
+        );
+        assert_eq!(ids(throttle.admit(&[], at("07:01"), now)), None);
+    }
+
+    #[test]
+    fn holds_items_over_the_rate_limit() {
+        let throttle = Throttle {
+            rate_limit: Some(RateLimit::parse("2/h").unwrap()),
+            ..Throttle::default()
+        };
+        let start = Instant::now();
+        let noon = at("12:00");
+        assert!(throttle.admit(&[shareable("1")], noon, start).is_some());
+        assert!(throttle.admit(&[shareable("2")], noon, start).is_some());
+        assert_eq!(ids(throttle.admit(&[shareable("3")], noon, start)), None);
+        assert_eq!(ids(throttle.admit(&[shareable("4")], noon, start)), None);
+        assert_eq!(
+            ids(throttle.admit(&[], noon, start + Duration::from_secs(3600))),
+            Some(vec![String::from("3"), String::from("4")])
+        );
+    }
+}
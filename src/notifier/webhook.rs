@@ -0,0 +1,142 @@
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use serde_json::{json, Value};
+
+use crate::events::EventType;
+use crate::fetcher::base::Shareable;
+use crate::http;
+use crate::notifier::{Delivery, Notifier, Undelivered};
+use crate::webhook;
+use crate::Config;
+
+// New items for NOTIFY_WEBHOOK_URLS, sent like the lifecycle events of
+// WEBHOOK_URLS but with quiet hours, digests and the outbox of the other
+// channels. Failed endpoints are retried from the outbox.
+pub struct WebhookNotifier {
+    urls: Vec<String>,
+    headers: HeaderMap,
+    keyword: String,
+}
+
+impl WebhookNotifier {
+    pub fn from_config(config: &Config) -> Result<WebhookNotifier, String> {
+        Ok(WebhookNotifier {
+            urls: config.notify_webhook_urls.clone(),
+            headers: webhook::headers(config)?,
+            keyword: config.keyword.clone(),
+        })
+    }
+
+    // All endpoints, or the one a retry is for. Endpoints removed from the
+    // config since are skipped.
+    fn targets<'a>(&'a self, target: Option<&'a str>) -> impl Iterator<Item = &'a String> {
+        self.urls
+            .iter()
+            .filter(move |url| target.is_none() || target == Some(url.as_str()))
+    }
+
+    async fn post(&self, url: &str, event: &str, body: &Value) -> Result<(), String> {
+        webhook::post(&http::client(), &self.headers, url, event, body).await
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    // Every endpoint gets every item, one failing endpoint doesn't stop the
+    // others. Each is retried with only the items it missed.
+    async fn notify(&self, shareables: &[Shareable], target: Option<&str>) -> Delivery {
+        let mut undelivered = vec![];
+        for url in self.targets(target) {
+            let mut missed = vec![];
+            let mut errors = vec![];
+            for shareable in shareables.iter() {
+                let body = json!({
+                    "keyword": self.keyword,
+                    "shareable": shareable,
+                });
+                if let Err(e) = self.post(url, EventType::Created.as_str(), &body).await {
+                    missed.push(shareable.clone());
+                    errors.push(format!("{} ({}): {}", http::redact(url), shareable.id, e));
+                }
+            }
+            if !missed.is_empty() {
+                undelivered.push(Undelivered {
+                    target: Some(url.clone()),
+                    shareables: missed,
+                    error: errors.join("; "),
+                });
+            }
+        }
+
+        if undelivered.is_empty() {
+            Ok(())
+        } else {
+            Err(undelivered)
+        }
+    }
+
+    // One request per endpoint, with all items under `shareables`.
+    async fn notify_digest(&self, shareables: &[Shareable], target: Option<&str>) -> Delivery {
+        let body = json!({
+            "keyword": self.keyword,
+            "digest": true,
+            "shareables": shareables,
+        });
+        let mut undelivered = vec![];
+        for url in self.targets(target) {
+            if let Err(e) = self.post(url, "digest", &body).await {
+                undelivered.push(Undelivered {
+                    target: Some(url.clone()),
+                    shareables: shareables.to_vec(),
+                    error: format!("{}: {}", http::redact(url), e),
+                });
+            }
+        }
+
+        if undelivered.is_empty() {
+            Ok(())
+        } else {
+            Err(undelivered)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifier::tests::{ids, serve, shareables};
+
+    #[tokio::test]
+    async fn keeps_only_failed_endpoints() {
+        let healthy = serve(vec![200]).await;
+        // With a token in the path, like many webhook URLs.
+        let broken = format!("{}hooks/s3cr3t", serve(vec![200, 400]).await);
+        let webhook = WebhookNotifier {
+            urls: vec![healthy.clone(), broken.clone()],
+            headers: HeaderMap::new(),
+            keyword: String::from("cdktf"),
+        };
+
+        let undelivered = webhook.notify(&shareables(2), None).await.unwrap_err();
+        assert_eq!(undelivered.len(), 1);
+        assert_eq!(undelivered[0].target.as_deref(), Some(broken.as_str()));
+        assert_eq!(ids(&undelivered[0]), vec!["twitter-2"]);
+        assert!(!undelivered[0].error.contains("s3cr3t"));
+
+        // Retries go to that endpoint only.
+        let retried = webhook
+            .notify(&undelivered[0].shareables, Some(&broken))
+            .await
+            .unwrap_err();
+        assert_eq!(retried.len(), 1);
+        assert_eq!(retried[0].target.as_deref(), Some(broken.as_str()));
+        assert!(webhook
+            .notify(&undelivered[0].shareables, Some(&healthy))
+            .await
+            .is_ok());
+    }
+}
@@ -0,0 +1,498 @@
+use async_trait::async_trait;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use mysql::prelude::*;
+use mysql::*;
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use reqwest::{StatusCode, Url};
+use serde::Deserialize;
+use serde_json::json;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+use crate::fetcher::base::Shareable;
+use crate::http;
+use crate::notifier::template::{self, ItemTemplate};
+use crate::notifier::{self, Delivery, Notifier, Undelivered};
+use crate::text;
+use crate::Config;
+
+// Bigger batches get one summary instead of a burst of notifications.
+const MAX_PUSHES_PER_BATCH: usize = 5;
+// How long push services hold a message for a browser that is offline, in
+// seconds.
+const TTL: &str = "86400";
+// Push services reject payloads over 4 KB, long titles are cut well before.
+const MAX_BODY_LENGTH: usize = 200;
+
+#[derive(Debug, Deserialize)]
+pub struct SubscriptionKeys {
+    pub p256dh: String,
+    pub auth: String,
+}
+
+// As the browser hands it out with `PushSubscription.toJSON()`.
+#[derive(Debug, Deserialize)]
+pub struct Subscription {
+    pub endpoint: String,
+    pub keys: SubscriptionKeys,
+}
+
+// Loopback, private and link-local addresses, what a push service never has.
+fn is_internal(ip: IpAddr) -> bool {
+    fn internal_v4(ip: Ipv4Addr) -> bool {
+        let [a, b, ..] = ip.octets();
+        ip.is_private()
+            || ip.is_loopback()
+            || ip.is_link_local()
+            || ip.is_unspecified()
+            || ip.is_broadcast()
+            || ip.is_documentation()
+            || a == 0
+            // Carrier-grade NAT, 100.64.0.0/10.
+            || (a == 100 && (64..128).contains(&b))
+    }
+    fn internal_v6(ip: Ipv6Addr) -> bool {
+        let first = ip.segments()[0];
+        ip.is_loopback()
+            || ip.is_unspecified()
+            // Unique local, fc00::/7, and link-local, fe80::/10.
+            || (first & 0xfe00) == 0xfc00
+            || (first & 0xffc0) == 0xfe80
+            || ip.to_ipv4_mapped().is_some_and(internal_v4)
+    }
+    match ip {
+        IpAddr::V4(ip) => internal_v4(ip),
+        IpAddr::V6(ip) => internal_v6(ip),
+    }
+}
+
+// Anyone can subscribe, and we POST to the endpoint on every new item, so it
+// has to be a public https URL.
+fn check_endpoint(endpoint: &str) -> Result<Url, String> {
+    let url = Url::parse(endpoint).map_err(|e| format!("endpoint: {}", e))?;
+    if url.scheme() != "https" {
+        return Err(String::from("endpoint: has to be https"));
+    }
+    // IPv6 hosts come in brackets.
+    let host = url
+        .host_str()
+        .unwrap_or_default()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .trim_end_matches('.')
+        .to_lowercase();
+    let internal = match host.parse::<IpAddr>() {
+        Ok(ip) => is_internal(ip),
+        Err(_) => {
+            host == "localhost"
+                || !host.contains('.')
+                || [".localhost", ".local", ".internal"]
+                    .iter()
+                    .any(|suffix| host.ends_with(suffix))
+        }
+    };
+    if internal {
+        return Err(String::from("endpoint: has to be a public host"));
+    }
+    Ok(url)
+}
+
+// Broken keys would make every later push to this subscription fail.
+fn check_keys(keys: &SubscriptionKeys) -> Result<(), String> {
+    let p256dh = decode_key("p256dh", &keys.p256dh)?;
+    if p256dh.len() != 65 || p256::PublicKey::from_sec1_bytes(&p256dh).is_err() {
+        return Err(String::from("p256dh: not an uncompressed P-256 public key"));
+    }
+    if decode_key("auth", &keys.auth)?.len() != 16 {
+        return Err(String::from("auth: has to be 16 bytes"));
+    }
+    Ok(())
+}
+
+// What the browser sent, before it is stored. The host is resolved, so names
+// pointing into the internal network are rejected as well.
+pub async fn validate(subscription: &Subscription) -> Result<(), String> {
+    let url = check_endpoint(&subscription.endpoint)?;
+    check_keys(&subscription.keys)?;
+    let host = url.host_str().unwrap_or_default();
+    let addrs = tokio::net::lookup_host((host, url.port_or_known_default().unwrap_or(443)))
+        .await
+        .map_err(|e| format!("endpoint: {}", e))?;
+    for addr in addrs {
+        if is_internal(addr.ip()) {
+            return Err(String::from("endpoint: has to be a public host"));
+        }
+    }
+    Ok(())
+}
+
+pub fn subscribe(pool: &Pool, subscription: &Subscription) -> mysql::Result<()> {
+    let mut conn = pool.get_conn()?;
+    conn.exec_drop(
+        r"INSERT INTO push_subscriptions (endpoint, p256dh, auth)
+          VALUES (:endpoint, :p256dh, :auth)
+          ON DUPLICATE KEY UPDATE p256dh = VALUES(p256dh), auth = VALUES(auth)",
+        params! {
+            "endpoint" => &subscription.endpoint,
+            "p256dh" => &subscription.keys.p256dh,
+            "auth" => &subscription.keys.auth,
+        },
+    )
+}
+
+pub fn unsubscribe(pool: &Pool, endpoint: &str) -> mysql::Result<()> {
+    let mut conn = pool.get_conn()?;
+    conn.exec_drop(
+        "DELETE FROM push_subscriptions WHERE endpoint = :endpoint",
+        params! { "endpoint" => endpoint },
+    )
+}
+
+fn subscriptions(pool: &Pool) -> mysql::Result<Vec<Subscription>> {
+    let mut conn = pool.get_conn()?;
+    conn.query_map(
+        "SELECT endpoint, p256dh, auth FROM push_subscriptions",
+        |(endpoint, p256dh, auth)| Subscription {
+            endpoint,
+            keys: SubscriptionKeys { p256dh, auth },
+        },
+    )
+}
+
+// Keys are base64url, browsers leave out the padding, key generators
+// sometimes don't.
+fn decode_key(name: &str, key: &str) -> Result<Vec<u8>, String> {
+    URL_SAFE_NO_PAD
+        .decode(key.trim_end_matches('='))
+        .map_err(|e| format!("{}: {}", name, e))
+}
+
+pub struct WebPushNotifier {
+    pool: Arc<Pool>,
+    signing_key: SigningKey,
+    public_key: String,
+    subject: String,
+    template: Option<ItemTemplate>,
+    public_url: Option<String>,
+}
+
+impl WebPushNotifier {
+    // Both keys and the subject are checked to be set in Config::from_env.
+    pub fn from_config(config: &Config, pool: Arc<Pool>) -> Result<WebPushNotifier, String> {
+        let private_key = decode_key(
+            "WEBPUSH_VAPID_PRIVATE_KEY",
+            config
+                .webpush_vapid_private_key
+                .as_deref()
+                .unwrap_or_default(),
+        )?;
+        let signing_key = SigningKey::from_slice(&private_key)
+            .map_err(|e| format!("WEBPUSH_VAPID_PRIVATE_KEY: {}", e))?;
+
+        let public_key = URL_SAFE_NO_PAD.encode(
+            signing_key
+                .verifying_key()
+                .to_encoded_point(false)
+                .as_bytes(),
+        );
+        let configured = config
+            .webpush_vapid_public_key
+            .as_deref()
+            .unwrap_or_default()
+            .trim_end_matches('=');
+        if configured != public_key {
+            return Err(String::from(
+                "WEBPUSH_VAPID_PUBLIC_KEY does not belong to WEBPUSH_VAPID_PRIVATE_KEY",
+            ));
+        }
+
+        Ok(WebPushNotifier {
+            pool,
+            signing_key,
+            public_key,
+            subject: config.webpush_subject.clone().unwrap_or_default(),
+            template: template::for_channel(config, "webpush")?,
+            public_url: config.public_url.clone(),
+        })
+    }
+
+    // VAPID (RFC 8292): a short lived JWT for the origin of the push service,
+    // signed with our private key, next to the public key browsers subscribed
+    // with.
+    fn authorization(&self, endpoint: &Url) -> String {
+        let expires_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            + 12 * 60 * 60;
+        let header = URL_SAFE_NO_PAD.encode(r#"{"typ":"JWT","alg":"ES256"}"#);
+        let claims = URL_SAFE_NO_PAD.encode(
+            json!({
+                "aud": endpoint.origin().ascii_serialization(),
+                "exp": expires_at,
+                "sub": self.subject,
+            })
+            .to_string(),
+        );
+        let unsigned = format!("{}.{}", header, claims);
+        let signature: Signature = self.signing_key.sign(unsigned.as_bytes());
+        format!(
+            "vapid t={}.{}, k={}",
+            unsigned,
+            URL_SAFE_NO_PAD.encode(signature.to_bytes()),
+            self.public_key
+        )
+    }
+
+    // The payload is encrypted for the browser (RFC 8291), the push service
+    // only relays it. Endpoints carry a token, so they stay out of errors.
+    async fn push(&self, subscription: &Subscription, payload: &[u8]) -> Result<(), String> {
+        let endpoint = Url::parse(&subscription.endpoint).map_err(|e| format!("{}", e))?;
+        let body = ece::encrypt(
+            &decode_key("p256dh", &subscription.keys.p256dh)?,
+            &decode_key("auth", &subscription.keys.auth)?,
+            payload,
+        )
+        .map_err(|e| format!("{}", e))?;
+
+        let resp = http::client()
+            .post(endpoint.clone())
+            .timeout(Duration::from_secs(10))
+            .header("Authorization", self.authorization(&endpoint))
+            .header("TTL", TTL)
+            .header("Content-Encoding", "aes128gcm")
+            .header("Content-Type", "application/octet-stream")
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| format!("{}", e.without_url()))?;
+
+        match resp.status() {
+            // The browser unsubscribed or the subscription expired.
+            StatusCode::NOT_FOUND | StatusCode::GONE => {
+                info!(
+                    "Removing expired push subscription on {}",
+                    endpoint.host_str().unwrap_or_default()
+                );
+                unsubscribe(&self.pool, &subscription.endpoint).map_err(|e| format!("{}", e))
+            }
+            _ => resp
+                .error_for_status()
+                .map(|_| ())
+                .map_err(|e| format!("{}", e.without_url())),
+        }
+    }
+}
+
+// The first items and how many more there are, linking to the dashboard.
+fn summary(title: String, shareables: &[Shareable]) -> Vec<u8> {
+    let mut lines: Vec<String> = shareables
+        .iter()
+        .take(MAX_PUSHES_PER_BATCH)
+        .map(|shareable| format!("[{}] {}", shareable.source, shareable.display_title()))
+        .collect();
+    if shareables.len() > MAX_PUSHES_PER_BATCH {
+        lines.push(format!(
+            "and {} more",
+            shareables.len() - MAX_PUSHES_PER_BATCH
+        ));
+    }
+    let body = lines.join("\n");
+    json!({
+        "title": title,
+        "body": text::truncate(&body, MAX_BODY_LENGTH * 2).unwrap_or(body),
+        "url": "/",
+    })
+    .to_string()
+    .into_bytes()
+}
+
+// Links are opened by our service worker, relative ones are on the dashboard.
+fn messages(
+    shareables: &[Shareable],
+    template: Option<&ItemTemplate>,
+    public_url: Option<&str>,
+) -> Vec<Vec<u8>> {
+    if shareables.len() > MAX_PUSHES_PER_BATCH {
+        let title = format!("{} new mentions", shareables.len());
+        return vec![summary(title, shareables)];
+    }
+
+    shareables
+        .iter()
+        .map(|shareable| {
+            let body = match template {
+                Some(template) => template.render(shareable, str::to_string),
+                None => shareable.display_title(),
+            };
+            json!({
+                "title": format!("New on {}", shareable.source),
+                "body": text::truncate(&body, MAX_BODY_LENGTH).unwrap_or(body),
+                "url": notifier::item_page(public_url, shareable),
+                "icon": shareable.icon_path(),
+            })
+            .to_string()
+            .into_bytes()
+        })
+        .collect()
+}
+
+// The items of the messages from `failed` on. Big batches are one summary.
+fn missed(shareables: &[Shareable], messages: usize, failed: usize) -> &[Shareable] {
+    if messages == shareables.len() {
+        &shareables[failed..]
+    } else {
+        shareables
+    }
+}
+
+impl WebPushNotifier {
+    // One broken subscription doesn't keep the others from being notified,
+    // and only it is retried, with the items it missed.
+    async fn send(
+        &self,
+        shareables: &[Shareable],
+        target: Option<&str>,
+        messages: Vec<Vec<u8>>,
+    ) -> Delivery {
+        let subscriptions = subscriptions(&self.pool)
+            .map_err(|e| Undelivered::all(shareables, format!("{}", e)))?;
+
+        let mut undelivered = vec![];
+        // Subscriptions gone since a failed attempt are skipped.
+        for subscription in subscriptions
+            .iter()
+            .filter(|subscription| target.is_none() || target == Some(&subscription.endpoint))
+        {
+            for (i, message) in messages.iter().enumerate() {
+                if let Err(e) = self.push(subscription, message).await {
+                    undelivered.push(Undelivered {
+                        target: Some(subscription.endpoint.clone()),
+                        shareables: missed(shareables, messages.len(), i).to_vec(),
+                        error: e,
+                    });
+                    break;
+                }
+            }
+        }
+
+        if undelivered.is_empty() {
+            Ok(())
+        } else {
+            Err(undelivered)
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for WebPushNotifier {
+    fn name(&self) -> &'static str {
+        "webpush"
+    }
+
+    async fn notify(&self, shareables: &[Shareable], target: Option<&str>) -> Delivery {
+        let messages = messages(
+            shareables,
+            self.template.as_ref(),
+            self.public_url.as_deref(),
+        );
+        self.send(shareables, target, messages).await
+    }
+
+    // One push per subscription, however many items there are.
+    async fn notify_digest(&self, shareables: &[Shareable], target: Option<&str>) -> Delivery {
+        let title = format!("Digest: {} new mentions", shareables.len());
+        self.send(shareables, target, vec![summary(title, shareables)])
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A key pair and auth secret as browsers generate them, from RFC 8291.
+    const P256DH: &str =
+        "BCVxsr7N_eNgVRqvHtD0zTZsEc6-VV-JvLexhqUzORcxaOzi6-AYWXvTBHm4bjyPjs7Vd8pZGH6SRpkNtoIAiw4";
+    const AUTH: &str = "BTBZMqHH6r4Tts7J_aSIgg";
+
+    fn keys(p256dh: &str, auth: &str) -> SubscriptionKeys {
+        SubscriptionKeys {
+            p256dh: String::from(p256dh),
+            auth: String::from(auth),
+        }
+    }
+
+    #[test]
+    fn accepts_public_https_endpoints() {
+        assert!(check_endpoint("https://fcm.googleapis.com/fcm/send/abc").is_ok());
+        assert!(check_endpoint("https://updates.push.services.mozilla.com/wpush/v2/abc").is_ok());
+        assert!(check_keys(&keys(P256DH, AUTH)).is_ok());
+    }
+
+    #[test]
+    fn rejects_internal_endpoints() {
+        for endpoint in [
+            "http://fcm.googleapis.com/fcm/send/abc",
+            "https://localhost/push",
+            "https://metadata/computeMetadata/v1",
+            "https://db.internal/push",
+            "https://127.0.0.1/push",
+            "https://10.0.0.5/push",
+            "https://169.254.169.254/latest/meta-data",
+            "https://100.64.1.1/push",
+            "https://[::1]/push",
+            "https://[fd00::1]/push",
+            "https://[::ffff:192.168.1.1]/push",
+            "file:///etc/passwd",
+            "not a url",
+        ] {
+            assert!(check_endpoint(endpoint).is_err(), "{}", endpoint);
+        }
+    }
+
+    #[test]
+    fn rejects_broken_keys() {
+        assert!(check_keys(&keys("not base64!", AUTH)).is_err());
+        // Right length, but not a point on the curve.
+        assert!(check_keys(&keys(&URL_SAFE_NO_PAD.encode([4u8; 65]), AUTH)).is_err());
+        assert!(check_keys(&keys(&P256DH[..40], AUTH)).is_err());
+        assert!(check_keys(&keys(P256DH, "c2hvcnQ")).is_err());
+    }
+
+    #[test]
+    fn links_to_item_pages() {
+        let shareables = crate::notifier::tests::shareables(2);
+        let url = |public_url: Option<&str>| -> Vec<String> {
+            messages(&shareables, None, public_url)
+                .iter()
+                .map(|message| {
+                    let message: serde_json::Value = serde_json::from_slice(message).unwrap();
+                    message["url"].as_str().unwrap().to_string()
+                })
+                .collect()
+        };
+        assert_eq!(
+            url(Some("https://news.example.com")),
+            vec![
+                "https://news.example.com/item/twitter-1",
+                "https://news.example.com/item/twitter-2"
+            ]
+        );
+        assert_eq!(url(None), vec!["/item/twitter-1", "/item/twitter-2"]);
+    }
+
+    #[test]
+    fn summarizes_the_first_items() {
+        let shareables = crate::notifier::tests::shareables(8);
+        let message: serde_json::Value =
+            serde_json::from_slice(&summary(String::from("Digest"), &shareables)).unwrap();
+        let body = message["body"].as_str().unwrap();
+        assert_eq!(body.lines().count(), MAX_PUSHES_PER_BATCH + 1);
+        assert!(body.ends_with("and 3 more"), "{}", body);
+        assert_eq!(message["url"], "/");
+    }
+}
@@ -0,0 +1,64 @@
+use serde_json::json;
+use tracing::info;
+
+use crate::fetcher::base::Shareable;
+use crate::http;
+use crate::Config;
+
+async fn add_to_pocket(
+    consumer_key: &str,
+    access_token: &str,
+    shareable: &Shareable,
+) -> Result<(), String> {
+    http::client()
+        .post("https://getpocket.com/v3/add")
+        .header("X-Accept", "application/json")
+        .json(&json!({
+            "url": shareable.url,
+            "title": shareable.title,
+            "tags": format!("keyword-notifier,{}", shareable.source),
+            "consumer_key": consumer_key,
+            "access_token": access_token,
+        }))
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| format!("{}", e))?;
+    Ok(())
+}
+
+async fn add_to_instapaper(
+    username: &str,
+    password: Option<&str>,
+    shareable: &Shareable,
+) -> Result<(), String> {
+    http::client()
+        .post("https://www.instapaper.com/api/add")
+        .basic_auth(username, password)
+        .form(&[
+            ("url", shareable.url.as_str()),
+            ("title", shareable.title.as_str()),
+        ])
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| format!("{}", e))?;
+    Ok(())
+}
+
+// Sends the shareable to every configured read-later service.
+pub async fn save(config: &Config, shareable: &Shareable) -> Result<(), String> {
+    if let (Some(consumer_key), Some(access_token)) =
+        (&config.pocket_consumer_key, &config.pocket_access_token)
+    {
+        add_to_pocket(consumer_key, access_token, shareable).await?;
+        info!("Saved {} to Pocket", shareable.id);
+    }
+
+    if let Some(username) = &config.instapaper_username {
+        add_to_instapaper(username, config.instapaper_password.as_deref(), shareable).await?;
+        info!("Saved {} to Instapaper", shareable.id);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::http;
+use crate::text;
+
+const PDS_URL: &str = "https://bsky.social/xrpc";
+// Bluesky counts graphemes, not bytes or chars.
+const MAX_POST_LENGTH: usize = 300;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlueskySession {
+    access_jwt: String,
+    did: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlueskyRecord {
+    uri: String,
+}
+
+pub async fn post(handle: &str, app_password: &str, text: &str) -> Result<String, String> {
+    let client = http::client();
+    // Leaves room for the ellipsis.
+    let text = if text::length(text) > MAX_POST_LENGTH {
+        text::truncate(text, MAX_POST_LENGTH - 1).unwrap_or_default()
+    } else {
+        text.to_string()
+    };
+
+    let session = client
+        .post(format!("{}/com.atproto.server.createSession", PDS_URL))
+        .json(&json!({ "identifier": handle, "password": app_password }))
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| format!("{}", e))?
+        .json::<BlueskySession>()
+        .await
+        .map_err(|e| format!("{}", e))?;
+
+    let record = client
+        .post(format!("{}/com.atproto.repo.createRecord", PDS_URL))
+        .bearer_auth(&session.access_jwt)
+        .json(&json!({
+            "repo": session.did,
+            "collection": "app.bsky.feed.post",
+            "record": {
+                "$type": "app.bsky.feed.post",
+                "text": text,
+                "createdAt": Utc::now().to_rfc3339(),
+            }
+        }))
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| format!("{}", e))?
+        .json::<BlueskyRecord>()
+        .await
+        .map_err(|e| format!("{}", e))?;
+
+    // at://did:plc:xyz/app.bsky.feed.post/<rkey>
+    let rkey = record.uri.rsplit('/').next().unwrap_or_default();
+    Ok(format!(
+        "https://bsky.app/profile/{}/post/{}",
+        session.did, rkey
+    ))
+}
@@ -0,0 +1,27 @@
+use serde::Deserialize;
+
+use crate::http;
+
+#[derive(Debug, Deserialize)]
+struct MastodonStatus {
+    url: String,
+}
+
+pub async fn post(instance_url: &str, access_token: &str, text: &str) -> Result<String, String> {
+    let resp = http::client()
+        .post(format!(
+            "{}/api/v1/statuses",
+            instance_url.trim_end_matches('/')
+        ))
+        .bearer_auth(access_token)
+        .form(&[("status", text)])
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| format!("{}", e))?
+        .json::<MastodonStatus>()
+        .await
+        .map_err(|e| format!("{}", e))?;
+
+    Ok(resp.url)
+}
@@ -0,0 +1,221 @@
+pub mod bluesky;
+pub mod mastodon;
+
+use chrono::{DateTime, Utc};
+use mysql::prelude::*;
+use mysql::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::{task, time};
+use tracing::{error, info, info_span, Instrument};
+
+use crate::maintenance;
+use crate::shutdown;
+use crate::Config;
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Target {
+    Mastodon,
+    Bluesky,
+}
+
+impl Target {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Target::Mastodon => "mastodon",
+            Target::Bluesky => "bluesky",
+        }
+    }
+
+    fn from_str(target: &str) -> Option<Target> {
+        match target {
+            "mastodon" => Some(Target::Mastodon),
+            "bluesky" => Some(Target::Bluesky),
+            _ => None,
+        }
+    }
+
+    pub fn is_configured(&self, config: &Config) -> bool {
+        match self {
+            Target::Mastodon => {
+                config.reshare_mastodon_instance.is_some()
+                    && config.reshare_mastodon_token.is_some()
+            }
+            Target::Bluesky => {
+                config.reshare_bluesky_handle.is_some()
+                    && config.reshare_bluesky_app_password.is_some()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Reshare {
+    pub id: u64,
+    pub shareable_id: String,
+    pub text: String,
+    pub target: String,
+    pub scheduled_at: String,
+    pub posted_at: Option<String>,
+    pub post_url: Option<String>,
+    pub error: Option<String>,
+}
+
+const SELECT_RESHARES: &str = r"SELECT id, shareable_id, text, target,
+    DATE_FORMAT(scheduled_at, '%Y-%m-%dT%H:%i:%sZ'),
+    DATE_FORMAT(posted_at, '%Y-%m-%dT%H:%i:%sZ'),
+    post_url, error
+  FROM reshares";
+
+type ReshareRow = (
+    u64,
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+);
+
+fn to_reshare(
+    (id, shareable_id, text, target, scheduled_at, posted_at, post_url, error): ReshareRow,
+) -> Reshare {
+    Reshare {
+        id,
+        shareable_id,
+        text,
+        target,
+        scheduled_at,
+        posted_at,
+        post_url,
+        error,
+    }
+}
+
+pub fn enqueue(
+    pool: &Pool,
+    shareable_id: &str,
+    text: &str,
+    target: Target,
+    scheduled_at: DateTime<Utc>,
+) -> mysql::Result<u64> {
+    let mut conn = pool.get_conn()?;
+    conn.exec_drop(
+        r"INSERT INTO reshares (shareable_id, text, target, scheduled_at)
+      VALUES (:shareable_id, :text, :target, :scheduled_at)",
+        params! {
+            "shareable_id" => shareable_id,
+            "text" => text,
+            "target" => target.as_str(),
+            "scheduled_at" => scheduled_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        },
+    )?;
+    Ok(conn.last_insert_id())
+}
+
+pub fn pending(pool: &Pool) -> mysql::Result<Vec<Reshare>> {
+    let mut conn = pool.get_conn()?;
+    conn.query_map(
+        format!(
+            "{} WHERE posted_at IS NULL AND error IS NULL ORDER BY scheduled_at",
+            SELECT_RESHARES
+        ),
+        to_reshare,
+    )
+}
+
+async fn publish(config: &Config, target: Target, text: &str) -> Result<String, String> {
+    match (target, config) {
+        (
+            Target::Mastodon,
+            Config {
+                reshare_mastodon_instance: Some(instance),
+                reshare_mastodon_token: Some(token),
+                ..
+            },
+        ) => mastodon::post(instance, token, text).await,
+        (
+            Target::Bluesky,
+            Config {
+                reshare_bluesky_handle: Some(handle),
+                reshare_bluesky_app_password: Some(password),
+                ..
+            },
+        ) => bluesky::post(handle, password, text).await,
+        _ => Err(format!("{} is not configured", target.as_str())),
+    }
+}
+
+fn due(pool: &Pool) -> mysql::Result<Vec<Reshare>> {
+    let mut conn = pool.get_conn()?;
+    conn.query_map(
+        format!(
+            "{} WHERE posted_at IS NULL AND error IS NULL AND scheduled_at <= UTC_TIMESTAMP() ORDER BY scheduled_at",
+            SELECT_RESHARES
+        ),
+        to_reshare,
+    )
+}
+
+fn record_result(pool: &Pool, id: u64, result: &Result<String, String>) -> mysql::Result<()> {
+    let mut conn = pool.get_conn()?;
+    match result {
+        Ok(post_url) => conn.exec_drop(
+            "UPDATE reshares SET posted_at = UTC_TIMESTAMP(), post_url = :post_url WHERE id = :id",
+            params! { "post_url" => post_url, "id" => id },
+        ),
+        Err(e) => conn.exec_drop(
+            "UPDATE reshares SET error = :error WHERE id = :id",
+            params! { "error" => e, "id" => id },
+        ),
+    }
+}
+
+// Posts queued reshares once their scheduled time has passed. Failed posts
+// keep their error and are not retried, so they can be inspected and re-queued.
+pub fn spawn_publisher(config: Config, pool: Arc<Pool>) -> JoinHandle<()> {
+    task::spawn(async move {
+        let mut interval = time::interval(Duration::from_secs(60));
+
+        while shutdown::tick(&mut interval).await {
+            if maintenance::is_active() {
+                continue;
+            }
+            let due = match due(&pool) {
+                Ok(due) => due,
+                Err(e) => {
+                    error!("Error loading reshares: {}", e);
+                    continue;
+                }
+            };
+
+            for reshare in due {
+                let span = info_span!(
+                    "reshare",
+                    shareable_id = %reshare.shareable_id,
+                    target = %reshare.target
+                );
+                let result = match Target::from_str(&reshare.target) {
+                    Some(target) => {
+                        publish(&config, target, &reshare.text)
+                            .instrument(span.clone())
+                            .await
+                    }
+                    None => Err(format!("Unknown reshare target {}", reshare.target)),
+                };
+                let _entered = span.enter();
+                match &result {
+                    Ok(url) => info!("Reshared {} to {}", reshare.shareable_id, url),
+                    Err(e) => error!("Could not reshare {}: {}", reshare.shareable_id, e),
+                }
+                if let Err(e) = record_result(&pool, reshare.id, &result) {
+                    error!("Error: {}", e);
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,119 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::storage::Filter;
+
+type Signature = Hmac<Sha256>;
+
+// A filtered view of the items that can be looked at without access to the
+// dashboard, until it expires. Everything is in the token, so links can't be
+// revoked one by one, only all of them by changing SHARE_SECRET.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Link {
+    pub filter: Filter,
+    pub expires_at: DateTime<Utc>,
+}
+
+fn signature(secret: &str) -> Signature {
+    // HMAC takes keys of any length.
+    Signature::new_from_slice(secret.as_bytes()).unwrap()
+}
+
+// `<payload>.<signature>`, both base64url so the token fits into a path.
+pub fn sign(secret: &str, link: &Link) -> String {
+    // Filters and dates always serialize.
+    let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(link).unwrap());
+    let mut mac = signature(secret);
+    mac.update(payload.as_bytes());
+    let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+    format!("{}.{}", payload, signature)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Invalid {
+    // Not signed with our secret, or not a token at all.
+    Forged,
+    Expired,
+}
+
+pub fn verify(secret: &str, token: &str, now: DateTime<Utc>) -> Result<Link, Invalid> {
+    let (payload, signature_part) = token.split_once('.').ok_or(Invalid::Forged)?;
+    let given = URL_SAFE_NO_PAD
+        .decode(signature_part)
+        .map_err(|_| Invalid::Forged)?;
+    let mut mac = signature(secret);
+    mac.update(payload.as_bytes());
+    mac.verify_slice(&given).map_err(|_| Invalid::Forged)?;
+
+    let link: Link = URL_SAFE_NO_PAD
+        .decode(payload)
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .ok_or(Invalid::Forged)?;
+    if link.expires_at <= now {
+        return Err(Invalid::Expired);
+    }
+    Ok(link)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn link() -> Link {
+        Link {
+            filter: Filter {
+                source: Some(String::from("twitter")),
+                since: Some("2022-06-01T00:00:00Z".parse().unwrap()),
+                ..Filter::default()
+            },
+            expires_at: "2022-06-08T00:00:00Z".parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn verifies_own_links() {
+        let token = sign("secret", &link());
+        let now = link().expires_at - Duration::days(1);
+        let verified = verify("secret", &token, now).unwrap();
+        assert_eq!(verified.filter.source.as_deref(), Some("twitter"));
+        assert_eq!(verified.filter.since, link().filter.since);
+        assert_eq!(verified.expires_at, link().expires_at);
+
+        assert_eq!(
+            verify("secret", &token, link().expires_at).unwrap_err(),
+            Invalid::Expired
+        );
+    }
+
+    #[test]
+    fn rejects_forged_links() {
+        let now = link().expires_at - Duration::days(1);
+        let token = sign("secret", &link());
+        assert_eq!(verify("other", &token, now).unwrap_err(), Invalid::Forged);
+
+        // A longer lifetime under the old signature.
+        let (_, signature) = token.split_once('.').unwrap();
+        let longer = sign(
+            "other",
+            &Link {
+                expires_at: link().expires_at + Duration::days(365),
+                ..link()
+            },
+        );
+        let (payload, _) = longer.split_once('.').unwrap();
+        assert_eq!(
+            verify("secret", &format!("{}.{}", payload, signature), now).unwrap_err(),
+            Invalid::Forged
+        );
+        assert_eq!(
+            verify("secret", "garbage", now).unwrap_err(),
+            Invalid::Forged
+        );
+    }
+}
@@ -0,0 +1,70 @@
+use std::sync::OnceLock;
+use tokio::signal;
+use tokio::sync::watch;
+use tokio::time::{self, Instant, Interval};
+use tracing::info;
+
+// Flipped once, on SIGTERM or Ctrl-C. Long running tasks finish what they are
+// doing and stop instead of starting something new.
+static SHUTDOWN: OnceLock<watch::Sender<bool>> = OnceLock::new();
+
+fn sender() -> &'static watch::Sender<bool> {
+    SHUTDOWN.get_or_init(|| watch::channel(false).0)
+}
+
+pub fn is_requested() -> bool {
+    *sender().borrow()
+}
+
+pub fn request() {
+    sender().send_replace(true);
+}
+
+// Resolves once shutdown was requested, right away if it already was.
+pub async fn requested() {
+    let mut receiver = sender().subscribe();
+    while !*receiver.borrow_and_update() {
+        if receiver.changed().await.is_err() {
+            return;
+        }
+    }
+}
+
+// Sleeps until `deadline`, returns false if shutdown was requested first.
+pub async fn sleep_until(deadline: Instant) -> bool {
+    tokio::select! {
+        _ = time::sleep_until(deadline) => true,
+        _ = requested() => false,
+    }
+}
+
+// Waits for the next tick, returns false if shutdown was requested first.
+pub async fn tick(interval: &mut Interval) -> bool {
+    tokio::select! {
+        _ = interval.tick() => true,
+        _ = requested() => false,
+    }
+}
+
+// SIGTERM is what container orchestrators send on deploys, Ctrl-C is for
+// local runs.
+pub async fn wait_for_signal() {
+    #[cfg(unix)]
+    let terminate = async {
+        match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+            Ok(mut stream) => {
+                stream.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = signal::ctrl_c() => {}
+        _ = terminate => {}
+    }
+    info!("Shutting down");
+    request();
+}
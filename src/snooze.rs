@@ -0,0 +1,194 @@
+use chrono::{DateTime, Utc};
+use mysql::prelude::*;
+use mysql::*;
+use serde::{Deserialize, Serialize};
+
+use crate::fetcher::base::Shareable;
+
+// Tag muted items get, so they can be found and aren't in digests either.
+pub const MUTED_TAG: &str = "muted";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Source,
+    // Part of the title, e.g. a conference hashtag that took over the keyword.
+    Keyword,
+}
+
+impl Kind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Source => "source",
+            Kind::Keyword => "keyword",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Kind> {
+        match kind {
+            "source" => Some(Kind::Source),
+            "keyword" => Some(Kind::Keyword),
+            _ => None,
+        }
+    }
+}
+
+// Items of a noisy source or keyword are still fetched and stored until then,
+// but nobody is notified about them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snooze {
+    pub kind: Kind,
+    pub name: String,
+    pub until: DateTime<Utc>,
+}
+
+impl Snooze {
+    fn mutes(&self, shareable: &Shareable) -> bool {
+        match self.kind {
+            Kind::Source => shareable.source == self.name,
+            Kind::Keyword => shareable
+                .title
+                .to_lowercase()
+                .contains(&self.name.to_lowercase()),
+        }
+    }
+}
+
+// The snoozes that have not run out yet.
+pub fn active(pool: &Pool) -> mysql::Result<Vec<Snooze>> {
+    let mut conn = pool.get_conn()?;
+    let rows: Vec<(String, String, String)> = conn.query(
+        r"SELECT kind, name, DATE_FORMAT(until, '%Y-%m-%dT%H:%i:%sZ') FROM snoozes
+          WHERE until > UTC_TIMESTAMP() ORDER BY until",
+    )?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(kind, name, until)| {
+            Some(Snooze {
+                kind: Kind::parse(&kind)?,
+                name,
+                until: until.parse().ok()?,
+            })
+        })
+        .collect())
+}
+
+// Replaces an earlier snooze of the same source or keyword.
+pub fn set(pool: &Pool, snooze: &Snooze) -> mysql::Result<()> {
+    let mut conn = pool.get_conn()?;
+    conn.exec_drop(
+        r"INSERT INTO snoozes (kind, name, until) VALUES (:kind, :name, :until)
+          ON DUPLICATE KEY UPDATE until = VALUES(until)",
+        params! {
+            "kind" => snooze.kind.as_str(),
+            "name" => &snooze.name,
+            "until" => snooze.until.format("%Y-%m-%d %H:%M:%S").to_string(),
+        },
+    )
+}
+
+pub fn remove(pool: &Pool, kind: Kind, name: &str) -> mysql::Result<()> {
+    let mut conn = pool.get_conn()?;
+    conn.exec_drop(
+        "DELETE FROM snoozes WHERE kind = :kind AND name = :name",
+        params! { "kind" => kind.as_str(), "name" => name },
+    )
+}
+
+fn partition(snoozes: &[Snooze], shareables: &[Shareable]) -> (Vec<Shareable>, Vec<Shareable>) {
+    shareables
+        .iter()
+        .cloned()
+        .partition(|shareable| snoozes.iter().any(|snooze| snooze.mutes(shareable)))
+}
+
+// Tags the new items that are snoozed and returns the others, the ones to
+// notify about.
+pub fn mute(pool: &Pool, shareables: &[Shareable]) -> mysql::Result<Vec<Shareable>> {
+    let (muted, rest) = partition(&active(pool)?, shareables);
+    if !muted.is_empty() {
+        let mut conn = pool.get_conn()?;
+        conn.exec_batch(
+            "INSERT IGNORE INTO shareable_tags (shareable_id, tag) VALUES (:id, :tag)",
+            muted
+                .iter()
+                .map(|shareable| params! { "id" => &shareable.id, "tag" => MUTED_TAG }),
+        )?;
+    }
+    Ok(rest)
+}
+
+// The items not tagged as muted when they arrived.
+pub fn unmuted(pool: &Pool, shareables: Vec<Shareable>) -> mysql::Result<Vec<Shareable>> {
+    if shareables.is_empty() {
+        return Ok(shareables);
+    }
+    let mut conn = pool.get_conn()?;
+    let placeholders = vec!["?"; shareables.len()].join(", ");
+    let mut values: Vec<Value> = vec![MUTED_TAG.into()];
+    values.extend(
+        shareables
+            .iter()
+            .map(|shareable| shareable.id.as_str().into()),
+    );
+    let muted: Vec<String> = conn.exec(
+        format!(
+            "SELECT shareable_id FROM shareable_tags WHERE tag = ? AND shareable_id IN ({})",
+            placeholders
+        ),
+        values,
+    )?;
+    Ok(shareables
+        .into_iter()
+        .filter(|shareable| !muted.contains(&shareable.id))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shareable(id: &str, title: &str, source: &str) -> Shareable {
+        Shareable {
+            id: String::from(id),
+            title: String::from(title),
+            date: String::from("2022-06-01T12:00:00Z"),
+            url: format!("https://example.com/{}", id),
+            source: String::from(source),
+            canonical_url: None,
+        }
+    }
+
+    #[test]
+    fn mutes_sources_and_keywords() {
+        let until = "2022-06-08T00:00:00Z".parse().unwrap();
+        let snoozes = vec![
+            Snooze {
+                kind: Kind::Source,
+                name: String::from("twitter"),
+                until,
+            },
+            Snooze {
+                kind: Kind::Keyword,
+                name: String::from("#HashiConf"),
+                until,
+            },
+        ];
+        let (muted, rest) = partition(
+            &snoozes,
+            &[
+                shareable("1", "cdktf is great", "twitter"),
+                shareable("2", "Live from #hashiconf: cdktf", "mastodon"),
+                shareable("3", "How do I use cdktf?", "stackoverflow"),
+            ],
+        );
+        let ids = |shareables: Vec<Shareable>| -> Vec<String> {
+            shareables
+                .into_iter()
+                .map(|shareable| shareable.id)
+                .collect()
+        };
+        assert_eq!(ids(muted), vec!["1", "2"]);
+        assert_eq!(ids(rest), vec!["3"]);
+    }
+}
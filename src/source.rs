@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tracing::warn;
+
+// Bundled with the binary, so the UI never has to hotlink an upstream favicon.
+const ICONS: &[(&str, &str)] = &[
+    ("bluesky", include_str!("../static/icons/bluesky.svg")),
+    ("crates", include_str!("../static/icons/crates.svg")),
+    ("default", include_str!("../static/icons/default.svg")),
+    ("devto", include_str!("../static/icons/devto.svg")),
+    ("discord", include_str!("../static/icons/discord.svg")),
+    ("dockerhub", include_str!("../static/icons/dockerhub.svg")),
+    ("github", include_str!("../static/icons/github.svg")),
+    ("gitlab", include_str!("../static/icons/gitlab.svg")),
+    ("hackernews", include_str!("../static/icons/hackernews.svg")),
+    ("mastodon", include_str!("../static/icons/mastodon.svg")),
+    ("matrix", include_str!("../static/icons/matrix.svg")),
+    ("medium", include_str!("../static/icons/medium.svg")),
+    ("news", include_str!("../static/icons/news.svg")),
+    ("npm", include_str!("../static/icons/npm.svg")),
+    ("podcast", include_str!("../static/icons/podcast.svg")),
+    ("quora", include_str!("../static/icons/quora.svg")),
+    ("slack", include_str!("../static/icons/slack.svg")),
+    (
+        "stackexchange",
+        include_str!("../static/icons/stackexchange.svg"),
+    ),
+    (
+        "stackoverflow",
+        include_str!("../static/icons/stackoverflow.svg"),
+    ),
+    ("telegram", include_str!("../static/icons/telegram.svg")),
+    ("terraform", include_str!("../static/icons/terraform.svg")),
+    ("twitch", include_str!("../static/icons/twitch.svg")),
+    ("twitter", include_str!("../static/icons/twitter.svg")),
+    ("wikipedia", include_str!("../static/icons/wikipedia.svg")),
+];
+
+#[derive(Debug, Default)]
+struct Icons {
+    // Source name to an icon file on disk, from `SOURCE_ICONS`.
+    custom: HashMap<String, PathBuf>,
+    // Stack Exchange sites other than Stack Overflow share one icon.
+    stackexchange_sites: Vec<String>,
+}
+
+static ICON_CONFIG: OnceLock<Icons> = OnceLock::new();
+
+// Called once on startup. Entries look like `blog=/etc/icons/blog.svg`.
+pub fn configure(source_icons: &[String], stackexchange_sites: &[String]) {
+    let mut custom = HashMap::new();
+    for entry in source_icons.iter() {
+        match entry.split_once('=') {
+            Some((name, path)) if !name.is_empty() && !path.is_empty() => {
+                custom.insert(name.to_string(), PathBuf::from(path));
+            }
+            _ => warn!("Ignoring source icon {}, expected name=path", entry),
+        }
+    }
+
+    let icons = Icons {
+        custom,
+        stackexchange_sites: stackexchange_sites.to_vec(),
+    };
+    if ICON_CONFIG.set(icons).is_err() {
+        warn!("Source icons were already configured");
+    }
+}
+
+fn icon_config() -> &'static Icons {
+    ICON_CONFIG.get_or_init(Icons::default)
+}
+
+pub enum Icon {
+    Bundled(&'static str),
+    File(PathBuf),
+}
+
+impl Icon {
+    pub fn content_type(&self) -> &'static str {
+        let extension = match self {
+            Icon::Bundled(_) => return "image/svg+xml",
+            Icon::File(path) => path.extension().and_then(|ext| ext.to_str()),
+        };
+        match extension {
+            Some("png") => "image/png",
+            Some("ico") => "image/x-icon",
+            Some("jpg") | Some("jpeg") => "image/jpeg",
+            _ => "image/svg+xml",
+        }
+    }
+}
+
+// The `source` column of an item, e.g. `twitter` or a Stack Exchange site.
+pub struct Source<'a>(pub &'a str);
+
+impl<'a> Source<'a> {
+    // Name of the icon to show, custom icons win over bundled ones.
+    fn icon_name(&self) -> &'a str {
+        let config = icon_config();
+        if config.custom.contains_key(self.0) || ICONS.iter().any(|(name, _)| *name == self.0) {
+            self.0
+        } else if config.stackexchange_sites.iter().any(|site| site == self.0) {
+            "stackexchange"
+        } else {
+            "default"
+        }
+    }
+
+    pub fn icon_path(&self) -> String {
+        format!("/static/icons/{}", self.icon_name())
+    }
+}
+
+// Resolves a name from `icon_path` for the static route.
+pub fn icon(name: &str) -> Option<Icon> {
+    if let Some(path) = icon_config().custom.get(name) {
+        return Some(Icon::File(path.clone()));
+    }
+    ICONS
+        .iter()
+        .find(|(bundled, _)| *bundled == name)
+        .map(|(_, svg)| Icon::Bundled(svg))
+}
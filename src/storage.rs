@@ -0,0 +1,438 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use mysql::prelude::*;
+use mysql::*;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crate::fetcher::base::{self, Shareable};
+use crate::fetcher::date::{normalize_date, DateFormat};
+use crate::fetcher::error::{self, ErrorCount};
+
+// A stored shareable together with the state we keep about it after fetching.
+#[derive(Debug, Clone, Serialize, Eq, PartialEq, PartialOrd, Ord)]
+pub struct Item {
+    #[serde(flatten)]
+    pub shareable: Shareable,
+    pub issue_key: Option<String>,
+    pub issue_url: Option<String>,
+    pub starred: bool,
+    pub read: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Stats {
+    pub total: u64,
+    pub by_source: Vec<(String, u64)>,
+    pub latest_date: Option<String>,
+    pub skipped_runs: u64,
+    pub fetch_errors: Vec<ErrorCount>,
+}
+
+static READ_POOL: OnceLock<Pool> = OnceLock::new();
+
+// Called once on startup when reads get their own pool, on the primary or a
+// replica. Big insert batches from the fetchers then can't starve page loads.
+pub fn use_read_pool(pool: Pool) {
+    if READ_POOL.set(pool).is_err() {
+        tracing::warn!("Read pool was already configured");
+    }
+}
+
+// For reads that may lag a little behind the writes, e.g. the item list.
+// Reads that come right before a write stay on the write pool.
+fn reader(write_pool: &Pool) -> &Pool {
+    READ_POOL.get().unwrap_or(write_pool)
+}
+
+// The time a caller still waits for a query. The web layer's timeout drops
+// the response, but the blocking query would keep running for nobody. With a
+// deadline, waiting for a connection gives up in time and MySQL aborts the
+// query itself once the budget is spent.
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline(Option<Instant>);
+
+impl Deadline {
+    #[cfg(feature = "grpc")]
+    pub const NONE: Deadline = Deadline(None);
+
+    pub fn after(budget: Duration) -> Deadline {
+        Deadline(Some(Instant::now() + budget))
+    }
+
+    fn remaining(&self) -> Option<Duration> {
+        self.0
+            .map(|at| at.saturating_duration_since(Instant::now()))
+    }
+
+    fn get_conn(&self, pool: &Pool) -> mysql::Result<PooledConn> {
+        match self.remaining() {
+            None => pool.get_conn(),
+            Some(remaining) if remaining.is_zero() => {
+                Err(mysql::Error::DriverError(DriverError::Timeout))
+            }
+            Some(remaining) => pool.try_get_conn(remaining),
+        }
+    }
+
+    // Optimizer hint for SELECTs. It is a comment to MariaDB, which ignores
+    // it, and unlike the session variable it can't leak into the next user
+    // of the pooled connection.
+    fn hint(&self) -> String {
+        match self.remaining() {
+            Some(remaining) => format!(
+                "/*+ MAX_EXECUTION_TIME({}) */",
+                remaining.as_millis().max(1)
+            ),
+            None => String::new(),
+        }
+    }
+}
+
+// A value as JSON has it or as the text of a query string.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FieldValue<T> {
+    Value(T),
+    Text(String),
+}
+
+// Empty form fields come as `since=`, they mean no value instead of an
+// invalid one.
+fn empty_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + FromStr,
+    T::Err: Display,
+{
+    match Option::<FieldValue<T>>::deserialize(deserializer)? {
+        Some(FieldValue::Value(value)) => Ok(Some(value)),
+        Some(FieldValue::Text(text)) if text.is_empty() => Ok(None),
+        Some(FieldValue::Text(text)) => text.parse().map(Some).map_err(de::Error::custom),
+        None => Ok(None),
+    }
+}
+
+// Narrows the item list, from the query string of `/` and `/api/shareables`.
+// Empty values are ignored, so a filter form can submit all of its fields.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Filter {
+    pub source: Option<String>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub since: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub until: Option<DateTime<Utc>>,
+    // Substring of the title.
+    pub q: Option<String>,
+    // Only items not marked as read yet.
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub unread: Option<bool>,
+}
+
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+impl Filter {
+    // The WHERE clause and its parameters. Dates are compared as stored,
+    // which works for the normalized RFC 3339 ones.
+    fn conditions(&self) -> (String, Vec<Value>) {
+        let mut conditions = vec![];
+        let mut values: Vec<Value> = vec![];
+        if let Some(source) = self.source.as_deref().filter(|source| !source.is_empty()) {
+            conditions.push("source = ?");
+            values.push(source.into());
+        }
+        if let Some(since) = self.since {
+            conditions.push("date >= ?");
+            values.push(since.to_rfc3339_opts(SecondsFormat::Secs, true).into());
+        }
+        if let Some(until) = self.until {
+            conditions.push("date < ?");
+            values.push(until.to_rfc3339_opts(SecondsFormat::Secs, true).into());
+        }
+        if let Some(q) = self.q.as_deref().filter(|q| !q.is_empty()) {
+            conditions.push("title LIKE ?");
+            values.push(format!("%{}%", escape_like(q)).into());
+        }
+        if self.unread == Some(true) {
+            conditions.push("read_at IS NULL");
+        }
+        if conditions.is_empty() {
+            (String::new(), values)
+        } else {
+            (format!(" WHERE {}", conditions.join(" AND ")), values)
+        }
+    }
+}
+
+type ItemRow = (
+    String,
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    bool,
+    bool,
+    Option<String>,
+);
+
+// Rows stored before dates were normalized are normalized on the way out, so
+// items sort by date across sources.
+fn to_item(
+    (id, title, url, date, source, issue_key, issue_url, starred, read, canonical_url): ItemRow,
+) -> Item {
+    Item {
+        shareable: Shareable {
+            id,
+            title,
+            date: normalize_date(&date, DateFormat::Any),
+            url,
+            source,
+            canonical_url,
+        },
+        issue_key,
+        issue_url,
+        starred,
+        read,
+    }
+}
+
+pub fn list_items(pool: &Pool, deadline: Deadline, filter: &Filter) -> mysql::Result<Vec<Item>> {
+    let mut conn = deadline.get_conn(reader(pool))?;
+    let (conditions, values) = filter.conditions();
+    conn.exec_map(
+        format!(
+            "SELECT {} id, title, url, date, source, issue_key, issue_url, starred_at IS NOT NULL, read_at IS NOT NULL, canonical_url from shareables{}",
+            deadline.hint(),
+            conditions
+        ),
+        values,
+        to_item,
+    )
+}
+
+// Marks the items of the filter as read, returns how many were unread.
+pub fn mark_read(pool: &Pool, filter: &Filter) -> mysql::Result<u64> {
+    let mut conn = pool.get_conn()?;
+    let (conditions, values) = filter.conditions();
+    // Items read before keep their time, and don't count as changed.
+    conn.exec_drop(
+        format!(
+            "UPDATE shareables SET read_at = COALESCE(read_at, UTC_TIMESTAMP()){}",
+            conditions
+        ),
+        values,
+    )?;
+    Ok(conn.affected_rows())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    #[serde(default)]
+    pub q: String,
+}
+
+const SEARCH_LIMIT: usize = 100;
+
+// Full-text search over titles, best matches first. Words shorter than the
+// server's `innodb_ft_min_token_size` (3 by default) are not indexed.
+pub fn search_items(pool: &Pool, deadline: Deadline, q: &str) -> mysql::Result<Vec<Item>> {
+    let mut conn = deadline.get_conn(reader(pool))?;
+    conn.exec_map(
+        format!(
+            r"SELECT {} id, title, url, date, source, issue_key, issue_url, starred_at IS NOT NULL, read_at IS NOT NULL, canonical_url
+              FROM shareables WHERE MATCH(title) AGAINST(:q IN NATURAL LANGUAGE MODE)
+              ORDER BY MATCH(title) AGAINST(:q IN NATURAL LANGUAGE MODE) DESC
+              LIMIT {}",
+            deadline.hint(),
+            SEARCH_LIMIT
+        ),
+        params! { "q" => q },
+        to_item,
+    )
+}
+
+pub fn load_shareable(pool: &Pool, id: &str) -> mysql::Result<Option<Shareable>> {
+    let mut conn = pool.get_conn()?;
+    let row: Option<(String, String, String, String, String, Option<String>)> = conn.exec_first(
+        "SELECT id, title, url, date, source, canonical_url FROM shareables WHERE id = :id",
+        params! { "id" => id },
+    )?;
+
+    Ok(
+        row.map(|(id, title, url, date, source, canonical_url)| Shareable {
+            id,
+            title,
+            date,
+            url,
+            source,
+            canonical_url,
+        }),
+    )
+}
+
+// How an item got here, recorded when it was first stored. Items stored
+// before provenance was have none.
+#[derive(Debug, Clone, Serialize)]
+pub struct Provenance {
+    pub fetcher: Option<String>,
+    pub keyword: Option<String>,
+    pub version: Option<String>,
+    pub ingested_at: Option<String>,
+}
+
+pub fn load_provenance(pool: &Pool, id: &str) -> mysql::Result<Option<Provenance>> {
+    let mut conn = pool.get_conn()?;
+    let row: Option<Row> = conn.exec_first(
+        r"SELECT ingested_by, ingested_keyword, ingested_version,
+            DATE_FORMAT(ingested_at, '%Y-%m-%dT%H:%i:%sZ')
+          FROM shareables WHERE id = :id",
+        params! { "id" => id },
+    )?;
+
+    Ok(row.map(|mut row| Provenance {
+        fetcher: row.take(0).flatten(),
+        keyword: row.take(1).flatten(),
+        version: row.take(2).flatten(),
+        ingested_at: row.take(3).flatten(),
+    }))
+}
+
+pub fn stats(pool: &Pool, deadline: Deadline) -> mysql::Result<Stats> {
+    let mut conn = deadline.get_conn(reader(pool))?;
+    let by_source: Vec<(String, u64)> = conn.query(format!(
+        "SELECT {} source, COUNT(*) FROM shareables GROUP BY source ORDER BY source",
+        deadline.hint()
+    ))?;
+    let latest_date: Option<Option<String>> = conn.query_first(format!(
+        "SELECT {} MAX(date) FROM shareables",
+        deadline.hint()
+    ))?;
+
+    Ok(Stats {
+        total: by_source.iter().map(|(_, count)| count).sum(),
+        by_source,
+        latest_date: latest_date.flatten(),
+        skipped_runs: base::skipped_runs(),
+        fetch_errors: error::counts(),
+    })
+}
+
+// Shareables created after the given item event, together with the id of the
+// last event seen. Lets consumers follow new items by polling the event log.
+pub fn created_since(pool: &Pool, after_event: u64) -> mysql::Result<(u64, Vec<Shareable>)> {
+    let mut conn = pool.get_conn()?;
+    let rows: Vec<(u64, String)> = conn.exec(
+        r"SELECT id, payload FROM item_events
+          WHERE id > :after AND event_type = 'item.created' ORDER BY id LIMIT 100",
+        params! { "after" => after_event },
+    )?;
+
+    let last = rows.last().map(|(id, _)| *id).unwrap_or(after_event);
+    let shareables = rows
+        .into_iter()
+        .filter_map(|(_, payload)| serde_json::from_str(&payload).ok())
+        .collect();
+    Ok((last, shareables))
+}
+
+pub fn last_event_id(pool: &Pool) -> mysql::Result<u64> {
+    let mut conn = pool.get_conn()?;
+    let last: Option<Option<u64>> = conn.query_first("SELECT MAX(id) FROM item_events")?;
+    Ok(last.flatten().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_empty_filter_fields() {
+        let filter: Filter = serde_json::from_value(serde_json::json!({
+            "source": "",
+            "since": "",
+            "until": "",
+            "q": "",
+            "unread": "",
+        }))
+        .unwrap();
+        assert_eq!(filter.conditions(), (String::new(), vec![]));
+
+        // As a query string has them and as share tokens store them.
+        let filter: Filter = serde_json::from_value(serde_json::json!({
+            "since": "2022-06-01T00:00:00Z",
+            "unread": "true",
+        }))
+        .unwrap();
+        assert!(filter.since.is_some());
+        assert_eq!(filter.unread, Some(true));
+        let filter: Filter =
+            serde_json::from_value(serde_json::json!({ "unread": true, "until": null })).unwrap();
+        assert_eq!(filter.unread, Some(true));
+        assert_eq!(filter.until, None);
+
+        assert!(serde_json::from_value::<Filter>(serde_json::json!({ "since": "soon" })).is_err());
+    }
+
+    #[test]
+    fn builds_filter_conditions() {
+        assert_eq!(Filter::default().conditions(), (String::new(), vec![]));
+
+        let filter = Filter {
+            source: Some(String::from("twitter")),
+            since: DateTime::parse_from_rfc3339("2022-06-01T00:00:00Z")
+                .ok()
+                .map(|since| since.with_timezone(&Utc)),
+            until: None,
+            q: Some(String::from("100%_sure")),
+            unread: None,
+        };
+        assert_eq!(
+            filter.conditions(),
+            (
+                String::from(" WHERE source = ? AND date >= ? AND title LIKE ?"),
+                vec![
+                    Value::from("twitter"),
+                    Value::from("2022-06-01T00:00:00Z"),
+                    Value::from("%100\\%\\_sure%"),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn ignores_empty_filter_values() {
+        let filter = Filter {
+            source: Some(String::new()),
+            q: Some(String::new()),
+            ..Filter::default()
+        };
+        assert_eq!(filter.conditions().0, "");
+    }
+
+    #[test]
+    fn filters_unread_items() {
+        let filter = Filter {
+            source: Some(String::from("twitter")),
+            unread: Some(true),
+            ..Filter::default()
+        };
+        assert_eq!(
+            filter.conditions().0,
+            " WHERE source = ? AND read_at IS NULL"
+        );
+        let filter = Filter {
+            unread: Some(false),
+            ..Filter::default()
+        };
+        assert_eq!(filter.conditions().0, "");
+    }
+}
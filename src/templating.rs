@@ -0,0 +1,73 @@
+use minijinja::{Environment, UndefinedBehavior};
+use serde::Serialize;
+use std::path::Path;
+
+// Templates dropped into a directory to change what we render without a fork,
+// the pages in TEMPLATES_DIR and the notifications in NOTIFY_TEMPLATE_DIR.
+// Undefined values are errors instead of empty text, so a misspelled field
+// fails when the templates are checked on startup. `.html` templates are
+// HTML-escaped.
+pub fn environment() -> Environment<'static> {
+    let mut env = Environment::new();
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    env
+}
+
+// Compiles the files in `dir` with the extension, named by file name, e.g.
+// `index.html`. Returns the names, sorted.
+pub fn load(
+    env: &mut Environment<'static>,
+    dir: &str,
+    extension: &str,
+) -> Result<Vec<String>, String> {
+    let entries = std::fs::read_dir(dir).map_err(|e| format!("{}: {}", dir, e))?;
+    let mut names = vec![];
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(extension) {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let source =
+            std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        env.add_template_owned(name.clone(), source)
+            .map_err(|e| format!("{}: {}", Path::new(dir).join(&name).display(), e))?;
+        names.push(name);
+    }
+    names.sort();
+    Ok(names)
+}
+
+pub fn render<C: Serialize>(
+    env: &Environment<'_>,
+    name: &str,
+    context: C,
+) -> Result<String, String> {
+    env.get_template(name)
+        .and_then(|template| template.render(context))
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn fails_on_undefined_values() {
+        let mut env = environment();
+        env.add_template("item.html", "<a href=\"{{ url }}\">{{ title }}</a>")
+            .unwrap();
+        assert_eq!(
+            render(
+                &env,
+                "item.html",
+                json!({ "url": "https://cdktf.io", "title": "<b>cdktf</b>" })
+            )
+            .unwrap(),
+            "<a href=\"https:&#x2f;&#x2f;cdktf.io\">&lt;b&gt;cdktf&lt;&#x2f;b&gt;</a>"
+        );
+        assert!(render(&env, "item.html", json!({ "url": "https://cdktf.io" })).is_err());
+        assert!(render(&env, "missing.html", json!({})).is_err());
+    }
+}
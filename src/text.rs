@@ -0,0 +1,66 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+// Cuts `text` after `max` graphemes, so emoji sequences and accents stay in
+// one piece. None if it already fits.
+pub fn truncate(text: &str, max: usize) -> Option<String> {
+    let (cut, _) = text.grapheme_indices(true).nth(max)?;
+    Some(format!("{}…", text[..cut].trim_end()))
+}
+
+// What a person would count as characters, e.g. for post length limits.
+pub fn length(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn keeps_text_that_fits() {
+        assert_eq!(truncate("cdktf", 5), None);
+        assert_eq!(truncate("", 0), None);
+        assert_eq!(truncate("🚀", 1), None);
+    }
+
+    #[test]
+    fn never_splits_zwj_sequences() {
+        // Woman technologist, a family and a rainbow flag, each one grapheme.
+        let text = "👩‍💻👨‍👩‍👧‍👦🏳️‍🌈 cdktf";
+        assert_eq!(length(text), 9);
+        assert_eq!(truncate(text, 1).as_deref(), Some("👩‍💻…"));
+        assert_eq!(truncate(text, 2).as_deref(), Some("👩‍💻👨‍👩‍👧‍👦…"));
+        assert_eq!(truncate(text, 3).as_deref(), Some("👩‍💻👨‍👩‍👧‍👦🏳️‍🌈…"));
+    }
+
+    #[test]
+    fn never_splits_flags_skin_tones_or_accents() {
+        assert_eq!(truncate("🇩🇪🇺🇸", 1).as_deref(), Some("🇩🇪…"));
+        assert_eq!(truncate("👍🏽👍🏽", 1).as_deref(), Some("👍🏽…"));
+        // "é" as e plus a combining acute accent.
+        assert_eq!(
+            truncate("cafe\u{301} au lait", 4).as_deref(),
+            Some("cafe\u{301}…")
+        );
+    }
+
+    #[test]
+    fn drops_trailing_whitespace_before_the_ellipsis() {
+        assert_eq!(truncate("cdktf is great", 6).as_deref(), Some("cdktf…"));
+    }
+
+    proptest! {
+        #[test]
+        fn truncated_text_is_a_prefix_within_the_limit(text in "\\PC{0,40}", max in 0usize..20) {
+            match truncate(&text, max) {
+                Some(short) => {
+                    let kept = short.strip_suffix('…').unwrap();
+                    prop_assert!(text.starts_with(kept));
+                    prop_assert!(length(kept) <= max);
+                }
+                None => prop_assert!(length(&text) <= max),
+            }
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use serde::Deserialize;
+use serde_json::json;
+use tracing::info;
+
+use crate::fetcher::base::Shareable;
+use crate::http;
+use crate::tracker::{description, Error, Ticket};
+use crate::Config;
+
+#[derive(Debug, Deserialize)]
+struct GitHubCreatedIssue {
+    number: u64,
+    html_url: String,
+}
+
+pub async fn create_issue(config: &Config, shareable: &Shareable) -> Result<Ticket, Error> {
+    let (token, repo) = match (&config.sources.github.token, &config.github_issue_repo) {
+        (Some(token), Some(repo)) => (token, repo),
+        _ => return Err(Error::NotConfigured),
+    };
+
+    let resp = http::client()
+        .post(format!("https://api.github.com/repos/{}/issues", repo))
+        .bearer_auth(token)
+        .header("Accept", "application/vnd.github+json")
+        .json(&json!({
+            "title": format!("[{}] {}", shareable.source, shareable.title),
+            "body": description(shareable),
+            "labels": config.github_issue_labels,
+        }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<GitHubCreatedIssue>()
+        .await?;
+
+    info!(
+        "Created GitHub issue {}#{} for {}",
+        repo, resp.number, shareable.id
+    );
+    Ok(Ticket {
+        key: format!("{}#{}", repo, resp.number),
+        url: resp.html_url,
+    })
+}
@@ -0,0 +1,50 @@
+use serde::Deserialize;
+use serde_json::json;
+use tracing::info;
+
+use crate::fetcher::base::Shareable;
+use crate::http;
+use crate::tracker::{description, Error, Ticket};
+use crate::Config;
+
+#[derive(Debug, Deserialize)]
+struct JiraCreatedIssue {
+    key: String,
+}
+
+pub async fn create_issue(config: &Config, shareable: &Shareable) -> Result<Ticket, Error> {
+    let (base_url, email, token, project) = match (
+        &config.jira_base_url,
+        &config.jira_email,
+        &config.jira_api_token,
+        &config.jira_project_key,
+    ) {
+        (Some(base_url), Some(email), Some(token), Some(project)) => {
+            (base_url.trim_end_matches('/'), email, token, project)
+        }
+        _ => return Err(Error::NotConfigured),
+    };
+
+    let resp = http::client()
+        .post(format!("{}/rest/api/2/issue", base_url))
+        .basic_auth(email, Some(token))
+        .json(&json!({
+            "fields": {
+                "project": { "key": project },
+                "summary": format!("[{}] {}", shareable.source, shareable.title),
+                "description": description(shareable),
+                "issuetype": { "name": "Task" },
+            }
+        }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<JiraCreatedIssue>()
+        .await?;
+
+    info!("Created Jira issue {} for {}", resp.key, shareable.id);
+    Ok(Ticket {
+        url: format!("{}/browse/{}", base_url, resp.key),
+        key: resp.key,
+    })
+}
@@ -0,0 +1,85 @@
+use serde::Deserialize;
+use serde_json::json;
+use tracing::info;
+
+use crate::fetcher::base::Shareable;
+use crate::http;
+use crate::tracker::{description, Error, Ticket};
+use crate::Config;
+
+const ISSUE_CREATE: &str = r"mutation IssueCreate($input: IssueCreateInput!) {
+  issueCreate(input: $input) {
+    success
+    issue { identifier url }
+  }
+}";
+
+#[derive(Debug, Deserialize)]
+struct LinearIssue {
+    identifier: String,
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LinearIssueCreate {
+    success: bool,
+    issue: Option<LinearIssue>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LinearData {
+    issue_create: LinearIssueCreate,
+}
+
+#[derive(Debug, Deserialize)]
+struct LinearResponse {
+    data: Option<LinearData>,
+    errors: Option<Vec<serde_json::Value>>,
+}
+
+pub async fn create_issue(config: &Config, shareable: &Shareable) -> Result<Ticket, Error> {
+    let (api_key, team_id) = match (&config.linear_api_key, &config.linear_team_id) {
+        (Some(api_key), Some(team_id)) => (api_key, team_id),
+        _ => return Err(Error::NotConfigured),
+    };
+
+    let resp = http::client()
+        .post("https://api.linear.app/graphql")
+        .header("Authorization", api_key)
+        .json(&json!({
+            "query": ISSUE_CREATE,
+            "variables": {
+                "input": {
+                    "teamId": team_id,
+                    "title": format!("[{}] {}", shareable.source, shareable.title),
+                    "description": description(shareable),
+                }
+            }
+        }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<LinearResponse>()
+        .await?;
+
+    match resp.data.map(|data| data.issue_create) {
+        Some(LinearIssueCreate {
+            success: true,
+            issue: Some(issue),
+        }) => {
+            info!(
+                "Created Linear issue {} for {}",
+                issue.identifier, shareable.id
+            );
+            Ok(Ticket {
+                key: issue.identifier,
+                url: issue.url,
+            })
+        }
+        _ => Err(Error::Upstream(format!(
+            "Linear did not create the issue: {:?}",
+            resp.errors
+        ))),
+    }
+}
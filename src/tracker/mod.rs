@@ -0,0 +1,63 @@
+pub mod github;
+pub mod jira;
+pub mod linear;
+
+use serde::Serialize;
+
+use crate::fetcher::base::Shareable;
+use crate::Config;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Ticket {
+    pub key: String,
+    pub url: String,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    NotConfigured,
+    Upstream(String),
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Upstream(format!("{}", e))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Tracker {
+    GitHub,
+    Jira,
+    Linear,
+}
+
+impl Tracker {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tracker::GitHub => "GitHub",
+            Tracker::Jira => "Jira",
+            Tracker::Linear => "Linear",
+        }
+    }
+
+    pub async fn create_issue(
+        &self,
+        config: &Config,
+        shareable: &Shareable,
+    ) -> Result<Ticket, Error> {
+        match self {
+            Tracker::GitHub => github::create_issue(config, shareable).await,
+            Tracker::Jira => jira::create_issue(config, shareable).await,
+            Tracker::Linear => linear::create_issue(config, shareable).await,
+        }
+    }
+}
+
+// Body text shared by all trackers, so a ticket always links back to the mention.
+fn description(shareable: &Shareable) -> String {
+    format!(
+        "Found on {} ({})\n\n{}\n\n{}",
+        shareable.source, shareable.date, shareable.title, shareable.url
+    )
+}
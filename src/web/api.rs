@@ -0,0 +1,985 @@
+use axum::{
+    body::StreamBody,
+    extract::{Extension, Path, Query},
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{self, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Json,
+};
+use chrono::{DateTime, Utc};
+use futures_util::stream::{self, Stream, StreamExt};
+use mysql::prelude::*;
+use mysql::*;
+use serde::Deserialize;
+use serde_json::json;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use tokio_util::io::ReaderStream;
+use tracing::error;
+
+use crate::build_info;
+use crate::bus::{self, Event};
+use crate::config;
+use crate::events::{self, EventType};
+use crate::export::{self, jobs};
+use crate::fetcher::base::Shareable;
+use crate::health;
+use crate::maintenance;
+use crate::notifier::outbox;
+use crate::notifier::webpush::{self, Subscription};
+use crate::readlater;
+use crate::reshare;
+use crate::share;
+use crate::shutdown;
+use crate::snooze::{self, Snooze};
+use crate::storage::{self, load_shareable, Deadline, Filter, SearchQuery};
+use crate::tracker::{self, Ticket, Tracker};
+use crate::webhook;
+use crate::Config;
+
+fn error_response(status: StatusCode, message: String) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+fn store_ticket(pool: &Pool, shareable: &Shareable, ticket: &Ticket) -> mysql::Result<()> {
+    let mut conn = pool.get_conn()?;
+    conn.exec_drop(
+        "UPDATE shareables SET issue_key = :issue_key, issue_url = :issue_url WHERE id = :id",
+        params! {
+            "issue_key" => ticket.key.clone(),
+            "issue_url" => ticket.url.clone(),
+            "id" => shareable.id.clone(),
+        },
+    )?;
+    events::record(&mut conn, EventType::Responded, shareable)
+}
+
+fn load_ticket(pool: &Pool, id: &str) -> mysql::Result<Option<Ticket>> {
+    let row: Option<(Option<String>, Option<String>)> = pool.get_conn()?.exec_first(
+        "SELECT issue_key, issue_url FROM shareables WHERE id = :id",
+        params! { "id" => id },
+    )?;
+    Ok(match row {
+        Some((Some(key), Some(url))) => Some(Ticket { key, url }),
+        _ => None,
+    })
+}
+
+// Items a ticket is being created for, a second request meanwhile would not
+// see it yet and create another one.
+static CREATING: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+struct CreatingGuard(String);
+
+impl CreatingGuard {
+    fn acquire(id: &str) -> Option<CreatingGuard> {
+        let mut creating = CREATING.lock().unwrap();
+        if creating.iter().any(|other| other == id) {
+            return None;
+        }
+        creating.push(id.to_string());
+        Some(CreatingGuard(id.to_string()))
+    }
+}
+
+impl Drop for CreatingGuard {
+    fn drop(&mut self) {
+        CREATING.lock().unwrap().retain(|other| other != &self.0);
+    }
+}
+
+// Items get one ticket, in whichever tracker was first. Asking again returns
+// that one with 200 instead of 201.
+async fn create_ticket(
+    tracker: Tracker,
+    config: &Config,
+    pool: &Pool,
+    id: &str,
+) -> Result<(StatusCode, Ticket), Response> {
+    let shareable = match load_shareable(pool, id) {
+        Ok(Some(shareable)) => shareable,
+        Ok(None) => {
+            return Err(error_response(
+                StatusCode::NOT_FOUND,
+                format!("No shareable with id {}", id),
+            ))
+        }
+        Err(e) => {
+            error!("Error loading shareable {}: {}", id, e);
+            return Err(error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("{}", e),
+            ));
+        }
+    };
+
+    let _creating = match CreatingGuard::acquire(id) {
+        Some(guard) => guard,
+        None => {
+            return Err(error_response(
+                StatusCode::CONFLICT,
+                format!("A ticket for {} is being created", id),
+            ))
+        }
+    };
+    match load_ticket(pool, id) {
+        Ok(Some(ticket)) => return Ok((StatusCode::OK, ticket)),
+        Ok(None) => {}
+        Err(e) => {
+            error!("Error loading the ticket of {}: {}", id, e);
+            return Err(error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("{}", e),
+            ));
+        }
+    }
+
+    let ticket = match tracker.create_issue(config, &shareable).await {
+        Ok(ticket) => ticket,
+        Err(tracker::Error::NotConfigured) => {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                format!("{} is not configured", tracker.name()),
+            ))
+        }
+        Err(tracker::Error::Upstream(e)) => {
+            error!(
+                "Could not create {} issue for {}: {}",
+                tracker.name(),
+                id,
+                e
+            );
+            return Err(error_response(StatusCode::BAD_GATEWAY, e));
+        }
+    };
+
+    if let Err(e) = store_ticket(pool, &shareable, &ticket) {
+        error!("Error storing {} issue for {}: {}", tracker.name(), id, e);
+        return Err(error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("{}", e),
+        ));
+    }
+
+    Ok((StatusCode::CREATED, ticket))
+}
+
+#[tracing::instrument(skip(config, pool))]
+pub async fn create_jira_issue(
+    Path(id): Path<String>,
+    Extension(config): Extension<Config>,
+    Extension(pool): Extension<Arc<Pool>>,
+) -> Response {
+    match create_ticket(Tracker::Jira, &config, &pool, &id).await {
+        Ok((status, ticket)) => (status, Json(ticket)).into_response(),
+        Err(response) => response,
+    }
+}
+
+#[tracing::instrument(skip(config, pool))]
+pub async fn create_linear_issue(
+    Path(id): Path<String>,
+    Extension(config): Extension<Config>,
+    Extension(pool): Extension<Arc<Pool>>,
+) -> Response {
+    match create_ticket(Tracker::Linear, &config, &pool, &id).await {
+        Ok((status, ticket)) => (status, Json(ticket)).into_response(),
+        Err(response) => response,
+    }
+}
+
+#[tracing::instrument(skip(config, pool))]
+pub async fn create_github_issue(
+    Path(id): Path<String>,
+    Extension(config): Extension<Config>,
+    Extension(pool): Extension<Arc<Pool>>,
+) -> Response {
+    match create_ticket(Tracker::GitHub, &config, &pool, &id).await {
+        Ok((status, ticket)) => (status, Json(ticket)).into_response(),
+        Err(response) => response,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReshareRequest {
+    text: String,
+    target: reshare::Target,
+    scheduled_at: Option<DateTime<Utc>>,
+}
+
+#[tracing::instrument(skip(config, pool))]
+pub async fn create_reshare(
+    Path(id): Path<String>,
+    Extension(config): Extension<Config>,
+    Extension(pool): Extension<Arc<Pool>>,
+    Json(request): Json<ReshareRequest>,
+) -> Response {
+    if !request.target.is_configured(&config) {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!("{:?} resharing is not configured", request.target),
+        );
+    }
+
+    let shareable = match load_shareable(&pool, &id) {
+        Ok(Some(shareable)) => shareable,
+        Ok(None) => {
+            return error_response(
+                StatusCode::NOT_FOUND,
+                format!("No shareable with id {}", id),
+            )
+        }
+        Err(e) => {
+            error!("Error loading shareable {}: {}", id, e);
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e));
+        }
+    };
+
+    let scheduled_at = request.scheduled_at.unwrap_or_else(Utc::now);
+    let queued = reshare::enqueue(&pool, &id, &request.text, request.target, scheduled_at)
+        .and_then(|reshare_id| {
+            let mut conn = pool.get_conn()?;
+            events::record(&mut conn, EventType::Responded, &shareable)?;
+            Ok(reshare_id)
+        });
+    match queued {
+        Ok(reshare_id) => (
+            StatusCode::CREATED,
+            Json(json!({ "id": reshare_id, "scheduled_at": scheduled_at })),
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Error queueing reshare for {}: {}", id, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+        }
+    }
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn list_reshares(Extension(pool): Extension<Arc<Pool>>) -> Response {
+    match reshare::pending(&pool) {
+        Ok(reshares) => Json(reshares).into_response(),
+        Err(e) => {
+            error!("Error loading reshares: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+        }
+    }
+}
+
+fn set_starred(pool: &Pool, id: &str, starred: bool) -> mysql::Result<()> {
+    let mut conn = pool.get_conn()?;
+    conn.exec_drop(
+        if starred {
+            "UPDATE shareables SET starred_at = UTC_TIMESTAMP() WHERE id = :id"
+        } else {
+            "UPDATE shareables SET starred_at = NULL WHERE id = :id"
+        },
+        params! { "id" => id },
+    )
+}
+
+// Newest first, narrowed by `source`, `since`, `until` (RFC 3339) and `q`.
+#[tracing::instrument(skip(pool, deadline))]
+pub async fn list_shareables(
+    Query(filter): Query<Filter>,
+    Extension(pool): Extension<Arc<Pool>>,
+    Extension(deadline): Extension<Deadline>,
+) -> Response {
+    match storage::list_items(&pool, deadline, &filter) {
+        Ok(mut items) => {
+            items.sort_by(|a, b| b.cmp(a));
+            Json(json!({ "items": items })).into_response()
+        }
+        Err(e) => {
+            error!("Error listing shareables: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+        }
+    }
+}
+
+// Full-text search over titles, best matches first.
+#[tracing::instrument(skip(pool, deadline))]
+pub async fn search(
+    Query(query): Query<SearchQuery>,
+    Extension(pool): Extension<Arc<Pool>>,
+    Extension(deadline): Extension<Deadline>,
+) -> Response {
+    if query.q.trim().is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, String::from("q is required"));
+    }
+    match storage::search_items(&pool, deadline, &query.q) {
+        Ok(items) => Json(json!({ "items": items })).into_response(),
+        Err(e) => {
+            error!("Error searching for {}: {}", query.q, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StreamQuery {
+    source: Option<String>,
+}
+
+// The next batch of new items, None once the stream should end.
+async fn next_inserted(events: &mut bus::Subscription) -> Option<Vec<Shareable>> {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event? {
+                Event::ItemsInserted { shareables, .. } => return Some(shareables),
+                Event::SourceFailed { .. } => {}
+            },
+            // Open streams would otherwise hold up the graceful shutdown,
+            // clients reconnect to the next instance.
+            _ = shutdown::requested() => return None,
+        }
+    }
+}
+
+// Server-Sent Events, one `shareable` event per newly stored item, optionally
+// of one `source` only. Only items stored after connecting are sent.
+pub async fn stream(
+    Query(query): Query<StreamQuery>,
+) -> Sse<impl Stream<Item = Result<sse::Event, Infallible>>> {
+    let events = bus::subscribe("api-stream");
+    let batches = stream::unfold(events, |mut events| async move {
+        let shareables = next_inserted(&mut events).await?;
+        Some((shareables, events))
+    });
+    let source = query.source;
+    let items = batches.flat_map(move |shareables| {
+        let events: Vec<Result<sse::Event, Infallible>> = shareables
+            .into_iter()
+            .filter(|shareable| {
+                source.is_none() || source.as_deref() == Some(shareable.source.as_str())
+            })
+            .map(|shareable| {
+                Ok(sse::Event::default()
+                    .event("shareable")
+                    .id(shareable.id.clone())
+                    .json_data(&shareable)
+                    // Shareables always serialize.
+                    .unwrap())
+            })
+            .collect();
+        stream::iter(events)
+    });
+    Sse::new(items).keep_alive(KeepAlive::default())
+}
+
+// The item with its provenance, for "why is this here?" questions.
+pub async fn get_shareable(
+    Path(id): Path<String>,
+    Extension(pool): Extension<Arc<Pool>>,
+) -> Response {
+    let shareable = match load_shareable(&pool, &id) {
+        Ok(Some(shareable)) => shareable,
+        Ok(None) => {
+            return error_response(
+                StatusCode::NOT_FOUND,
+                format!("No shareable with id {}", id),
+            )
+        }
+        Err(e) => {
+            error!("Error loading shareable {}: {}", id, e);
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e));
+        }
+    };
+
+    match storage::load_provenance(&pool, &id) {
+        Ok(provenance) => {
+            let mut item = json!(shareable);
+            item["provenance"] = json!(provenance);
+            Json(item).into_response()
+        }
+        Err(e) => {
+            error!("Error loading provenance of {}: {}", id, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+        }
+    }
+}
+
+// Starring also hands the item to the configured read-later services.
+#[tracing::instrument(skip(config, pool))]
+pub async fn star_shareable(
+    Path(id): Path<String>,
+    Extension(config): Extension<Config>,
+    Extension(pool): Extension<Arc<Pool>>,
+) -> Response {
+    let shareable = match load_shareable(&pool, &id) {
+        Ok(Some(shareable)) => shareable,
+        Ok(None) => {
+            return error_response(
+                StatusCode::NOT_FOUND,
+                format!("No shareable with id {}", id),
+            )
+        }
+        Err(e) => {
+            error!("Error loading shareable {}: {}", id, e);
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e));
+        }
+    };
+
+    // Saved first, so a failed save doesn't leave a star behind and a retry
+    // does both again.
+    if let Err(e) = readlater::save(&config, &shareable).await {
+        error!("Could not save {} to read-later: {}", id, e);
+        return error_response(StatusCode::BAD_GATEWAY, e);
+    }
+
+    match set_starred(&pool, &id, true) {
+        Ok(_) => Json(json!({ "starred": true })).into_response(),
+        Err(e) => {
+            error!("Error starring {}: {}", id, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+        }
+    }
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn unstar_shareable(
+    Path(id): Path<String>,
+    Extension(pool): Extension<Arc<Pool>>,
+) -> Response {
+    match set_starred(&pool, &id, false) {
+        Ok(_) => Json(json!({ "starred": false })).into_response(),
+        Err(e) => {
+            error!("Error unstarring {}: {}", id, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+        }
+    }
+}
+
+fn set_read(pool: &Pool, id: &str, read: bool) -> mysql::Result<()> {
+    let mut conn = pool.get_conn()?;
+    conn.exec_drop(
+        if read {
+            "UPDATE shareables SET read_at = UTC_TIMESTAMP() WHERE id = :id"
+        } else {
+            "UPDATE shareables SET read_at = NULL WHERE id = :id"
+        },
+        params! { "id" => id },
+    )
+}
+
+// Triaged items stay in the list, `?unread=true` leaves them out.
+#[tracing::instrument(skip(pool))]
+pub async fn read_shareable(
+    Path(id): Path<String>,
+    Extension(pool): Extension<Arc<Pool>>,
+) -> Response {
+    match set_read(&pool, &id, true) {
+        Ok(_) => Json(json!({ "read": true })).into_response(),
+        Err(e) => {
+            error!("Error marking {} as read: {}", id, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+        }
+    }
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn unread_shareable(
+    Path(id): Path<String>,
+    Extension(pool): Extension<Arc<Pool>>,
+) -> Response {
+    match set_read(&pool, &id, false) {
+        Ok(_) => Json(json!({ "read": false })).into_response(),
+        Err(e) => {
+            error!("Error marking {} as unread: {}", id, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+        }
+    }
+}
+
+// Everything, or what the same `source`, `since`, `until` and `q` as the list
+// show.
+#[tracing::instrument(skip(pool))]
+pub async fn mark_all_read(
+    Query(filter): Query<Filter>,
+    Extension(pool): Extension<Arc<Pool>>,
+) -> Response {
+    match storage::mark_read(&pool, &filter) {
+        Ok(marked) => Json(json!({ "marked": marked })).into_response(),
+        Err(e) => {
+            error!("Error marking items as read: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    // Comma separated shareable ids, defaults to all starred items.
+    ids: Option<String>,
+}
+
+impl ExportQuery {
+    fn ids(&self) -> Vec<String> {
+        self.ids
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .filter(|id| !id.is_empty())
+            .map(String::from)
+            .collect()
+    }
+}
+
+fn export_selection_error(e: mysql::Error) -> Response {
+    error!("Error loading export selection: {}", e);
+    error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn export_markdown(
+    Query(query): Query<ExportQuery>,
+    Extension(pool): Extension<Arc<Pool>>,
+) -> Response {
+    match export::load_selection(&pool, &query.ids()) {
+        Ok(shareables) => (
+            [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+            export::markdown::digest(&shareables),
+        )
+            .into_response(),
+        Err(e) => export_selection_error(e),
+    }
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn export_markdown_zip(
+    Query(query): Query<ExportQuery>,
+    Extension(pool): Extension<Arc<Pool>>,
+) -> Response {
+    let shareables = match export::load_selection(&pool, &query.ids()) {
+        Ok(shareables) => shareables,
+        Err(e) => return export_selection_error(e),
+    };
+
+    match export::markdown::zip(&shareables) {
+        Ok(bytes) => (
+            [
+                (header::CONTENT_TYPE, "application/zip"),
+                (
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"keyword-notifier.zip\"",
+                ),
+            ],
+            bytes,
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Error building markdown zip: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+        }
+    }
+}
+
+#[tracing::instrument(skip(config, pool))]
+pub async fn sync_markdown(
+    Query(query): Query<ExportQuery>,
+    Extension(config): Extension<Config>,
+    Extension(pool): Extension<Arc<Pool>>,
+) -> Response {
+    let dir = match &config.markdown_export_dir {
+        Some(dir) => dir,
+        None => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                String::from("MARKDOWN_EXPORT_DIR is not configured"),
+            )
+        }
+    };
+    let shareables = match export::load_selection(&pool, &query.ids()) {
+        Ok(shareables) => shareables,
+        Err(e) => return export_selection_error(e),
+    };
+
+    match export::markdown::sync_to_dir(std::path::Path::new(dir), &shareables) {
+        Ok(written) => Json(json!({ "written": written, "dir": dir })).into_response(),
+        Err(e) => {
+            error!("Error writing markdown to {}: {}", dir, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+        }
+    }
+}
+
+fn export_dir_missing() -> Response {
+    error_response(
+        StatusCode::BAD_REQUEST,
+        String::from("EXPORT_DIR is not configured"),
+    )
+}
+
+fn export_json(job: &jobs::Job) -> serde_json::Value {
+    let download =
+        (job.status == jobs::Status::Done).then(|| format!("/api/exports/{}/download", job.id));
+    json!({
+        "job": job,
+        "progress": job.progress(),
+        "download": download,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShareRequest {
+    #[serde(flatten)]
+    filter: Filter,
+    // A week from now if not given.
+    expires_at: Option<DateTime<Utc>>,
+}
+
+// A link to a read-only view of the filtered items that works until it
+// expires, e.g. last week's mentions for someone without dashboard access.
+#[tracing::instrument(skip(config))]
+pub async fn create_share_link(
+    Extension(config): Extension<Config>,
+    headers: HeaderMap,
+    Json(request): Json<ShareRequest>,
+) -> Response {
+    let secret = match &config.share_secret {
+        Some(secret) => secret,
+        None => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                String::from("SHARE_SECRET is not configured"),
+            )
+        }
+    };
+    let now = Utc::now();
+    let expires_at = request
+        .expires_at
+        .unwrap_or_else(|| now + chrono::Duration::days(7));
+    if expires_at <= now {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            String::from("expires_at is in the past"),
+        );
+    }
+    let token = share::sign(
+        secret,
+        &share::Link {
+            filter: request.filter,
+            expires_at,
+        },
+    );
+    (
+        StatusCode::CREATED,
+        Json(json!({
+            "url": format!("{}/shared/{}", super::base_url(&config, &headers), token),
+            "expires_at": expires_at,
+        })),
+    )
+        .into_response()
+}
+
+// Queues a CSV export, the worker writes it to EXPORT_DIR.
+#[tracing::instrument(skip(config, pool))]
+pub async fn create_export(
+    Extension(config): Extension<Config>,
+    Extension(pool): Extension<Arc<Pool>>,
+    Json(request): Json<jobs::Request>,
+) -> Response {
+    if config.export_dir.is_none() {
+        return export_dir_missing();
+    }
+    match jobs::create(&pool, &request).and_then(|id| jobs::load(&pool, id)) {
+        Ok(Some(job)) => (StatusCode::ACCEPTED, Json(export_json(&job))).into_response(),
+        Ok(None) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            String::from("Export job vanished"),
+        ),
+        Err(e) => {
+            error!("Error creating export job: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+        }
+    }
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_export(Path(id): Path<u64>, Extension(pool): Extension<Arc<Pool>>) -> Response {
+    match jobs::load(&pool, id) {
+        Ok(Some(job)) => Json(export_json(&job)).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, format!("No export {}", id)),
+        Err(e) => {
+            error!("Error loading export job {}: {}", id, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+        }
+    }
+}
+
+// Streams the finished file instead of loading a year of items into memory.
+#[tracing::instrument(skip(config, pool))]
+pub async fn download_export(
+    Path(id): Path<u64>,
+    Extension(config): Extension<Config>,
+    Extension(pool): Extension<Arc<Pool>>,
+) -> Response {
+    let dir = match &config.export_dir {
+        Some(dir) => std::path::Path::new(dir),
+        None => return export_dir_missing(),
+    };
+    match jobs::load(&pool, id) {
+        Ok(Some(job)) if job.status == jobs::Status::Done => {}
+        Ok(Some(job)) if job.status == jobs::Status::Expired => {
+            return error_response(StatusCode::GONE, format!("Export {} expired", id))
+        }
+        Ok(Some(_)) => {
+            return error_response(StatusCode::CONFLICT, format!("Export {} is not done", id))
+        }
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, format!("No export {}", id)),
+        Err(e) => {
+            error!("Error loading export job {}: {}", id, e);
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e));
+        }
+    }
+
+    let path = jobs::path(dir, id);
+    match tokio::fs::File::open(&path).await {
+        Ok(file) => (
+            [
+                (
+                    header::CONTENT_TYPE,
+                    String::from("text/csv; charset=utf-8"),
+                ),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"keyword-notifier-{}.csv\"", id),
+                ),
+            ],
+            StreamBody::new(ReaderStream::new(file)),
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Error opening {}: {}", path.display(), e);
+            error_response(StatusCode::GONE, format!("Export {} is gone", id))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReplayRequest {
+    since: DateTime<Utc>,
+    url: Option<String>,
+}
+
+// Re-delivers every event since the given time on the next webhook round.
+#[tracing::instrument(skip(pool))]
+pub async fn replay_webhooks(
+    Extension(pool): Extension<Arc<Pool>>,
+    Json(request): Json<ReplayRequest>,
+) -> Response {
+    let since = request.since.format("%Y-%m-%d %H:%M:%S").to_string();
+    match webhook::replay(&pool, &since, request.url.as_deref()) {
+        Ok(endpoints) => {
+            Json(json!({ "endpoints": endpoints, "since": request.since })).into_response()
+        }
+        Err(e) => {
+            error!("Error rewinding webhooks: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+        }
+    }
+}
+
+// Browsers need the public VAPID key to subscribe.
+#[tracing::instrument(skip(config))]
+pub async fn push_key(Extension(config): Extension<Config>) -> Response {
+    match &config.webpush_vapid_public_key {
+        Some(key) => Json(json!({ "public_key": key })).into_response(),
+        None => error_response(
+            StatusCode::NOT_FOUND,
+            String::from("Web Push is not configured"),
+        ),
+    }
+}
+
+#[tracing::instrument(skip(config, pool, subscription))]
+pub async fn push_subscribe(
+    Extension(config): Extension<Config>,
+    Extension(pool): Extension<Arc<Pool>>,
+    Json(subscription): Json<Subscription>,
+) -> Response {
+    if config.webpush_vapid_private_key.is_none() {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            String::from("Web Push is not configured"),
+        );
+    }
+    if let Err(e) = webpush::validate(&subscription).await {
+        return error_response(StatusCode::BAD_REQUEST, e);
+    }
+    match webpush::subscribe(&pool, &subscription) {
+        Ok(_) => (StatusCode::CREATED, Json(json!({ "subscribed": true }))).into_response(),
+        Err(e) => {
+            error!("Error storing push subscription: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UnsubscribeRequest {
+    endpoint: String,
+}
+
+#[tracing::instrument(skip(pool, request))]
+pub async fn push_unsubscribe(
+    Extension(pool): Extension<Arc<Pool>>,
+    Json(request): Json<UnsubscribeRequest>,
+) -> Response {
+    match webpush::unsubscribe(&pool, &request.endpoint) {
+        Ok(_) => Json(json!({ "subscribed": false })).into_response(),
+        Err(e) => {
+            error!("Error removing push subscription: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+        }
+    }
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn stats(
+    Extension(pool): Extension<Arc<Pool>>,
+    Extension(deadline): Extension<Deadline>,
+) -> Response {
+    match storage::stats(&pool, deadline) {
+        Ok(stats) => Json(stats).into_response(),
+        Err(e) => {
+            error!("Error loading stats: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+        }
+    }
+}
+
+// Which build is running, to tell which one produced which data.
+pub async fn version() -> Response {
+    Json(build_info::get()).into_response()
+}
+
+#[tracing::instrument(skip(config))]
+pub async fn admin_config(Extension(config): Extension<Config>) -> Response {
+    Json(config::describe(&config)).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MaintenanceRequest {
+    enabled: bool,
+}
+
+pub async fn get_maintenance() -> Response {
+    Json(json!({ "maintenance": maintenance::is_active() })).into_response()
+}
+
+#[tracing::instrument]
+pub async fn set_maintenance(Json(request): Json<MaintenanceRequest>) -> Response {
+    maintenance::set(request.enabled);
+    Json(json!({ "maintenance": request.enabled })).into_response()
+}
+
+// Sources and keywords whose new items don't notify anyone for now.
+#[tracing::instrument(skip(pool))]
+pub async fn list_snoozes(Extension(pool): Extension<Arc<Pool>>) -> Response {
+    match snooze::active(&pool) {
+        Ok(snoozes) => Json(json!({ "snoozes": snoozes })).into_response(),
+        Err(e) => {
+            error!("Error loading snoozes: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+        }
+    }
+}
+
+// `{"kind": "source", "name": "twitter", "until": "2022-06-08T00:00:00Z"}`,
+// or `"kind": "keyword"` to match part of the title.
+#[tracing::instrument(skip(pool))]
+pub async fn set_snooze(
+    Extension(pool): Extension<Arc<Pool>>,
+    Json(snooze): Json<Snooze>,
+) -> Response {
+    if snooze.name.trim().is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, String::from("name is required"));
+    }
+    if snooze.until <= Utc::now() {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            String::from("until is in the past"),
+        );
+    }
+    match snooze::set(&pool, &snooze) {
+        Ok(_) => Json(json!(snooze)).into_response(),
+        Err(e) => {
+            error!(
+                "Error snoozing {} {}: {}",
+                snooze.kind.as_str(),
+                snooze.name,
+                e
+            );
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+        }
+    }
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn remove_snooze(
+    Path((kind, name)): Path<(String, String)>,
+    Extension(pool): Extension<Arc<Pool>>,
+) -> Response {
+    let kind = match snooze::Kind::parse(&kind) {
+        Some(kind) => kind,
+        None => {
+            return error_response(
+                StatusCode::NOT_FOUND,
+                format!("Unknown kind {}, use source or keyword", kind),
+            )
+        }
+    };
+    match snooze::remove(&pool, kind, &name) {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => {
+            error!("Error removing snooze of {}: {}", name, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+        }
+    }
+}
+
+// Alive as long as the process answers, it doesn't touch the database and
+// stays up during maintenance.
+pub async fn healthz() -> Response {
+    Json(json!({ "alive": true })).into_response()
+}
+
+// Ready once the database answers. Not ready anymore once shutdown started,
+// so load balancers send new requests elsewhere. The boot check of the source
+// credentials is only reported, it runs once and another instance would not
+// fetch any better.
+#[tracing::instrument(skip(pool))]
+pub async fn readyz(Extension(pool): Extension<Arc<Pool>>) -> Response {
+    let database = match pool
+        .get_conn()
+        .and_then(|mut conn| conn.query_drop("SELECT 1"))
+    {
+        Ok(_) => None,
+        Err(e) => Some(format!("{}", e)),
+    };
+    let credentials = health::credential_checks();
+    let shutting_down = shutdown::is_requested();
+    // Informational, failed notifications don't make the service unready.
+    let notifications = outbox::counts(&pool).ok();
+    let ready = !shutting_down && database.is_none();
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(json!({
+            "ready": ready,
+            "shutting_down": shutting_down,
+            "database_error": database,
+            "credentials": credentials,
+            "notifications": notifications,
+            "maintenance": maintenance::is_active(),
+        })),
+    )
+        .into_response()
+}
@@ -0,0 +1,94 @@
+use axum::{
+    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    response::Response,
+};
+use serde_json::json;
+
+use crate::bus::{self, Event};
+use crate::shutdown;
+
+// What the browser is sent for an event, as JSON text messages:
+// `{"type": "shareable", "shareable": {...}}` for every new item and
+// `{"type": "source_failed", "fetcher": "...", "kind": "..."}` for failed runs.
+fn messages(event: Event) -> Vec<String> {
+    match event {
+        Event::ItemsInserted { shareables, .. } => shareables
+            .into_iter()
+            .map(|shareable| json!({ "type": "shareable", "shareable": shareable }).to_string())
+            .collect(),
+        Event::SourceFailed { fetcher, kind } => vec![json!({
+            "type": "source_failed",
+            "fetcher": fetcher,
+            "kind": kind.as_str(),
+        })
+        .to_string()],
+    }
+}
+
+async fn forward(mut socket: WebSocket) {
+    let mut events = bus::subscribe("websocket");
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => match event {
+                Some(event) => event,
+                None => break,
+            },
+            // Only read to notice the browser going away, pings are answered
+            // by the socket itself.
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => continue,
+            },
+            // Open sockets would otherwise hold up the graceful shutdown.
+            _ = shutdown::requested() => break,
+        };
+        for message in messages(event) {
+            if socket.send(Message::Text(message)).await.is_err() {
+                return;
+            }
+        }
+    }
+    let _ = socket.close().await;
+}
+
+// New items and failed fetcher runs as they happen, for the dashboard.
+pub async fn websocket(upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(forward)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetcher::base::Shareable;
+    use crate::fetcher::error::ErrorKind;
+
+    #[test]
+    fn sends_one_message_per_item() {
+        let shareable = |id: &str| Shareable {
+            id: String::from(id),
+            title: String::from("cdktf is great"),
+            date: String::from("2022-06-01T12:00:00Z"),
+            url: format!("https://twitter.com/twitter/status/{}", id),
+            source: String::from("twitter"),
+            canonical_url: None,
+        };
+        let sent = messages(Event::ItemsInserted {
+            fetcher: String::from("twitter"),
+            shareables: vec![shareable("1"), shareable("2")],
+        });
+        assert_eq!(sent.len(), 2);
+        let first: serde_json::Value = serde_json::from_str(&sent[0]).unwrap();
+        assert_eq!(first["type"], "shareable");
+        assert_eq!(first["shareable"]["id"], "1");
+
+        let sent = messages(Event::SourceFailed {
+            fetcher: "twitter",
+            kind: ErrorKind::RateLimit,
+        });
+        let failed: serde_json::Value = serde_json::from_str(&sent[0]).unwrap();
+        assert_eq!(
+            failed,
+            json!({ "type": "source_failed", "fetcher": "twitter", "kind": "rate_limit" })
+        );
+    }
+}
@@ -0,0 +1,823 @@
+mod api;
+mod live;
+mod oembed;
+pub mod overrides;
+pub mod timeouts;
+
+use askama::Template;
+use axum::{
+    error_handling::HandleErrorLayer,
+    extract::{Extension, Path, Query},
+    http::{header, HeaderMap, Request, StatusCode, Uri},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use chrono::Utc;
+use mysql::*;
+use serde::Serialize;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tower::{BoxError, ServiceBuilder};
+use tower_http::{add_extension::AddExtensionLayer, trace::TraceLayer};
+use tracing::{error, info};
+
+use crate::feed::{self, Feed, Format};
+use crate::fetcher::base::{self, Shareable};
+use crate::health::{self, CredentialCheck};
+use crate::maintenance;
+use crate::reshare;
+use crate::share;
+use crate::snooze::{self, Snooze};
+use crate::source::{self, Icon};
+use crate::storage::{self, Deadline, Filter, Item, SearchQuery};
+use crate::Config;
+use overrides::Page;
+use timeouts::Timeouts;
+
+// Gives every request the time left until its group's timeout as its query
+// budget.
+async fn deadline<B>(timeout: Duration, mut request: Request<B>, next: Next<B>) -> Response {
+    request.extensions_mut().insert(Deadline::after(timeout));
+    next.run(request).await
+}
+
+async fn handle_timeout(error: BoxError) -> Result<StatusCode, (StatusCode, String)> {
+    if error.is::<tower::timeout::error::Elapsed>() {
+        Ok(StatusCode::REQUEST_TIMEOUT)
+    } else {
+        Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Unhandled internal error: {}", error),
+        ))
+    }
+}
+
+// During maintenance the dashboard shows a friendly page and the API answers
+// 503. Admin routes and health checks are not wrapped and keep working.
+async fn maintenance_page<B>(request: Request<B>, next: Next<B>) -> Response {
+    if !maintenance::is_active() {
+        return next.run(request).await;
+    }
+    let status = StatusCode::SERVICE_UNAVAILABLE;
+    let retry_after = [(header::RETRY_AFTER, "300")];
+    if request.uri().path().starts_with("/api/") {
+        let error = json!({ "error": "down for maintenance" });
+        (status, retry_after, Json(error)).into_response()
+    } else {
+        (status, retry_after, HtmlTemplate(MaintenanceTemplate)).into_response()
+    }
+}
+
+fn with_timeout(routes: Router, timeout: Duration) -> Router {
+    routes.layer(
+        ServiceBuilder::new()
+            .layer(HandleErrorLayer::new(handle_timeout))
+            .timeout(timeout)
+            .layer(middleware::from_fn(move |request, next| {
+                deadline(timeout, request, next)
+            }))
+            .into_inner(),
+    )
+}
+
+pub fn router(config: Config, pool: Arc<Pool>) -> Router {
+    // validated in Config::from_env
+    let timeouts = Timeouts::parse(&config.route_timeouts).unwrap();
+
+    let ui = Router::new()
+        .route("/", get(root))
+        .route("/sw.js", get(service_worker))
+        .route("/go/:id", get(go))
+        .route("/item/:id", get(shareable))
+        .route("/search", get(search))
+        .route("/shared/:token", get(shared))
+        .route("/status", get(status))
+        .route("/ws", get(live::websocket))
+        .route("/static/icons/:name", get(icon));
+    let api = Router::new()
+        .route("/api/shareables", get(api::list_shareables))
+        .route("/api/shareables/:id", get(api::get_shareable))
+        .route("/api/shareables/:id/github", post(api::create_github_issue))
+        .route("/api/shareables/:id/jira", post(api::create_jira_issue))
+        .route("/api/shareables/:id/linear", post(api::create_linear_issue))
+        .route("/api/shareables/:id/reshare", post(api::create_reshare))
+        .route(
+            "/api/shareables/:id/star",
+            post(api::star_shareable).delete(api::unstar_shareable),
+        )
+        .route("/api/shareables/read", post(api::mark_all_read))
+        .route(
+            "/api/shareables/:id/read",
+            post(api::read_shareable).delete(api::unread_shareable),
+        )
+        .route("/api/search", get(api::search))
+        .route("/api/stream", get(api::stream))
+        .route("/oembed", get(oembed::oembed))
+        .route("/api/stats", get(api::stats))
+        .route("/api/version", get(api::version))
+        .route("/api/push/key", get(api::push_key))
+        .route(
+            "/api/push/subscribe",
+            post(api::push_subscribe).delete(api::push_unsubscribe),
+        )
+        .route("/api/reshares", get(api::list_reshares))
+        .route("/api/export/markdown", get(api::export_markdown))
+        .route("/api/export/markdown.zip", get(api::export_markdown_zip))
+        .route("/api/exports", post(api::create_export))
+        .route("/api/exports/:id", get(api::get_export))
+        .route("/api/exports/:id/download", get(api::download_export));
+    let admin = Router::new()
+        .route("/admin/config", get(api::admin_config))
+        .route(
+            "/admin/maintenance",
+            get(api::get_maintenance).put(api::set_maintenance),
+        )
+        .route("/admin/share-links", post(api::create_share_link))
+        .route(
+            "/admin/snoozes",
+            get(api::list_snoozes).put(api::set_snooze),
+        )
+        .route("/admin/snoozes/:kind/:name", delete(api::remove_snooze))
+        .route("/api/webhooks/replay", post(api::replay_webhooks))
+        .route("/api/export/markdown/sync", post(api::sync_markdown));
+    let feeds = Router::new()
+        .route("/feed.xml", get(feed))
+        .route("/feed.atom", get(feed))
+        .route("/feed.json", get(feed))
+        .route("/feed/:file", get(source_feed));
+    let health = Router::new()
+        .route("/healthz", get(api::healthz))
+        .route("/readyz", get(api::readyz));
+
+    let maintenance_page = || middleware::from_fn(maintenance_page);
+    with_timeout(ui.layer(maintenance_page()), timeouts.ui)
+        .merge(with_timeout(api.layer(maintenance_page()), timeouts.api))
+        .merge(with_timeout(admin, timeouts.admin))
+        .merge(with_timeout(
+            feeds.layer(maintenance_page()),
+            timeouts.feeds,
+        ))
+        .merge(with_timeout(health, timeouts.health))
+        .layer(
+            ServiceBuilder::new()
+                .layer(TraceLayer::new_for_http())
+                .layer(AddExtensionLayer::new(config))
+                .layer(AddExtensionLayer::new(pool))
+                .into_inner(),
+        )
+}
+
+#[derive(Template)]
+#[template(path = "base.html")]
+struct BaseTemplate {
+    title: String,
+}
+
+#[derive(Template, Serialize)]
+#[template(path = "index.html")]
+struct IndexTemplate {
+    items: Vec<Item>,
+    github_issues_enabled: bool,
+    jira_enabled: bool,
+    linear_enabled: bool,
+    reshare_targets: Vec<&'static str>,
+    title_max_length: usize,
+    push_enabled: bool,
+    // What the search box shows, empty outside of search results.
+    search: String,
+    // Behind a share link, without search and item actions.
+    read_only: bool,
+}
+
+#[derive(Template, Serialize)]
+#[template(path = "status.html")]
+struct StatusTemplate {
+    stats: storage::Stats,
+    // None while the boot check is still running.
+    credentials: Option<Vec<CredentialCheck>>,
+    snoozes: Vec<Snooze>,
+}
+
+#[derive(Template, Serialize)]
+#[template(path = "shareable.html")]
+struct ShareableTemplate {
+    shareable: Shareable,
+    provenance: Option<storage::Provenance>,
+    // For the unfurl tags, absolute like the URLs chat apps see.
+    base_url: String,
+}
+
+#[derive(Template, Serialize)]
+#[template(path = "error.html")]
+struct ErrorTemplate {
+    message: String,
+}
+
+#[derive(Template, Serialize)]
+#[template(path = "maintenance.html")]
+struct MaintenanceTemplate;
+
+struct HtmlTemplate<T>(T);
+
+impl<T> IntoResponse for HtmlTemplate<T>
+where
+    T: Page,
+{
+    fn into_response(self) -> Response {
+        let html = match overrides::render(&self.0) {
+            Some(html) => html,
+            None => self.0.render().map_err(|e| e.to_string()),
+        };
+        match html {
+            Ok(html) => Html(html).into_response(),
+            Err(err) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to render template. Error: {}", err),
+            )
+                .into_response(),
+        }
+    }
+}
+
+fn index_page(
+    config: &Config,
+    items: mysql::Result<Vec<Item>>,
+    search: String,
+    by_date: bool,
+    read_only: bool,
+) -> Response {
+    match items {
+        Ok(items) => {
+            info!("Fetched {} items", items.len());
+            base::log_sample("Items", &items);
+
+            let mut sanitized_items = items
+                .into_iter()
+                .map(|item| Item {
+                    shareable: Shareable {
+                        title: item.shareable.display_title(),
+                        ..item.shareable
+                    },
+                    ..item
+                })
+                .filter(|item| !item.shareable.title.contains("[Dependency Updated]"))
+                .collect::<Vec<Item>>();
+
+            if by_date {
+                sanitized_items.sort_by(|a, b| b.cmp(a));
+            }
+
+            HtmlTemplate(IndexTemplate {
+                items: sanitized_items,
+                github_issues_enabled: !read_only && config.github_issue_repo.is_some(),
+                jira_enabled: !read_only && config.jira_base_url.is_some(),
+                linear_enabled: !read_only && config.linear_api_key.is_some(),
+                reshare_targets: [
+                    (reshare::Target::Mastodon, "mastodon"),
+                    (reshare::Target::Bluesky, "bluesky"),
+                ]
+                .into_iter()
+                .filter(|(target, _)| !read_only && target.is_configured(config))
+                .map(|(_, name)| name)
+                .collect(),
+                title_max_length: config.title_max_length,
+                push_enabled: !read_only && config.webpush_vapid_private_key.is_some(),
+                search,
+                read_only,
+            })
+            .into_response()
+        }
+        Err(e) => {
+            error!("Error loading data: {}", e);
+            HtmlTemplate(ErrorTemplate {
+                message: format!("{}", e),
+            })
+            .into_response()
+        }
+    }
+}
+
+#[tracing::instrument]
+async fn root(
+    Extension(config): Extension<Config>,
+    Extension(pool): Extension<Arc<Pool>>,
+    Extension(deadline): Extension<Deadline>,
+    Query(filter): Query<Filter>,
+) -> Response {
+    let items = storage::list_items(&pool, deadline, &filter);
+    index_page(&config, items, String::new(), true, false)
+}
+
+// The search box, same results as `/api/search` in order of relevance.
+#[tracing::instrument]
+async fn search(
+    Extension(config): Extension<Config>,
+    Extension(pool): Extension<Arc<Pool>>,
+    Extension(deadline): Extension<Deadline>,
+    Query(query): Query<SearchQuery>,
+) -> Response {
+    if query.q.trim().is_empty() {
+        return Redirect::to("/").into_response();
+    }
+    let items = storage::search_items(&pool, deadline, &query.q);
+    index_page(&config, items, query.q, false, false)
+}
+
+// The items of a share link, see `POST /admin/share-links`.
+#[tracing::instrument(skip(config, pool, deadline))]
+async fn shared(
+    Path(token): Path<String>,
+    Extension(config): Extension<Config>,
+    Extension(pool): Extension<Arc<Pool>>,
+    Extension(deadline): Extension<Deadline>,
+) -> Response {
+    let verified = match &config.share_secret {
+        Some(secret) => share::verify(secret, &token, Utc::now()),
+        None => Err(share::Invalid::Forged),
+    };
+    match verified {
+        Ok(link) => {
+            let items = storage::list_items(&pool, deadline, &link.filter);
+            index_page(&config, items, String::new(), true, true)
+        }
+        Err(share::Invalid::Expired) => (
+            StatusCode::GONE,
+            HtmlTemplate(ErrorTemplate {
+                message: String::from("This link has expired."),
+            }),
+        )
+            .into_response(),
+        Err(share::Invalid::Forged) => (
+            StatusCode::NOT_FOUND,
+            HtmlTemplate(ErrorTemplate {
+                message: String::from("This link is not valid."),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+#[tracing::instrument(skip(pool))]
+async fn status(
+    Extension(pool): Extension<Arc<Pool>>,
+    Extension(deadline): Extension<Deadline>,
+) -> impl IntoResponse {
+    match storage::stats(&pool, deadline).and_then(|stats| Ok((stats, snooze::active(&pool)?))) {
+        Ok((stats, snoozes)) => HtmlTemplate(StatusTemplate {
+            stats,
+            credentials: health::credential_checks(),
+            snoozes,
+        })
+        .into_response(),
+        Err(e) => {
+            error!("Error loading stats: {}", e);
+            HtmlTemplate(ErrorTemplate {
+                message: format!("{}", e),
+            })
+            .into_response()
+        }
+    }
+}
+
+// Redirects to the canonical URL of an item, the list keeps showing the link
+// as the source had it.
+async fn go(Path(id): Path<String>, Extension(pool): Extension<Arc<Pool>>) -> Response {
+    match storage::load_shareable(&pool, &id) {
+        Ok(Some(shareable)) => Redirect::temporary(shareable.link()).into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            error!("Error loading shareable {}: {}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+// A permalink for one item, e.g. for notifications, with where it came from.
+#[tracing::instrument(skip(config, pool, headers))]
+async fn shareable(
+    Path(id): Path<String>,
+    Extension(config): Extension<Config>,
+    Extension(pool): Extension<Arc<Pool>>,
+    headers: HeaderMap,
+) -> Response {
+    let loaded = storage::load_shareable(&pool, &id).and_then(|shareable| {
+        Ok(match shareable {
+            Some(shareable) => Some((shareable, storage::load_provenance(&pool, &id)?)),
+            None => None,
+        })
+    });
+    match loaded {
+        Ok(Some((shareable, provenance))) => HtmlTemplate(ShareableTemplate {
+            shareable: Shareable {
+                title: shareable.display_title(),
+                ..shareable
+            },
+            provenance,
+            base_url: base_url(&config, &headers),
+        })
+        .into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            HtmlTemplate(ErrorTemplate {
+                message: format!("No item with id {}", id),
+            }),
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Error loading shareable {}: {}", id, e);
+            HtmlTemplate(ErrorTemplate {
+                message: format!("{}", e),
+            })
+            .into_response()
+        }
+    }
+}
+
+// PUBLIC_URL, or the host the request was sent to.
+fn base_url(config: &Config, headers: &HeaderMap) -> String {
+    match &config.public_url {
+        Some(url) => url.trim_end_matches('/').to_string(),
+        None => format!(
+            "http://{}",
+            headers
+                .get(header::HOST)
+                .and_then(|host| host.to_str().ok())
+                .unwrap_or("localhost")
+        ),
+    }
+}
+
+// `<name>.<extension>` split into name and feed format.
+fn feed_file(file: &str) -> Option<(&str, Format)> {
+    let (name, extension) = file.rsplit_once('.')?;
+    Some((name, Format::from_extension(extension)?))
+}
+
+fn feed_response(
+    config: &Config,
+    pool: &Pool,
+    deadline: Deadline,
+    headers: &HeaderMap,
+    path: &str,
+    source: Option<&str>,
+    format: Format,
+) -> Response {
+    let filter = Filter {
+        source: source.map(String::from),
+        ..Filter::default()
+    };
+    match storage::list_items(pool, deadline, &filter) {
+        Ok(mut items) => {
+            items.sort_by(|a, b| b.cmp(a));
+            let shareables: Vec<Shareable> = items
+                .into_iter()
+                .take(feed::LENGTH)
+                .map(|item| item.shareable)
+                .collect();
+            let base_url = base_url(config, headers);
+            let feed = Feed {
+                keyword: &config.keyword,
+                source,
+                home_url: format!("{}/", base_url),
+                feed_url: format!("{}{}", base_url, path),
+                shareables: &shareables,
+            };
+            (
+                [(header::CONTENT_TYPE, format.content_type())],
+                feed.render(format),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            error!("Error loading feed items: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+// `/feed.xml`, `/feed.atom` or `/feed.json`
+async fn feed(
+    uri: Uri,
+    Extension(config): Extension<Config>,
+    Extension(pool): Extension<Arc<Pool>>,
+    Extension(deadline): Extension<Deadline>,
+    headers: HeaderMap,
+) -> Response {
+    match feed_file(uri.path()) {
+        Some((_, format)) => {
+            feed_response(&config, &pool, deadline, &headers, uri.path(), None, format)
+        }
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+// `/feed/<source>.xml`, `.atom` or `.json`
+async fn source_feed(
+    uri: Uri,
+    Path(file): Path<String>,
+    Extension(config): Extension<Config>,
+    Extension(pool): Extension<Arc<Pool>>,
+    Extension(deadline): Extension<Deadline>,
+    headers: HeaderMap,
+) -> Response {
+    match feed_file(&file) {
+        Some((source, format)) => feed_response(
+            &config,
+            &pool,
+            deadline,
+            &headers,
+            uri.path(),
+            Some(source),
+            format,
+        ),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+// Served from the root, a service worker only controls pages below its own
+// path.
+async fn service_worker() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/javascript")],
+        include_str!("../../static/sw.js"),
+    )
+}
+
+const ICON_CACHE_CONTROL: &str = "public, max-age=86400";
+
+async fn icon(Path(name): Path<String>) -> Response {
+    let icon = match source::icon(&name) {
+        Some(icon) => icon,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let content_type = icon.content_type();
+    match icon {
+        Icon::Bundled(svg) => (
+            [
+                (header::CONTENT_TYPE, content_type),
+                (header::CACHE_CONTROL, ICON_CACHE_CONTROL),
+            ],
+            svg,
+        )
+            .into_response(),
+        Icon::File(path) => match tokio::fs::read(&path).await {
+            Ok(bytes) => (
+                [
+                    (header::CONTENT_TYPE, content_type),
+                    (header::CACHE_CONTROL, ICON_CACHE_CONTROL),
+                ],
+                bytes,
+            )
+                .into_response(),
+            Err(e) => {
+                error!("Could not read icon {}: {}", path.display(), e);
+                StatusCode::NOT_FOUND.into_response()
+            }
+        },
+    }
+}
+
+// Golden files for the templates, review changes with `cargo insta review`.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetcher::error::ErrorCount;
+
+    fn item(id: &str, title: &str, source: &str) -> Item {
+        Item {
+            shareable: Shareable {
+                id: String::from(id),
+                title: String::from(title),
+                date: String::from("2022-06-01T12:00:00Z"),
+                url: format!("https://example.com/{}", id),
+                source: String::from(source),
+                canonical_url: None,
+            },
+            issue_key: None,
+            issue_url: None,
+            starred: false,
+            read: false,
+        }
+    }
+
+    fn items() -> Vec<Item> {
+        vec![
+            item("stackoverflow-1", "How do I use cdktf?", "stackoverflow"),
+            Item {
+                issue_key: Some(String::from("CDKTF-42")),
+                issue_url: Some(String::from("https://jira.example.com/CDKTF-42")),
+                starred: true,
+                ..item("twitter-2", "cdktf & <b>friends</b> 🚀", "twitter")
+            },
+            Item {
+                read: true,
+                ..item("slack-3", "#general @me: ✅ cdktf deployed", "slack")
+            },
+        ]
+    }
+
+    #[test]
+    fn renders_index() {
+        let html = IndexTemplate {
+            items: items(),
+            github_issues_enabled: true,
+            jira_enabled: false,
+            linear_enabled: true,
+            reshare_targets: vec!["mastodon"],
+            title_max_length: 140,
+            push_enabled: true,
+            search: String::from("\"provider\" <aws>"),
+            read_only: false,
+        }
+        .render()
+        .unwrap();
+        insta::assert_snapshot!(html);
+    }
+
+    #[test]
+    fn renders_empty_index() {
+        let html = IndexTemplate {
+            items: vec![],
+            github_issues_enabled: false,
+            jira_enabled: false,
+            linear_enabled: false,
+            reshare_targets: vec![],
+            title_max_length: 140,
+            push_enabled: false,
+            search: String::new(),
+            read_only: false,
+        }
+        .render()
+        .unwrap();
+        insta::assert_snapshot!(html);
+    }
+
+    #[test]
+    fn escapes_titles() {
+        let html = IndexTemplate {
+            items: vec![item(
+                "mastodon-5",
+                "@eve: <script>alert(1)</script>",
+                "mastodon",
+            )],
+            github_issues_enabled: false,
+            jira_enabled: false,
+            linear_enabled: false,
+            reshare_targets: vec![],
+            title_max_length: 140,
+            push_enabled: false,
+            search: String::new(),
+            read_only: false,
+        }
+        .render()
+        .unwrap();
+        assert!(!html.contains("<script>alert(1)"));
+        assert!(html.contains("&lt;script&gt;alert(1)"));
+    }
+
+    #[test]
+    fn renders_truncated_titles() {
+        let html = IndexTemplate {
+            items: vec![item(
+                "twitter-4",
+                "cdktf 👩‍💻👩‍💻👩‍💻 is great for multi-cloud deployments",
+                "twitter",
+            )],
+            github_issues_enabled: false,
+            jira_enabled: false,
+            linear_enabled: false,
+            reshare_targets: vec![],
+            title_max_length: 8,
+            push_enabled: false,
+            search: String::new(),
+            read_only: false,
+        }
+        .render()
+        .unwrap();
+        insta::assert_snapshot!(html);
+    }
+
+    #[test]
+    fn renders_shared_index() {
+        let html = IndexTemplate {
+            items: items(),
+            github_issues_enabled: false,
+            jira_enabled: false,
+            linear_enabled: false,
+            reshare_targets: vec![],
+            title_max_length: 140,
+            push_enabled: false,
+            search: String::new(),
+            read_only: true,
+        }
+        .render()
+        .unwrap();
+        insta::assert_snapshot!(html);
+    }
+
+    #[test]
+    fn renders_shareable() {
+        let html = ShareableTemplate {
+            shareable: Shareable {
+                canonical_url: Some(String::from("https://example.com/twitter-2")),
+                url: String::from("https://example.com/twitter-2?utm_source=feed"),
+                ..item("twitter-2", "cdktf & <b>friends</b> 🚀", "twitter").shareable
+            },
+            provenance: Some(storage::Provenance {
+                fetcher: Some(String::from("twitter")),
+                keyword: Some(String::from("cdktf")),
+                version: Some(String::from("0.1.0")),
+                ingested_at: Some(String::from("2022-06-01T12:05:00Z")),
+            }),
+            base_url: String::from("https://news.example.com"),
+        }
+        .render()
+        .unwrap();
+        insta::assert_snapshot!(html);
+    }
+
+    #[test]
+    fn renders_error() {
+        let html = ErrorTemplate {
+            message: String::from("Access denied for user <root>"),
+        }
+        .render()
+        .unwrap();
+        insta::assert_snapshot!(html);
+    }
+
+    #[test]
+    fn renders_maintenance() {
+        insta::assert_snapshot!(MaintenanceTemplate.render().unwrap());
+    }
+
+    #[test]
+    fn renders_status() {
+        let html = StatusTemplate {
+            stats: storage::Stats {
+                total: 3,
+                by_source: vec![
+                    (String::from("slack"), 1),
+                    (String::from("stackoverflow"), 2),
+                ],
+                latest_date: Some(String::from("2022-06-01T12:00:00Z")),
+                skipped_runs: 4,
+                fetch_errors: vec![
+                    ErrorCount {
+                        fetcher: String::from("GitHub issues"),
+                        kind: "rate_limit",
+                        count: 2,
+                    },
+                    ErrorCount {
+                        fetcher: String::from("Slack messages"),
+                        kind: "auth",
+                        count: 1,
+                    },
+                ],
+            },
+            credentials: Some(vec![
+                CredentialCheck {
+                    source: String::from("github"),
+                    ok: true,
+                    message: String::from("authenticated"),
+                    checked_at: String::from("2022-06-01T12:00:00Z"),
+                },
+                CredentialCheck {
+                    source: String::from("slack"),
+                    ok: false,
+                    message: String::from("invalid_auth"),
+                    checked_at: String::from("2022-06-01T12:00:00Z"),
+                },
+            ]),
+            snoozes: vec![Snooze {
+                kind: snooze::Kind::Keyword,
+                name: String::from("#hashiconf"),
+                until: "2022-06-08T00:00:00Z".parse().unwrap(),
+            }],
+        }
+        .render()
+        .unwrap();
+        insta::assert_snapshot!(html);
+    }
+
+    #[test]
+    fn renders_status_while_checking() {
+        let html = StatusTemplate {
+            stats: storage::Stats {
+                total: 0,
+                by_source: vec![],
+                latest_date: None,
+                skipped_runs: 0,
+                fetch_errors: vec![],
+            },
+            credentials: None,
+            snoozes: vec![],
+        }
+        .render()
+        .unwrap();
+        insta::assert_snapshot!(html);
+    }
+}
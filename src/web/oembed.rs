@@ -0,0 +1,101 @@
+use axum::{
+    extract::{Extension, Query},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use mysql::Pool;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use tracing::error;
+
+use super::base_url;
+use crate::storage;
+use crate::Config;
+
+// Bundled icons are square SVGs, oEmbed wants a size for the thumbnail.
+const THUMBNAIL_SIZE: u32 = 64;
+
+#[derive(Debug, Deserialize)]
+pub struct OembedQuery {
+    url: String,
+    format: Option<String>,
+}
+
+// The item id of one of our own `/item/<id>` pages.
+fn item_id<'a>(base_url: &str, url: &'a str) -> Option<&'a str> {
+    let id = url.strip_prefix(base_url)?.strip_prefix("/item/")?;
+    let id = id.split(['?', '#']).next().unwrap_or_default();
+    if id.is_empty() || id.contains('/') {
+        return None;
+    }
+    Some(id)
+}
+
+// oEmbed for item pages, so chat apps unfurl pasted links with the title,
+// source icon and date. The pages link here for discovery.
+#[tracing::instrument(skip(config, pool, headers))]
+pub async fn oembed(
+    Query(query): Query<OembedQuery>,
+    Extension(config): Extension<Config>,
+    Extension(pool): Extension<Arc<Pool>>,
+    headers: HeaderMap,
+) -> Response {
+    // Required by the spec for formats we don't speak.
+    if query.format.as_deref().unwrap_or("json") != "json" {
+        return StatusCode::NOT_IMPLEMENTED.into_response();
+    }
+    let base_url = base_url(&config, &headers);
+    let id = match item_id(&base_url, &query.url) {
+        Some(id) => id,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+    match storage::load_shareable(&pool, id) {
+        Ok(Some(shareable)) => Json(json!({
+            "version": "1.0",
+            "type": "link",
+            "title": shareable.display_title(),
+            "author_name": format!("{} · {}", shareable.source, shareable.date),
+            "provider_name": format!("{} mentions", config.keyword),
+            "provider_url": format!("{}/", base_url),
+            "thumbnail_url": format!("{}{}", base_url, shareable.icon_path()),
+            "thumbnail_width": THUMBNAIL_SIZE,
+            "thumbnail_height": THUMBNAIL_SIZE,
+        }))
+        .into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            error!("Error loading shareable {}: {}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_only_own_item_pages() {
+        let base = "https://news.example.com";
+        assert_eq!(
+            item_id(base, "https://news.example.com/item/twitter-2"),
+            Some("twitter-2")
+        );
+        assert_eq!(
+            item_id(
+                base,
+                "https://news.example.com/item/twitter-2?ref=slack#top"
+            ),
+            Some("twitter-2")
+        );
+        assert_eq!(
+            item_id(base, "https://evil.example.com/item/twitter-2"),
+            None
+        );
+        assert_eq!(item_id(base, "https://news.example.com/go/twitter-2"), None);
+        assert_eq!(item_id(base, "https://news.example.com/item/"), None);
+        assert_eq!(item_id(base, "https://news.example.com/item/a/b"), None);
+    }
+}
@@ -0,0 +1,247 @@
+use askama::Template;
+use minijinja::Environment;
+use serde::Serialize;
+use std::sync::OnceLock;
+use tracing::warn;
+
+use super::{ErrorTemplate, IndexTemplate, MaintenanceTemplate, ShareableTemplate, StatusTemplate};
+use crate::fetcher::base::Shareable;
+use crate::fetcher::error::ErrorCount;
+use crate::health::CredentialCheck;
+use crate::snooze::{self, Snooze};
+use crate::storage::{Item, Provenance, Stats};
+use crate::templating;
+
+// A page `<TEMPLATES_DIR>/<NAME>` replaces. The override is a minijinja
+// template that gets the fields of the page as its context.
+pub trait Page: Template + Serialize {
+    const NAME: &'static str;
+
+    // Every field filled in, the override has to render it on startup.
+    fn sample() -> Self;
+}
+
+fn sample_shareable() -> Shareable {
+    Shareable {
+        id: String::from("twitter-1"),
+        title: String::from("cdktf is great"),
+        date: String::from("2022-06-01T12:00:00Z"),
+        url: String::from("https://twitter.com/twitter/status/1"),
+        source: String::from("twitter"),
+        canonical_url: Some(String::from("https://twitter.com/twitter/status/1")),
+    }
+}
+
+impl Page for IndexTemplate {
+    const NAME: &'static str = "index.html";
+
+    fn sample() -> Self {
+        IndexTemplate {
+            items: vec![Item {
+                shareable: sample_shareable(),
+                issue_key: Some(String::from("NEWS-1")),
+                issue_url: Some(String::from("https://example.atlassian.net/browse/NEWS-1")),
+                starred: true,
+                read: false,
+            }],
+            github_issues_enabled: true,
+            jira_enabled: true,
+            linear_enabled: true,
+            reshare_targets: vec!["mastodon"],
+            title_max_length: 100,
+            push_enabled: true,
+            search: String::from("cdktf"),
+            read_only: false,
+        }
+    }
+}
+
+impl Page for StatusTemplate {
+    const NAME: &'static str = "status.html";
+
+    fn sample() -> Self {
+        StatusTemplate {
+            stats: Stats {
+                total: 1,
+                by_source: vec![(String::from("twitter"), 1)],
+                latest_date: Some(String::from("2022-06-01T12:00:00Z")),
+                skipped_runs: 0,
+                fetch_errors: vec![ErrorCount {
+                    fetcher: String::from("twitter"),
+                    kind: "rate_limit",
+                    count: 1,
+                }],
+            },
+            credentials: Some(vec![CredentialCheck {
+                source: String::from("twitter"),
+                ok: true,
+                message: String::from("ok"),
+                checked_at: String::from("2022-06-01T12:00:00Z"),
+            }]),
+            snoozes: vec![Snooze {
+                kind: snooze::Kind::Source,
+                name: String::from("twitter"),
+                until: "2022-06-08T00:00:00Z".parse().unwrap(),
+            }],
+        }
+    }
+}
+
+impl Page for ShareableTemplate {
+    const NAME: &'static str = "shareable.html";
+
+    fn sample() -> Self {
+        ShareableTemplate {
+            shareable: sample_shareable(),
+            provenance: Some(Provenance {
+                fetcher: Some(String::from("twitter")),
+                keyword: Some(String::from("cdktf")),
+                version: Some(String::from("0.1.0")),
+                ingested_at: Some(String::from("2022-06-01T12:00:00Z")),
+            }),
+            base_url: String::from("https://news.example.com"),
+        }
+    }
+}
+
+impl Page for ErrorTemplate {
+    const NAME: &'static str = "error.html";
+
+    fn sample() -> Self {
+        ErrorTemplate {
+            message: String::from("Something went wrong"),
+        }
+    }
+}
+
+impl Page for MaintenanceTemplate {
+    const NAME: &'static str = "maintenance.html";
+
+    fn sample() -> Self {
+        MaintenanceTemplate
+    }
+}
+
+const PAGES: [&str; 5] = [
+    IndexTemplate::NAME,
+    StatusTemplate::NAME,
+    ShareableTemplate::NAME,
+    ErrorTemplate::NAME,
+    MaintenanceTemplate::NAME,
+];
+
+// The page from its override, None without one.
+fn render_with<P: Page>(env: &Environment<'_>, page: &P) -> Option<Result<String, String>> {
+    env.get_template(P::NAME).ok()?;
+    Some(templating::render(env, P::NAME, page))
+}
+
+fn check_page<P: Page>(env: &Environment<'_>) -> Result<(), String> {
+    match render_with(env, &P::sample()) {
+        Some(Err(e)) => Err(format!("{}: {}", P::NAME, e)),
+        _ => Ok(()),
+    }
+}
+
+// Renders every overridden page with a sample, so an override that uses
+// fields the page does not have fails on startup instead of on a request.
+fn check(env: &Environment<'_>, names: &[String]) -> Result<(), String> {
+    if !names.iter().any(|name| PAGES.contains(&name.as_str())) {
+        return Err(format!(
+            "none of the pages {} is overridden, other templates are only used by them",
+            PAGES.join(", ")
+        ));
+    }
+    check_page::<IndexTemplate>(env)?;
+    check_page::<StatusTemplate>(env)?;
+    check_page::<ShareableTemplate>(env)?;
+    check_page::<ErrorTemplate>(env)?;
+    check_page::<MaintenanceTemplate>(env)
+}
+
+// All `.html` files in TEMPLATES_DIR. The ones named after a page replace it,
+// the others are there for them to extend or include, e.g. a `base.html`.
+// None without any, pages then keep the built-in templates.
+pub fn load(dir: &str) -> Result<Option<Environment<'static>>, String> {
+    let mut env = templating::environment();
+    let names =
+        templating::load(&mut env, dir, "html").map_err(|e| format!("TEMPLATES_DIR: {}", e))?;
+    if names.is_empty() {
+        return Ok(None);
+    }
+    check(&env, &names).map_err(|e| format!("TEMPLATES_DIR: {}", e))?;
+    Ok(Some(env))
+}
+
+static OVERRIDES: OnceLock<Environment<'static>> = OnceLock::new();
+
+pub fn configure(env: Environment<'static>) {
+    if OVERRIDES.set(env).is_err() {
+        warn!("Template overrides already configured, keeping the first ones");
+    }
+}
+
+// None if the page is not overridden, it then renders from the built-in one.
+pub fn render<P: Page>(page: &P) -> Option<Result<String, String>> {
+    render_with(OVERRIDES.get()?, page)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(
+        templates: &[(&'static str, &'static str)],
+    ) -> (Environment<'static>, Vec<String>) {
+        let mut env = templating::environment();
+        for (name, source) in templates {
+            env.add_template(name, source).unwrap();
+        }
+        let names = templates.iter().map(|(name, _)| name.to_string()).collect();
+        (env, names)
+    }
+
+    #[test]
+    fn renders_overridden_pages() {
+        let (env, names) = overrides(&[
+            (
+                "base.html",
+                "<title>{% block title %}{% endblock %} | News</title><main>{% block content %}{% endblock %}</main>",
+            ),
+            (
+                "error.html",
+                "{% extends \"base.html\" %}{% block title %}Oops{% endblock %}{% block content %}<p>{{ message }}</p>{% endblock %}",
+            ),
+        ]);
+        assert!(check(&env, &names).is_ok());
+        let page = ErrorTemplate {
+            message: String::from("<script>"),
+        };
+        assert_eq!(
+            render_with(&env, &page).unwrap().unwrap(),
+            "<title>Oops | News</title><main><p>&lt;script&gt;</p></main>"
+        );
+        // Pages without an override keep the built-in template.
+        assert!(render_with(&env, &MaintenanceTemplate).is_none());
+    }
+
+    #[test]
+    fn rejects_overrides_that_do_not_fit() {
+        let (env, names) = overrides(&[(
+            "index.html",
+            "{% for item in items %}{{ item.titel }}{% endfor %}",
+        )]);
+        let error = check(&env, &names).unwrap_err();
+        assert!(error.starts_with("index.html: "), "{}", error);
+
+        let (env, names) = overrides(&[(
+            "index.html",
+            "{% for item in items %}<a href=\"{{ item.url }}\">{{ item.title }}</a>{% endfor %}",
+        )]);
+        assert!(check(&env, &names).is_ok());
+
+        // A layout alone would silently change nothing.
+        let (env, names) = overrides(&[("base.html", "{% block content %}{% endblock %}")]);
+        assert!(check(&env, &names).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+// How long a request of each route group may take before it is answered with
+// 408. Health checks should fail fast, admin actions like replaying webhooks
+// or syncing exports get longer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    pub ui: Duration,
+    pub api: Duration,
+    pub admin: Duration,
+    pub feeds: Duration,
+    pub health: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Timeouts {
+        Timeouts {
+            ui: Duration::from_secs(5),
+            api: Duration::from_secs(5),
+            admin: Duration::from_secs(60),
+            feeds: Duration::from_secs(10),
+            health: Duration::from_secs(2),
+        }
+    }
+}
+
+// `500ms`, `5s` or `2m`
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let (number, unit) = value.split_at(
+        value
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(value.len()),
+    );
+    let number: u64 = number
+        .parse()
+        .ok()
+        .filter(|number| *number > 0)
+        .ok_or_else(|| format!("{}: expected a positive number with a unit", value))?;
+    match unit {
+        "ms" => Ok(Duration::from_millis(number)),
+        "s" => Ok(Duration::from_secs(number)),
+        "m" => Ok(Duration::from_secs(number * 60)),
+        other => Err(format!("{}: unknown unit {}, use ms, s or m", value, other)),
+    }
+}
+
+impl Timeouts {
+    // `group=duration` entries set one group, a bare duration all of them
+    // that are not set by name.
+    pub fn parse(entries: &[String]) -> Result<Timeouts, String> {
+        let mut timeouts = Timeouts::default();
+        let (named, bare): (Vec<&String>, Vec<&String>) =
+            entries.iter().partition(|entry| entry.contains('='));
+        for entry in bare {
+            let timeout = parse_duration(entry)?;
+            timeouts = Timeouts {
+                ui: timeout,
+                api: timeout,
+                admin: timeout,
+                feeds: timeout,
+                health: timeout,
+            };
+        }
+        for entry in named {
+            // contains a `=`, see above
+            let (group, value) = entry.split_once('=').unwrap();
+            let timeout = parse_duration(value)?;
+            match group.trim() {
+                "ui" => timeouts.ui = timeout,
+                "api" => timeouts.api = timeout,
+                "admin" => timeouts.admin = timeout,
+                "feeds" => timeouts.feeds = timeout,
+                "health" => timeouts.health = timeout,
+                other => {
+                    return Err(format!(
+                        "unknown route group {}, use ui, api, admin, feeds or health",
+                        other
+                    ))
+                }
+            }
+        }
+        Ok(timeouts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(entries: &[&str]) -> Vec<String> {
+        entries.iter().map(|entry| entry.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_groups() {
+        let timeouts = Timeouts::parse(&entries(&["api=30s", "health=500ms"])).unwrap();
+        assert_eq!(timeouts.api, Duration::from_secs(30));
+        assert_eq!(timeouts.health, Duration::from_millis(500));
+        assert_eq!(timeouts.ui, Timeouts::default().ui);
+
+        let timeouts = Timeouts::parse(&entries(&["admin=2m", "10s"])).unwrap();
+        assert_eq!(timeouts.admin, Duration::from_secs(120));
+        assert_eq!(timeouts.feeds, Duration::from_secs(10));
+        assert_eq!(timeouts.health, Duration::from_secs(10));
+    }
+
+    #[test]
+    fn rejects_bad_entries() {
+        assert!(Timeouts::parse(&entries(&["exports=30s"])).is_err());
+        assert!(Timeouts::parse(&entries(&["api=30"])).is_err());
+        assert!(Timeouts::parse(&entries(&["api=0s"])).is_err());
+        assert!(Timeouts::parse(&entries(&["api=-1s"])).is_err());
+    }
+}
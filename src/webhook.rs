@@ -0,0 +1,203 @@
+use mysql::prelude::*;
+use mysql::*;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::{task, time};
+use tracing::{debug, error, info};
+
+use crate::http;
+use crate::maintenance;
+use crate::shutdown;
+use crate::Config;
+
+const BATCH_SIZE: usize = 100;
+
+#[derive(Debug, Clone)]
+struct ItemEvent {
+    id: u64,
+    event_type: String,
+    shareable_id: String,
+    payload: String,
+    occurred_at: String,
+}
+
+// New endpoints start at the end of the log instead of receiving its history.
+fn cursor(conn: &mut PooledConn, url: &str) -> mysql::Result<u64> {
+    conn.exec_drop(
+        r"INSERT IGNORE INTO webhook_cursors (url, last_event_id)
+      SELECT :url, COALESCE(MAX(id), 0) FROM item_events",
+        params! { "url" => url },
+    )?;
+    let cursor: Option<u64> = conn.exec_first(
+        "SELECT last_event_id FROM webhook_cursors WHERE url = :url",
+        params! { "url" => url },
+    )?;
+    Ok(cursor.unwrap_or_default())
+}
+
+fn advance(conn: &mut PooledConn, url: &str, event_id: u64) -> mysql::Result<()> {
+    conn.exec_drop(
+        "UPDATE webhook_cursors SET last_event_id = :event_id WHERE url = :url",
+        params! { "event_id" => event_id, "url" => url },
+    )
+}
+
+fn pending(conn: &mut PooledConn, after: u64) -> mysql::Result<Vec<ItemEvent>> {
+    conn.exec_map(
+        format!(
+            r"SELECT id, event_type, shareable_id, payload,
+            DATE_FORMAT(occurred_at, '%Y-%m-%dT%H:%i:%sZ')
+          FROM item_events WHERE id > :after ORDER BY id LIMIT {}",
+            BATCH_SIZE
+        ),
+        params! { "after" => after },
+        |(id, event_type, shareable_id, payload, occurred_at)| ItemEvent {
+            id,
+            event_type,
+            shareable_id,
+            payload,
+            occurred_at,
+        },
+    )
+}
+
+// Points every endpoint (or just `url`) back to the first event at or after
+// `since`, so the next delivery round sends those events again.
+pub fn replay(pool: &Pool, since: &str, url: Option<&str>) -> mysql::Result<u64> {
+    let mut conn = pool.get_conn()?;
+    conn.exec_drop(
+        r"UPDATE webhook_cursors SET last_event_id = (
+            SELECT COALESCE(MIN(id), (SELECT COALESCE(MAX(id), 0) + 1 FROM item_events)) - 1
+            FROM item_events WHERE occurred_at >= :since
+          )
+          WHERE :url IS NULL OR url = :url",
+        params! { "since" => since, "url" => url },
+    )?;
+    Ok(conn.affected_rows())
+}
+
+// `Name: value` headers from WEBHOOK_HEADERS, sent to every endpoint, the
+// lifecycle ones here and the notifier's.
+pub fn headers(config: &Config) -> Result<HeaderMap, String> {
+    let mut headers = HeaderMap::new();
+    for header in config.webhook_headers.iter() {
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| format!("WEBHOOK_HEADERS: expected Name: value, got {}", header))?;
+        headers.insert(
+            HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|e| format!("WEBHOOK_HEADERS: {}: {}", name, e))?,
+            HeaderValue::from_str(value.trim())
+                .map_err(|e| format!("WEBHOOK_HEADERS: {}: {}", name, e))?,
+        );
+    }
+    Ok(headers)
+}
+
+// POSTs an event to an endpoint once, failures are retried by the caller.
+// Errors leave out the URL, webhook URLs often carry a token.
+pub async fn post(
+    client: &reqwest::Client,
+    headers: &HeaderMap,
+    url: &str,
+    event: &str,
+    body: &Value,
+) -> Result<(), String> {
+    client
+        .post(url)
+        .timeout(Duration::from_secs(10))
+        .headers(headers.clone())
+        .header("X-Keyword-Notifier-Event", event)
+        .json(body)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map(|_| ())
+        .map_err(|e| format!("{}", e.without_url()))
+}
+
+#[tracing::instrument(skip(client, headers, url, event), fields(endpoint = %http::redact(url), event_id = event.id, event = %event.event_type, shareable_id = %event.shareable_id))]
+async fn deliver(
+    client: &reqwest::Client,
+    headers: &HeaderMap,
+    url: &str,
+    event: &ItemEvent,
+) -> Result<(), String> {
+    let shareable: Value = serde_json::from_str(&event.payload).unwrap_or(Value::Null);
+    let body = json!({
+        "id": event.id,
+        "event": event.event_type,
+        "occurred_at": event.occurred_at,
+        "shareable": shareable,
+    });
+    post(client, headers, url, &event.event_type, &body).await
+}
+
+async fn deliver_pending(
+    config: &Config,
+    pool: &Pool,
+    client: &reqwest::Client,
+    headers: &HeaderMap,
+    url: &str,
+) -> mysql::Result<()> {
+    let events = {
+        let mut conn = pool.get_conn()?;
+        let after = cursor(&mut conn, url)?;
+        pending(&mut conn, after)?
+    };
+
+    // No connection is held while sending, a slow endpoint would otherwise
+    // keep one out of the pool for every delivery.
+    for event in events {
+        let wanted =
+            config.webhook_events.is_empty() || config.webhook_events.contains(&event.event_type);
+        if wanted {
+            if let Err(e) = deliver(client, headers, url, &event).await {
+                // Keep the cursor in place so the event is retried in order.
+                error!(
+                    "Could not deliver event {} to {}: {}",
+                    event.id,
+                    http::redact(url),
+                    e
+                );
+                return Ok(());
+            }
+            debug!(
+                "Delivered {} for {} to {}",
+                event.event_type,
+                event.shareable_id,
+                http::redact(url)
+            );
+        }
+        advance(&mut pool.get_conn()?, url, event.id)?;
+    }
+
+    Ok(())
+}
+
+pub fn spawn_dispatcher(config: Config, pool: Arc<Pool>) -> JoinHandle<()> {
+    task::spawn(async move {
+        let client = http::client();
+        // Validated with the config.
+        let headers = headers(&config).unwrap_or_default();
+        let mut interval = time::interval(Duration::from_secs(10));
+        info!(
+            "Delivering webhooks to {} endpoints",
+            config.webhook_urls.len()
+        );
+
+        while shutdown::tick(&mut interval).await {
+            if maintenance::is_active() {
+                continue;
+            }
+            for url in config.webhook_urls.iter() {
+                if let Err(e) = deliver_pending(&config, &pool, &client, &headers, url).await {
+                    error!("Error: {}", e);
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,16 @@
+// Shows the notifications the server pushes, see src/notifier/webpush.rs.
+self.addEventListener("push", function (event) {
+  var data = event.data ? event.data.json() : {};
+  event.waitUntil(
+    self.registration.showNotification(data.title || "New mentions", {
+      body: data.body,
+      icon: data.icon,
+      data: { url: data.url || "/" },
+    })
+  );
+});
+
+self.addEventListener("notificationclick", function (event) {
+  event.notification.close();
+  event.waitUntil(clients.openWindow(event.notification.data.url));
+});